@@ -1,7 +1,10 @@
 use async_trait::async_trait;
-use reqwest::Client;
+use reqwest::{Client, Response};
 use rocust::{
-    rocust_lib::{futures::RocustFutures, run, Context, Shared, Test, TestConfig, User},
+    rocust_lib::{
+        futures::RocustFutures, http::RocustHttpClient, run, Context, Outcome, Shared, StartError,
+        Test, TestConfig, User,
+    },
     rocust_macros::has_task,
 };
 use std::{net::SocketAddr, sync::Arc};
@@ -22,9 +25,18 @@ impl Shared for MyShared {
     }
 }
 
+// success for 2xx, failure for other status codes and error if the request could not be sent
+fn classify(res: &Result<Response, reqwest::Error>) -> Outcome {
+    match res {
+        Ok(res) if res.status().is_success() => Outcome::Success,
+        Ok(_) => Outcome::Failure,
+        Err(error) => Outcome::Error(error.to_string()),
+    }
+}
+
 struct GoogleUser {
     id: u64,
-    client: Client,
+    client: RocustHttpClient,
     host: &'static str,
 }
 
@@ -74,83 +86,34 @@ impl GoogleUser {
 
     #[task(priority = 40)]
     pub async fn index(&mut self, context: &Context) {
-        let (res, elapsed) = self
+        // reported as GET /, the base url is set in GoogleUser::new
+        let res = self
             .client
-            .get(format!("https://{}", self.host))
-            .send()
-            .timed()
+            .get("/")
+            .send(context)
             .delayed(std::time::Duration::from_secs(1))
             .await;
 
+        if let Ok(res) = res {
+            context
+                .check_all(&[
+                    ("status is 200", res.status().as_u16() == 200),
+                    ("body is not empty", res.size() > 0),
+                ])
+                .await;
+
+            context.trend("body_size").add(res.size() as f64).await;
+        }
+
         // println!(
         //     "GoogleUser fetched [https://{}] with delay of 1 second",
         //     self.host
         // );
-
-        match res {
-            Ok(res) => {
-                if res.status().is_success() {
-                    let elapsed = elapsed.as_secs_f64();
-                    context
-                        .add_success(String::from("GET"), format!("{}/", self.host), elapsed)
-                        .await;
-                } else {
-                    context
-                        .add_failure(String::from("GET"), format!("{}/", self.host))
-                        .await;
-                }
-            }
-            Err(_) => {
-                context
-                    .add_error(
-                        String::from("GET"),
-                        format!("{}/", self.host),
-                        String::from("error"),
-                    )
-                    .await;
-            }
-        }
     }
 
     #[task(priority = 40)]
     pub async fn none_existing_path(&mut self, context: &Context) {
-        let (res, elapsed) = self
-            .client
-            .get(format!("https://{}/none_existing_path", self.host))
-            .send()
-            .timed()
-            .await;
-
-        match res {
-            Ok(res) => {
-                if res.status().is_success() {
-                    let elapsed = elapsed.as_secs_f64();
-                    context
-                        .add_success(
-                            String::from("GET"),
-                            format!("{}/none_existing_path", self.host),
-                            elapsed,
-                        )
-                        .await;
-                } else {
-                    context
-                        .add_failure(
-                            String::from("GET"),
-                            format!("{}/none_existing_path", self.host),
-                        )
-                        .await;
-                }
-            }
-            Err(_) => {
-                context
-                    .add_error(
-                        String::from("GET"),
-                        format!("{}/none_existing_path", self.host),
-                        String::from("error"),
-                    )
-                    .await;
-            }
-        }
+        let _res = self.client.get("/none_existing_path").send(context).await;
     }
 
     // #[task(priority = 1)]
@@ -170,17 +133,23 @@ impl GoogleUser {
 impl User for GoogleUser {
     type Shared = ();
 
-    async fn new(_test_config: &TestConfig, context: &Context, _shared: Self::Shared) -> Self {
-        let client = Client::new();
-        GoogleUser {
+    async fn new(
+        _test_config: &TestConfig,
+        context: &Context,
+        _shared: Self::Shared,
+    ) -> Result<Self, StartError> {
+        let client =
+            RocustHttpClient::new(context).with_base_url(String::from("https://google.com"));
+        Ok(GoogleUser {
             id: context.get_id(),
             client,
             host: "google.com",
-        }
+        })
     }
 
-    async fn on_start(&mut self, _: &Context) {
+    async fn on_start(&mut self, _: &Context) -> Result<(), StartError> {
         println!("GoogleUser [{}] started", self.id);
+        Ok(())
     }
 
     async fn on_stop(&mut self, _: &Context) {
@@ -196,42 +165,22 @@ struct FacebookUser {
 impl FacebookUser {
     #[task(priority = 40)]
     pub async fn index(&mut self, context: &Context) {
-        let ((res, elapsed), _polls) = self
-            .client
-            .get(String::from("https://facebook.com"))
-            .send()
-            .timed()
-            .counted()
+        let (_res, _polls) = context
+            .measure(
+                "GET",
+                "facebook.com/",
+                self.client
+                    .get(String::from("https://facebook.com"))
+                    .send()
+                    .counted(),
+                |(res, _polls)| classify(res),
+            )
             .await;
 
         // println!(
         //     "FacebookUser performed {} polls to fetch [https://facebook.com]",
         //     polls
         // );
-
-        match res {
-            Ok(res) => {
-                if res.status().is_success() {
-                    let elapsed = elapsed.as_secs_f64();
-                    context
-                        .add_success(String::from("GET"), String::from("facebook.com/"), elapsed)
-                        .await;
-                } else {
-                    context
-                        .add_failure(String::from("GET"), String::from("facebook.com/"))
-                        .await;
-                }
-            }
-            Err(_) => {
-                context
-                    .add_error(
-                        String::from("GET"),
-                        String::from("facebook.com/"),
-                        String::from("error"),
-                    )
-                    .await;
-            }
-        }
     }
 
     // #[task(priority = 1)]
@@ -245,13 +194,18 @@ impl FacebookUser {
 #[async_trait]
 impl User for FacebookUser {
     type Shared = ();
-    async fn new(_test_config: &TestConfig, _context: &Context, _shared: Self::Shared) -> Self {
+    async fn new(
+        _test_config: &TestConfig,
+        _context: &Context,
+        _shared: Self::Shared,
+    ) -> Result<Self, StartError> {
         let client = Client::new();
-        FacebookUser { client }
+        Ok(FacebookUser { client })
     }
 
-    async fn on_start(&mut self, context: &Context) {
+    async fn on_start(&mut self, context: &Context) -> Result<(), StartError> {
         println!("FacebookUser [{}] started", context.get_id());
+        Ok(())
     }
 
     async fn on_stop(&mut self, context: &Context) {
@@ -279,6 +233,7 @@ async fn main() {
         .prometheus_metrics_history_folder(String::from("results/metrics_history"))
         .server_address(SocketAddr::from(([127, 0, 0, 1], 3000)))
         .precision(3)
+        .check_threshold(String::from("status is 200"), 0.99)
         .additional_args(vec![])
         .additional_arg(String::from("test"));
 
@@ -296,4 +251,11 @@ async fn main() {
     });
 
     run!(test, FacebookUser, GoogleUser).await;
+
+    if !test.check_thresholds_passed().await
+        || !test.endpoint_thresholds_passed().await
+        || test.stop_condition_met().await.is_some()
+    {
+        std::process::exit(1);
+    }
 }
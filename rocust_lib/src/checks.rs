@@ -0,0 +1,239 @@
+use prettytable::{row, Cell, Row, Table};
+use serde::Serialize;
+use std::{collections::HashMap, num::ParseFloatError, str::FromStr};
+use thiserror::Error as ThisError;
+
+const CONSOLE_HEADERS: [&str; 4] = ["CHECK", "PASSES", "FAILS", "PASS RATE"];
+
+// checks do not affect the results, they are only counted
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct CheckStats {
+    passes: u64,
+    fails: u64,
+}
+
+impl CheckStats {
+    fn add(&mut self, passed: bool) {
+        if passed {
+            self.passes += 1;
+        } else {
+            self.fails += 1;
+        }
+    }
+
+    pub fn get_passes(&self) -> u64 {
+        self.passes
+    }
+
+    pub fn get_fails(&self) -> u64 {
+        self.fails
+    }
+
+    // between 0 and 1
+    pub fn get_pass_rate(&self) -> f64 {
+        let total = self.passes + self.fails;
+        if total == 0 {
+            return 0.0;
+        }
+        self.passes as f64 / total as f64
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct SerCheckStats {
+    name: String,
+    passes: u64,
+    fails: u64,
+    pass_rate: f64,
+}
+
+/// The test is considered failed if the pass rate of the check is below `min_pass_rate` or the check was never executed.
+/// Given as `{check_name}={min_pass_rate}` on the command line, e.g. `status is 200=0.99`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CheckThreshold {
+    pub check_name: String,
+    pub min_pass_rate: f64,
+}
+
+#[derive(Debug, ThisError)]
+pub enum CheckThresholdParseError {
+    #[error("Expected {{check_name}}={{min_pass_rate}}")]
+    MissingSeparator,
+    #[error("Invalid min pass rate: {0}")]
+    InvalidPassRate(#[from] ParseFloatError),
+}
+
+impl FromStr for CheckThreshold {
+    type Err = CheckThresholdParseError;
+
+    // the name may contain '=', so split at the last one
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (check_name, min_pass_rate) = s
+            .rsplit_once('=')
+            .ok_or(CheckThresholdParseError::MissingSeparator)?;
+        Ok(Self {
+            check_name: String::from(check_name),
+            min_pass_rate: min_pass_rate.trim().parse()?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CheckThresholdResult {
+    pub check_name: String,
+    pub min_pass_rate: f64,
+    // None if the check was never executed
+    pub pass_rate: Option<f64>,
+    pub passed: bool,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct AllChecks {
+    checks: HashMap<String, CheckStats>,
+}
+
+impl AllChecks {
+    pub(crate) fn add_check(&mut self, name: &str, passed: bool) {
+        // avoid allocating the name for every check
+        match self.checks.get_mut(name) {
+            Some(check_stats) => check_stats.add(passed),
+            None => {
+                let mut check_stats = CheckStats::default();
+                check_stats.add(passed);
+                self.checks.insert(String::from(name), check_stats);
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.checks.is_empty()
+    }
+
+    pub fn get_by_name(&self, name: &str) -> Option<&CheckStats> {
+        self.checks.get(name)
+    }
+
+    pub fn get_checks(&self) -> &HashMap<String, CheckStats> {
+        &self.checks
+    }
+
+    pub fn evaluate_thresholds(&self, thresholds: &[CheckThreshold]) -> Vec<CheckThresholdResult> {
+        thresholds
+            .iter()
+            .map(|threshold| {
+                let pass_rate = self
+                    .get_by_name(&threshold.check_name)
+                    .map(CheckStats::get_pass_rate);
+                CheckThresholdResult {
+                    check_name: threshold.check_name.clone(),
+                    min_pass_rate: threshold.min_pass_rate,
+                    pass_rate,
+                    passed: pass_rate
+                        .map(|pass_rate| pass_rate >= threshold.min_pass_rate)
+                        .unwrap_or(false),
+                }
+            })
+            .collect()
+    }
+
+    // sorted by name, so the table does not jump around
+    fn sorted_checks(&self) -> Vec<(&String, &CheckStats)> {
+        let mut checks: Vec<(&String, &CheckStats)> = self.checks.iter().collect();
+        checks.sort_by_key(|(name, _)| *name);
+        checks
+    }
+
+    pub(crate) fn table_string(&self, precision: usize) -> String {
+        let mut table = Table::new();
+        table.add_row(Row::new(
+            CONSOLE_HEADERS.iter().map(|s| Cell::new(s)).collect(),
+        ));
+        for (name, check_stats) in self.sorted_checks() {
+            table.add_row(row![
+                name,
+                check_stats.passes,
+                check_stats.fails,
+                format!("{:.1$}", check_stats.get_pass_rate(), precision),
+            ]);
+        }
+        table.to_string()
+    }
+
+    pub(crate) fn ser_checks(&self) -> Vec<SerCheckStats> {
+        self.sorted_checks()
+            .into_iter()
+            .map(|(name, check_stats)| SerCheckStats {
+                name: name.clone(),
+                passes: check_stats.passes,
+                fails: check_stats.fails,
+                pass_rate: check_stats.get_pass_rate(),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn threshold_is_parsed_at_the_last_separator() {
+        let check_threshold: CheckThreshold = "status=200 = 0.99".parse().unwrap();
+
+        assert_eq!(check_threshold.check_name, "status=200 ");
+        assert_eq!(check_threshold.min_pass_rate, 0.99);
+        assert!(matches!(
+            "status is 200".parse::<CheckThreshold>(),
+            Err(CheckThresholdParseError::MissingSeparator)
+        ));
+        assert!(matches!(
+            "status is 200=high".parse::<CheckThreshold>(),
+            Err(CheckThresholdParseError::InvalidPassRate(_))
+        ));
+    }
+
+    #[test]
+    fn checks_are_counted_by_name() {
+        let mut all_checks = AllChecks::default();
+        for passed in [true, true, true, false] {
+            all_checks.add_check("status is 200", passed);
+        }
+        all_checks.add_check("body is json", false);
+
+        let status = all_checks.get_by_name("status is 200").unwrap();
+        assert_eq!(status.get_passes(), 3);
+        assert_eq!(status.get_fails(), 1);
+        assert_eq!(status.get_pass_rate(), 0.75);
+        assert_eq!(
+            all_checks
+                .get_by_name("body is json")
+                .unwrap()
+                .get_pass_rate(),
+            0.0
+        );
+        assert_eq!(CheckStats::default().get_pass_rate(), 0.0);
+    }
+
+    #[test]
+    fn thresholds_are_evaluated_against_the_pass_rate() {
+        let mut all_checks = AllChecks::default();
+        for passed in [true, true, true, false] {
+            all_checks.add_check("status is 200", passed);
+        }
+        let thresholds: Vec<CheckThreshold> =
+            ["status is 200=0.75", "status is 200=0.9", "never=0"]
+                .iter()
+                .map(|threshold| threshold.parse().unwrap())
+                .collect();
+
+        let results = all_checks.evaluate_thresholds(&thresholds);
+
+        // the min pass rate is inclusive
+        assert!(results[0].passed);
+        assert_eq!(results[0].pass_rate, Some(0.75));
+        assert!(!results[1].passed);
+        // checks that were never executed fail their threshold
+        assert!(!results[2].passed);
+        assert_eq!(results[2].pass_rate, None);
+    }
+}
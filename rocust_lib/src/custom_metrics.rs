@@ -0,0 +1,745 @@
+use crate::{histogram::ValueHistogram, Context};
+use prettytable::{row, Cell, Row, Table};
+use serde::{ser::SerializeStruct, Serialize};
+use std::{collections::HashMap, time::Duration};
+use thiserror::Error as ThisError;
+
+const CONSOLE_HEADERS: [&str; 3] = ["METRIC", "KIND", "VALUE"];
+
+// trend values are recorded in the percentiles in millionths, see TestConfig::trend_scale
+pub(crate) const DEFAULT_TREND_SCALE: f64 = 1_000_000.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MetricKind {
+    Counter,
+    Gauge,
+    Trend,
+    Rate,
+}
+
+impl MetricKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            MetricKind::Counter => "counter",
+            MetricKind::Gauge => "gauge",
+            MetricKind::Trend => "trend",
+            MetricKind::Rate => "rate",
+        }
+    }
+}
+
+// sent by the users, aggregated in the main loop
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum MetricUpdate {
+    CounterAdd(f64),
+    GaugeSet(f64),
+    GaugeAdd(f64),
+    TrendAdd(f64),
+    RateAdd(bool),
+}
+
+impl MetricUpdate {
+    // the counter delta, rates are counted as 0 or 1
+    pub(crate) fn value(&self) -> f64 {
+        match self {
+            MetricUpdate::CounterAdd(value)
+            | MetricUpdate::GaugeSet(value)
+            | MetricUpdate::GaugeAdd(value)
+            | MetricUpdate::TrendAdd(value) => *value,
+            MetricUpdate::RateAdd(value) => *value as u8 as f64,
+        }
+    }
+
+    fn kind(&self) -> MetricKind {
+        match self {
+            MetricUpdate::CounterAdd(_) => MetricKind::Counter,
+            MetricUpdate::GaugeSet(_) | MetricUpdate::GaugeAdd(_) => MetricKind::Gauge,
+            MetricUpdate::TrendAdd(_) => MetricKind::Trend,
+            MetricUpdate::RateAdd(_) => MetricKind::Rate,
+        }
+    }
+}
+
+#[derive(Debug, ThisError)]
+pub enum MetricUpdateError {
+    #[error("Metric is a {registered:?} and can not be updated as a {given:?}")]
+    KindMismatch {
+        registered: MetricKind,
+        given: MetricKind,
+    },
+    #[error("Trend values must be finite and not negative, got {0}")]
+    InvalidTrendValue(f64),
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct CounterStats {
+    total: f64,
+    per_second: f64,
+}
+
+impl CounterStats {
+    pub fn get_total(&self) -> f64 {
+        self.total
+    }
+
+    pub fn get_per_second(&self) -> f64 {
+        self.per_second
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct GaugeStats {
+    value: f64,
+    min: f64,
+    max: f64,
+}
+
+impl GaugeStats {
+    fn set(&mut self, value: f64, first: bool) {
+        self.value = value;
+        if first || value < self.min {
+            self.min = value;
+        }
+        if first || value > self.max {
+            self.max = value;
+        }
+    }
+
+    pub fn get_value(&self) -> f64 {
+        self.value
+    }
+
+    pub fn get_min(&self) -> f64 {
+        self.min
+    }
+
+    pub fn get_max(&self) -> f64 {
+        self.max
+    }
+}
+
+// a distribution of values, like response times
+#[derive(Debug, Clone, Serialize)]
+pub struct TrendStats {
+    count: u64,
+    sum: f64,
+    average: f64,
+    min: f64,
+    median: f64,
+    percentile_90: f64,
+    percentile_95: f64,
+    percentile_99: f64,
+    max: f64,
+    #[serde(skip)]
+    histogram: ValueHistogram,
+}
+
+impl TrendStats {
+    fn new(scale: f64) -> Self {
+        Self {
+            count: 0,
+            sum: 0.0,
+            average: 0.0,
+            min: 0.0,
+            median: 0.0,
+            percentile_90: 0.0,
+            percentile_95: 0.0,
+            percentile_99: 0.0,
+            max: 0.0,
+            histogram: ValueHistogram::new(scale),
+        }
+    }
+
+    fn add(&mut self, value: f64) {
+        if self.count == 0 || value < self.min {
+            self.min = value;
+        }
+        if self.count == 0 || value > self.max {
+            self.max = value;
+        }
+        self.count += 1;
+        self.sum += value;
+        self.histogram.record(value);
+    }
+
+    fn calculate_on_update_interval(&mut self) {
+        if self.count == 0 {
+            return;
+        }
+        self.average = self.sum / self.count as f64;
+        [
+            self.median,
+            self.percentile_90,
+            self.percentile_95,
+            self.percentile_99,
+        ] = self.histogram.percentiles(self.min, self.max);
+    }
+
+    pub fn get_count(&self) -> u64 {
+        self.count
+    }
+
+    pub fn get_average(&self) -> f64 {
+        self.average
+    }
+
+    pub fn get_min(&self) -> f64 {
+        self.min
+    }
+
+    pub fn get_median(&self) -> f64 {
+        self.median
+    }
+
+    pub fn get_percentile_90(&self) -> f64 {
+        self.percentile_90
+    }
+
+    pub fn get_percentile_95(&self) -> f64 {
+        self.percentile_95
+    }
+
+    pub fn get_percentile_99(&self) -> f64 {
+        self.percentile_99
+    }
+
+    pub fn get_max(&self) -> f64 {
+        self.max
+    }
+
+    // used for prometheus
+    pub(crate) fn quantiles(&self) -> [(&'static str, f64); 4] {
+        [
+            ("0.5", self.median),
+            ("0.9", self.percentile_90),
+            ("0.95", self.percentile_95),
+            ("0.99", self.percentile_99),
+        ]
+    }
+}
+
+// the percentage of true values
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct RateStats {
+    trues: u64,
+    falses: u64,
+    rate: f64,
+}
+
+impl RateStats {
+    fn add(&mut self, value: bool) {
+        if value {
+            self.trues += 1;
+        } else {
+            self.falses += 1;
+        }
+        self.rate = self.trues as f64 / (self.trues + self.falses) as f64;
+    }
+
+    pub fn get_trues(&self) -> u64 {
+        self.trues
+    }
+
+    pub fn get_falses(&self) -> u64 {
+        self.falses
+    }
+
+    // between 0 and 1
+    pub fn get_rate(&self) -> f64 {
+        self.rate
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Metric {
+    Counter(CounterStats),
+    Gauge(GaugeStats),
+    Trend(TrendStats),
+    Rate(RateStats),
+}
+
+impl Metric {
+    fn new(kind: MetricKind, trend_scale: f64) -> Self {
+        match kind {
+            MetricKind::Counter => Metric::Counter(CounterStats::default()),
+            MetricKind::Gauge => Metric::Gauge(GaugeStats::default()),
+            MetricKind::Trend => Metric::Trend(TrendStats::new(trend_scale)),
+            MetricKind::Rate => Metric::Rate(RateStats::default()),
+        }
+    }
+
+    pub fn get_kind(&self) -> MetricKind {
+        match self {
+            Metric::Counter(_) => MetricKind::Counter,
+            Metric::Gauge(_) => MetricKind::Gauge,
+            Metric::Trend(_) => MetricKind::Trend,
+            Metric::Rate(_) => MetricKind::Rate,
+        }
+    }
+
+    fn update(&mut self, update: MetricUpdate, first: bool) {
+        match (self, update) {
+            (Metric::Counter(stats), MetricUpdate::CounterAdd(value)) => stats.total += value,
+            (Metric::Gauge(stats), MetricUpdate::GaugeSet(value)) => stats.set(value, first),
+            (Metric::Gauge(stats), MetricUpdate::GaugeAdd(value)) => {
+                stats.set(stats.value + value, first)
+            }
+            (Metric::Trend(stats), MetricUpdate::TrendAdd(value)) => stats.add(value),
+            (Metric::Rate(stats), MetricUpdate::RateAdd(value)) => stats.add(value),
+            // the kind is checked before
+            _ => {}
+        }
+    }
+
+    fn calculate_on_update_interval(&mut self, elapsed: &Duration) {
+        match self {
+            Metric::Counter(stats) => stats.per_second = stats.total / elapsed.as_secs_f64(),
+            Metric::Trend(stats) => stats.calculate_on_update_interval(),
+            Metric::Gauge(_) | Metric::Rate(_) => {}
+        }
+    }
+
+    // the most important value of the metric for the console
+    fn display_value(&self, precision: usize) -> String {
+        match self {
+            Metric::Counter(stats) => format!(
+                "total: {:.2$}, per second: {:.2$}",
+                stats.total, stats.per_second, precision
+            ),
+            Metric::Gauge(stats) => format!(
+                "value: {:.3$}, min: {:.3$}, max: {:.3$}",
+                stats.value, stats.min, stats.max, precision
+            ),
+            Metric::Trend(stats) => format!(
+                "avg: {:.6$}, min: {:.6$}, median: {:.6$}, 95%: {:.6$}, max: {:.6$}, count: {}",
+                stats.average,
+                stats.min,
+                stats.median,
+                stats.percentile_95,
+                stats.max,
+                stats.count,
+                precision
+            ),
+            Metric::Rate(stats) => format!(
+                "rate: {:.3$}, true: {}, false: {}",
+                stats.get_rate(),
+                stats.trues,
+                stats.falses,
+                precision
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct SerMetric {
+    name: String,
+    metric: Metric,
+}
+
+impl Serialize for SerMetric {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let mut state = serializer.serialize_struct("SerMetric", 3)?;
+        state.serialize_field("name", &self.name)?;
+        state.serialize_field("kind", &self.metric.get_kind())?;
+        match &self.metric {
+            Metric::Counter(stats) => state.serialize_field("stats", stats)?,
+            Metric::Gauge(stats) => state.serialize_field("stats", stats)?,
+            Metric::Trend(stats) => state.serialize_field("stats", stats)?,
+            Metric::Rate(stats) => state.serialize_field("stats", stats)?,
+        }
+        state.end()
+    }
+}
+
+/// User defined metrics, registered on their first update.
+/// The kind of a metric is given by its first update, updates with another kind are rejected.
+/// Negative trend values are rejected, the percentiles can not record them.
+#[derive(Debug, Clone)]
+pub struct AllMetrics {
+    metrics: HashMap<String, Metric>,
+    trend_scale: f64,
+}
+
+impl Default for AllMetrics {
+    fn default() -> Self {
+        Self::new(DEFAULT_TREND_SCALE)
+    }
+}
+
+impl AllMetrics {
+    pub(crate) fn new(trend_scale: f64) -> Self {
+        Self {
+            metrics: HashMap::new(),
+            trend_scale,
+        }
+    }
+
+    pub(crate) fn update(
+        &mut self,
+        name: &str,
+        update: MetricUpdate,
+    ) -> Result<&Metric, MetricUpdateError> {
+        if let MetricUpdate::TrendAdd(value) = update {
+            if !value.is_finite() || value < 0.0 {
+                return Err(MetricUpdateError::InvalidTrendValue(value));
+            }
+        }
+
+        let given = update.kind();
+        let first = !self.metrics.contains_key(name);
+        let trend_scale = self.trend_scale;
+        let metric = self
+            .metrics
+            .entry(String::from(name))
+            .or_insert_with(|| Metric::new(given, trend_scale));
+
+        let registered = metric.get_kind();
+        if registered != given {
+            return Err(MetricUpdateError::KindMismatch { registered, given });
+        }
+
+        metric.update(update, first);
+        Ok(metric)
+    }
+
+    pub(crate) fn calculate_on_update_interval(&mut self, elapsed: &Duration) {
+        for metric in self.metrics.values_mut() {
+            metric.calculate_on_update_interval(elapsed);
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.metrics.is_empty()
+    }
+
+    pub fn get_by_name(&self, name: &str) -> Option<&Metric> {
+        self.metrics.get(name)
+    }
+
+    pub fn get_metrics(&self) -> &HashMap<String, Metric> {
+        &self.metrics
+    }
+
+    // sorted by name, so the table does not jump around
+    fn sorted_metrics(&self) -> Vec<(&String, &Metric)> {
+        let mut metrics: Vec<(&String, &Metric)> = self.metrics.iter().collect();
+        metrics.sort_by_key(|(name, _)| *name);
+        metrics
+    }
+
+    pub(crate) fn table_string(&self, precision: usize) -> String {
+        let mut table = Table::new();
+        table.add_row(Row::new(
+            CONSOLE_HEADERS.iter().map(|s| Cell::new(s)).collect(),
+        ));
+        for (name, metric) in self.sorted_metrics() {
+            table.add_row(row![
+                name,
+                metric.get_kind().as_str(),
+                metric.display_value(precision),
+            ]);
+        }
+        table.to_string()
+    }
+
+    pub(crate) fn ser_metrics(&self) -> Vec<SerMetric> {
+        self.sorted_metrics()
+            .into_iter()
+            .map(|(name, metric)| SerMetric {
+                name: name.clone(),
+                metric: metric.clone(),
+            })
+            .collect()
+    }
+}
+
+/// Handle for a counter, see Context::counter.
+pub struct Counter<'a> {
+    context: &'a Context,
+    name: &'a str,
+}
+
+impl Counter<'_> {
+    pub async fn inc(&self) {
+        self.inc_by(1.0).await;
+    }
+
+    pub async fn inc_by(&self, value: f64) {
+        self.context
+            .update_metric(self.name, MetricUpdate::CounterAdd(value))
+            .await;
+    }
+}
+
+/// Handle for a gauge, see Context::gauge. The gauge is shared between all users.
+pub struct Gauge<'a> {
+    context: &'a Context,
+    name: &'a str,
+}
+
+impl Gauge<'_> {
+    pub async fn set(&self, value: f64) {
+        self.context
+            .update_metric(self.name, MetricUpdate::GaugeSet(value))
+            .await;
+    }
+
+    pub async fn add(&self, value: f64) {
+        self.context
+            .update_metric(self.name, MetricUpdate::GaugeAdd(value))
+            .await;
+    }
+
+    pub async fn inc(&self) {
+        self.add(1.0).await;
+    }
+
+    pub async fn dec(&self) {
+        self.add(-1.0).await;
+    }
+}
+
+/// Handle for a trend, see Context::trend.
+pub struct Trend<'a> {
+    context: &'a Context,
+    name: &'a str,
+}
+
+impl Trend<'_> {
+    pub async fn add(&self, value: f64) {
+        self.context
+            .update_metric(self.name, MetricUpdate::TrendAdd(value))
+            .await;
+    }
+}
+
+/// Handle for a rate, see Context::rate.
+pub struct Rate<'a> {
+    context: &'a Context,
+    name: &'a str,
+}
+
+impl Rate<'_> {
+    pub async fn add(&self, value: bool) {
+        self.context
+            .update_metric(self.name, MetricUpdate::RateAdd(value))
+            .await;
+    }
+}
+
+impl Context {
+    pub fn counter<'a>(&'a self, name: &'a str) -> Counter<'a> {
+        Counter {
+            context: self,
+            name,
+        }
+    }
+
+    pub fn gauge<'a>(&'a self, name: &'a str) -> Gauge<'a> {
+        Gauge {
+            context: self,
+            name,
+        }
+    }
+
+    /// Value distribution with percentiles. Values must be finite and not negative, other values are rejected.
+    /// The precision of the percentiles is given by TestConfig::trend_scale.
+    pub fn trend<'a>(&'a self, name: &'a str) -> Trend<'a> {
+        Trend {
+            context: self,
+            name,
+        }
+    }
+
+    /// Percentage of true values.
+    pub fn rate<'a>(&'a self, name: &'a str) -> Rate<'a> {
+        Rate {
+            context: self,
+            name,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counters_are_summed_per_second() {
+        let mut all_metrics = AllMetrics::default();
+        all_metrics
+            .update("orders", MetricUpdate::CounterAdd(1.0))
+            .unwrap();
+        all_metrics
+            .update("orders", MetricUpdate::CounterAdd(3.0))
+            .unwrap();
+        all_metrics.calculate_on_update_interval(&Duration::from_secs(2));
+
+        match all_metrics.get_by_name("orders") {
+            Some(Metric::Counter(stats)) => {
+                assert_eq!(stats.get_total(), 4.0);
+                assert_eq!(stats.get_per_second(), 2.0);
+            }
+            _ => panic!("expected a counter"),
+        }
+    }
+
+    #[test]
+    fn gauges_keep_min_and_max() {
+        let mut all_metrics = AllMetrics::default();
+        // the first value is the min, even if it is above 0
+        all_metrics
+            .update("queue", MetricUpdate::GaugeSet(5.0))
+            .unwrap();
+        all_metrics
+            .update("queue", MetricUpdate::GaugeAdd(3.0))
+            .unwrap();
+        all_metrics
+            .update("queue", MetricUpdate::GaugeAdd(-6.0))
+            .unwrap();
+
+        match all_metrics.get_by_name("queue") {
+            Some(Metric::Gauge(stats)) => {
+                assert_eq!(stats.get_value(), 2.0);
+                assert_eq!(stats.get_min(), 2.0);
+                assert_eq!(stats.get_max(), 8.0);
+            }
+            _ => panic!("expected a gauge"),
+        }
+    }
+
+    #[test]
+    fn trends_have_percentiles_within_min_and_max() {
+        let mut all_metrics = AllMetrics::default();
+        for value in 1..=100 {
+            all_metrics
+                .update("cart_size", MetricUpdate::TrendAdd(value as f64))
+                .unwrap();
+        }
+        all_metrics.calculate_on_update_interval(&Duration::from_secs(1));
+
+        match all_metrics.get_by_name("cart_size") {
+            Some(Metric::Trend(stats)) => {
+                assert_eq!(stats.get_count(), 100);
+                assert_eq!(stats.get_average(), 50.5);
+                assert_eq!(stats.get_min(), 1.0);
+                assert_eq!(stats.get_max(), 100.0);
+                assert!((stats.get_median() - 50.0).abs() < 0.1);
+                assert!((stats.get_percentile_99() - 99.0).abs() < 0.1);
+                assert!(stats.get_percentile_99() <= stats.get_max());
+            }
+            _ => panic!("expected a trend"),
+        }
+    }
+
+    #[test]
+    fn rates_are_the_percentage_of_true_values() {
+        let mut all_metrics = AllMetrics::default();
+        for value in [true, true, true, false] {
+            all_metrics
+                .update("cache_hit", MetricUpdate::RateAdd(value))
+                .unwrap();
+        }
+
+        match all_metrics.get_by_name("cache_hit") {
+            Some(Metric::Rate(stats)) => {
+                assert_eq!(stats.get_trues(), 3);
+                assert_eq!(stats.get_falses(), 1);
+                assert_eq!(stats.get_rate(), 0.75);
+            }
+            _ => panic!("expected a rate"),
+        }
+    }
+
+    #[test]
+    fn updates_of_another_kind_are_rejected() {
+        let mut all_metrics = AllMetrics::default();
+        all_metrics
+            .update("orders", MetricUpdate::CounterAdd(1.0))
+            .unwrap();
+
+        let error = all_metrics
+            .update("orders", MetricUpdate::GaugeSet(1.0))
+            .unwrap_err();
+
+        assert!(matches!(
+            error,
+            MetricUpdateError::KindMismatch {
+                registered: MetricKind::Counter,
+                given: MetricKind::Gauge
+            }
+        ));
+        match all_metrics.get_by_name("orders") {
+            Some(Metric::Counter(stats)) => assert_eq!(stats.get_total(), 1.0),
+            _ => panic!("expected a counter"),
+        }
+    }
+
+    #[test]
+    fn metrics_are_serialized_with_name_and_kind() {
+        let mut all_metrics = AllMetrics::default();
+        all_metrics
+            .update("orders", MetricUpdate::CounterAdd(1.0))
+            .unwrap();
+        all_metrics
+            .update("cache_hit", MetricUpdate::RateAdd(true))
+            .unwrap();
+
+        let json = serde_json::to_value(all_metrics.ser_metrics()).unwrap();
+
+        // sorted by name
+        assert_eq!(json[0]["name"], "cache_hit");
+        assert_eq!(json[0]["kind"], "rate");
+        assert_eq!(json[0]["stats"]["rate"], 1.0);
+        assert_eq!(json[1]["name"], "orders");
+        assert_eq!(json[1]["kind"], "counter");
+        assert_eq!(json[1]["stats"]["total"], 1.0);
+    }
+
+    #[test]
+    fn invalid_trend_values_are_rejected() {
+        let mut all_metrics = AllMetrics::default();
+        all_metrics
+            .update("discount", MetricUpdate::TrendAdd(1.0))
+            .unwrap();
+
+        for value in [-1.0, f64::NAN, f64::INFINITY] {
+            let error = all_metrics
+                .update("discount", MetricUpdate::TrendAdd(value))
+                .unwrap_err();
+            assert!(matches!(error, MetricUpdateError::InvalidTrendValue(_)));
+        }
+        match all_metrics.get_by_name("discount") {
+            Some(Metric::Trend(stats)) => {
+                assert_eq!(stats.get_count(), 1);
+                assert_eq!(stats.get_min(), 1.0);
+            }
+            _ => panic!("expected a trend"),
+        }
+    }
+
+    #[test]
+    fn trend_percentiles_are_recorded_with_the_trend_scale() {
+        // nanoseconds given in seconds, below the default scale
+        let mut all_metrics = AllMetrics::new(1_000_000_000.0);
+        for nanos in 1..=100 {
+            all_metrics
+                .update("lookup", MetricUpdate::TrendAdd(nanos as f64 / 1e9))
+                .unwrap();
+        }
+        all_metrics.calculate_on_update_interval(&Duration::from_secs(1));
+
+        match all_metrics.get_by_name("lookup") {
+            Some(Metric::Trend(stats)) => {
+                assert!((stats.get_median() - 50e-9).abs() < 1e-10);
+                assert!((stats.get_percentile_99() - 99e-9).abs() < 1e-10);
+            }
+            _ => panic!("expected a trend"),
+        }
+    }
+}
@@ -0,0 +1,352 @@
+use crate::{
+    results::{CSVError, EndpointTypeName},
+    utils,
+};
+use csv::Writer as CsvWriter;
+use prettytable::{row, Cell, Row, Table};
+use serde::{ser::SerializeStruct, Serialize};
+use std::{cmp::Reverse, collections::HashMap};
+
+// new messages are counted here once the limit of distinct messages is reached
+pub const OTHER_ERRORS: &str = "Other errors (too many distinct error messages)";
+pub const OTHER_FAILURE_REASONS: &str = "Other failures (too many distinct failure reasons)";
+
+pub const DEFAULT_MAX_DISTINCT_ERRORS: usize = 1000;
+
+// errors are aggregated by message, failures by reason
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    Error,
+    Failure,
+}
+
+impl ErrorKind {
+    fn console_headers(&self) -> [&'static str; 4] {
+        match self {
+            ErrorKind::Error => ["TYPE", "NAME", "ERROR", "OCCURRENCES"],
+            ErrorKind::Failure => ["TYPE", "NAME", "REASON", "OCCURRENCES"],
+        }
+    }
+
+    // the name of the message field in files
+    fn message_field(&self) -> &'static str {
+        match self {
+            ErrorKind::Error => "error",
+            ErrorKind::Failure => "reason",
+        }
+    }
+
+    fn other_message(&self) -> &'static str {
+        match self {
+            ErrorKind::Error => OTHER_ERRORS,
+            ErrorKind::Failure => OTHER_FAILURE_REASONS,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct EndpointTypeNameError {
+    pub endpoint_type_name: EndpointTypeName,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ErrorStats {
+    occurrences: u64,
+    // timestamps in milliseconds
+    first_seen: u64,
+    last_seen: u64,
+}
+
+impl ErrorStats {
+    fn new(timestamp: u64) -> Self {
+        Self {
+            occurrences: 0,
+            first_seen: timestamp,
+            last_seen: timestamp,
+        }
+    }
+
+    pub fn get_occurrences(&self) -> u64 {
+        self.occurrences
+    }
+
+    pub fn get_first_seen(&self) -> u64 {
+        self.first_seen
+    }
+
+    pub fn get_last_seen(&self) -> u64 {
+        self.last_seen
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct SerErrorStats {
+    kind: ErrorKind,
+    endpoint_type_name_error: EndpointTypeNameError,
+    error_stats: ErrorStats,
+}
+
+impl Serialize for SerErrorStats {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let mut state = serializer.serialize_struct("SerErrorStats", 6)?;
+        state.serialize_field(
+            "type",
+            &self.endpoint_type_name_error.endpoint_type_name.r#type,
+        )?;
+        state.serialize_field(
+            "name",
+            &self.endpoint_type_name_error.endpoint_type_name.name,
+        )?;
+        state.serialize_field(
+            self.kind.message_field(),
+            &self.endpoint_type_name_error.error,
+        )?;
+        state.serialize_field("occurrences", &self.error_stats.occurrences)?;
+        state.serialize_field("first_seen", &self.error_stats.first_seen)?;
+        state.serialize_field("last_seen", &self.error_stats.last_seen)?;
+        state.end()
+    }
+}
+
+// errors deduplicated by endpoint and message, also used for failures deduplicated by endpoint and reason
+#[derive(Debug, Clone)]
+pub struct AllErrors {
+    kind: ErrorKind,
+    errors: HashMap<EndpointTypeNameError, ErrorStats>,
+    max_distinct_errors: usize,
+}
+
+impl Default for AllErrors {
+    fn default() -> Self {
+        Self::new(ErrorKind::Error, DEFAULT_MAX_DISTINCT_ERRORS)
+    }
+}
+
+impl AllErrors {
+    pub(crate) fn new(kind: ErrorKind, max_distinct_errors: usize) -> Self {
+        Self {
+            kind,
+            errors: HashMap::new(),
+            max_distinct_errors,
+        }
+    }
+
+    pub fn get_kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    pub(crate) fn add_error(&mut self, endpoint_type_name: &EndpointTypeName, error: &str) {
+        let timestamp = utils::get_timestamp_as_millis().unwrap_or_default() as u64;
+
+        let mut endpoint_type_name_error = EndpointTypeNameError {
+            endpoint_type_name: endpoint_type_name.clone(),
+            error: String::from(error),
+        };
+
+        // the other errors are bounded by the number of endpoints
+        if !self.errors.contains_key(&endpoint_type_name_error)
+            && self.errors.len() >= self.max_distinct_errors
+        {
+            endpoint_type_name_error.error = String::from(self.kind.other_message());
+        }
+
+        let error_stats = self
+            .errors
+            .entry(endpoint_type_name_error)
+            .or_insert_with(|| ErrorStats::new(timestamp));
+
+        error_stats.occurrences += 1;
+        error_stats.last_seen = timestamp;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    // most frequent first
+    pub fn get_errors(&self) -> Vec<(&EndpointTypeNameError, &ErrorStats)> {
+        let mut errors: Vec<(&EndpointTypeNameError, &ErrorStats)> = self.errors.iter().collect();
+        errors.sort_by_key(|(_, error_stats)| Reverse(error_stats.occurrences));
+        errors
+    }
+
+    pub(crate) fn table_string(&self) -> String {
+        let mut table = Table::new();
+        table.add_row(Row::new(
+            self.kind
+                .console_headers()
+                .iter()
+                .map(|s| Cell::new(s))
+                .collect(),
+        ));
+        for (endpoint_type_name_error, error_stats) in self.get_errors() {
+            table.add_row(row![
+                endpoint_type_name_error.endpoint_type_name.r#type,
+                endpoint_type_name_error.endpoint_type_name.name,
+                endpoint_type_name_error.error,
+                error_stats.occurrences,
+            ]);
+        }
+        table.to_string()
+    }
+
+    pub(crate) fn csv_string(&self) -> Result<String, CSVError> {
+        let mut wtr = CsvWriter::from_writer(vec![]);
+        wtr.write_record([
+            "type",
+            "name",
+            self.kind.message_field(),
+            "occurrences",
+            "first_seen",
+            "last_seen",
+        ])?;
+        for (endpoint_type_name_error, error_stats) in self.get_errors() {
+            wtr.write_record([
+                &endpoint_type_name_error.endpoint_type_name.r#type,
+                &endpoint_type_name_error.endpoint_type_name.name,
+                &endpoint_type_name_error.error,
+                &error_stats.occurrences.to_string(),
+                &error_stats.first_seen.to_string(),
+                &error_stats.last_seen.to_string(),
+            ])?;
+        }
+        let data = String::from_utf8(wtr.into_inner()?)?;
+        Ok(data)
+    }
+
+    pub(crate) fn ser_errors(&self) -> Vec<SerErrorStats> {
+        self.get_errors()
+            .into_iter()
+            .map(|(endpoint_type_name_error, error_stats)| SerErrorStats {
+                kind: self.kind,
+                endpoint_type_name_error: endpoint_type_name_error.clone(),
+                error_stats: error_stats.clone(),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn endpoint_type_name(name: &str) -> EndpointTypeName {
+        EndpointTypeName {
+            r#type: String::from("GET"),
+            name: String::from(name),
+        }
+    }
+
+    // (name, message, occurrences), most frequent first
+    fn errors(all_errors: &AllErrors) -> Vec<(String, String, u64)> {
+        all_errors
+            .get_errors()
+            .into_iter()
+            .map(|(endpoint_type_name_error, error_stats)| {
+                (
+                    endpoint_type_name_error.endpoint_type_name.name.clone(),
+                    endpoint_type_name_error.error.clone(),
+                    error_stats.get_occurrences(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn errors_are_aggregated_by_endpoint_and_message() {
+        let mut all_errors = AllErrors::default();
+        all_errors.add_error(&endpoint_type_name("/items"), "refused");
+        all_errors.add_error(&endpoint_type_name("/items"), "refused");
+        all_errors.add_error(&endpoint_type_name("/items"), "reset");
+        all_errors.add_error(&endpoint_type_name("/cart"), "refused");
+        all_errors.add_error(&endpoint_type_name("/items"), "refused");
+
+        let mut errors = errors(&all_errors);
+        assert_eq!(
+            errors.remove(0),
+            (String::from("/items"), String::from("refused"), 3)
+        );
+        errors.sort();
+        assert_eq!(
+            errors,
+            vec![
+                (String::from("/cart"), String::from("refused"), 1),
+                (String::from("/items"), String::from("reset"), 1)
+            ]
+        );
+        let (_, error_stats) = all_errors.get_errors()[0];
+        assert!(error_stats.get_first_seen() <= error_stats.get_last_seen());
+    }
+
+    #[test]
+    fn new_messages_go_to_the_other_bucket_after_the_limit() {
+        let mut all_errors = AllErrors::new(ErrorKind::Error, 2);
+        all_errors.add_error(&endpoint_type_name("/items"), "refused");
+        all_errors.add_error(&endpoint_type_name("/items"), "reset");
+        all_errors.add_error(&endpoint_type_name("/items"), "timed out");
+        all_errors.add_error(&endpoint_type_name("/items"), "dns");
+        all_errors.add_error(&endpoint_type_name("/cart"), "refused");
+        // known messages are still counted
+        all_errors.add_error(&endpoint_type_name("/items"), "refused");
+
+        let mut errors = errors(&all_errors);
+        errors.sort();
+        assert_eq!(
+            errors,
+            vec![
+                (String::from("/cart"), String::from(OTHER_ERRORS), 1),
+                (String::from("/items"), String::from(OTHER_ERRORS), 2),
+                (String::from("/items"), String::from("refused"), 2),
+                (String::from("/items"), String::from("reset"), 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn csv_has_a_row_per_error() {
+        let mut all_errors = AllErrors::default();
+        all_errors.add_error(&endpoint_type_name("/items"), "refused");
+
+        let csv = all_errors.csv_string().unwrap();
+        let mut lines = csv.lines();
+
+        assert_eq!(
+            lines.next(),
+            Some("type,name,error,occurrences,first_seen,last_seen")
+        );
+        assert!(lines.next().unwrap().starts_with("GET,/items,refused,1,"));
+        assert!(lines.next().is_none());
+    }
+
+    #[test]
+    fn failure_reasons_are_aggregated_like_errors() {
+        let mut all_failures = AllErrors::new(ErrorKind::Failure, 1);
+        all_failures.add_error(&endpoint_type_name("/cart"), "status 500");
+        all_failures.add_error(&endpoint_type_name("/cart"), "status 503");
+
+        let mut failures = errors(&all_failures);
+        failures.sort();
+        assert_eq!(
+            failures,
+            vec![
+                (
+                    String::from("/cart"),
+                    String::from(OTHER_FAILURE_REASONS),
+                    1
+                ),
+                (String::from("/cart"), String::from("status 500"), 1)
+            ]
+        );
+        assert!(all_failures
+            .csv_string()
+            .unwrap()
+            .starts_with("type,name,reason,occurrences"));
+        let json = serde_json::to_value(all_failures.ser_errors()).unwrap();
+        assert!(json[0].get("reason").is_some());
+        assert!(json[0].get("error").is_none());
+    }
+}
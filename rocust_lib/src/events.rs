@@ -1,15 +1,27 @@
+#[cfg(feature = "otel")]
+use crate::otel::take_current_span_context;
 use crate::{
+    custom_metrics::MetricUpdate,
     messages::{
-        ErrorResultMessage, FailureResultMessage, MainMessage, ResultMessage, SuccessResultMessage,
-        TaskExecutedMessage, UserFinishedMessage, UserPanickedMessage, UserSelfStoppedMessage,
-        UserSpawnedMessage, UserUnknownStatusMessage,
+        CheckMessage, ErrorResultMessage, FailureResultMessage, MainMessage, MetricMessage,
+        ResultMessage, SuccessResultMessage, TaskExecutedMessage, TaskTimedOutMessage,
+        UserFailedToStartMessage, UserFinishedMessage, UserPanickedMessage, UserRestartedMessage,
+        UserSelfStoppedMessage, UserSpawnedMessage, UserUnknownStatusMessage,
     },
-    results::EndpointTypeName,
+    results::{EndpointTypeName, ResponseInfo},
     tasks::EventsTaskInfo,
     test::user::EventsUserInfo,
+    utils,
 };
 use tokio::sync::mpsc::Sender;
 
+// of a result, taken here and not in the main loop, which lags behind the users under load
+fn timestamp() -> u64 {
+    utils::get_timestamp_as_millis()
+        .map(|timestamp| timestamp as u64)
+        .unwrap_or_default()
+}
+
 #[derive(Debug, Clone)]
 pub struct EventsHandler {
     user_info: EventsUserInfo,
@@ -25,33 +37,87 @@ impl EventsHandler {
         let _ = self.sender.send(message).await;
     }
 
+    /// Reports a success without a task. Users report through Context::add_success, which adds the current task.
     pub async fn add_success(&self, r#type: String, name: String, response_time: f64) {
+        self.report_success(None, r#type, name, response_time, ResponseInfo::default())
+            .await;
+    }
+
+    /// Reports a failure without a task. Users report through Context::add_failure, which adds the current task.
+    pub async fn add_failure(&self, r#type: String, name: String) {
+        self.report_failure(None, r#type, name, None, None, ResponseInfo::default())
+            .await;
+    }
+
+    /// Reports an error without a task. Users report through Context::add_error, which adds the current task.
+    pub async fn add_error(&self, r#type: String, name: String, error: String) {
+        self.report_error(None, r#type, name, error).await;
+    }
+
+    pub(crate) async fn report_success(
+        &self,
+        task_info: Option<EventsTaskInfo>,
+        r#type: String,
+        name: String,
+        response_time: f64,
+        response_info: ResponseInfo,
+    ) {
         self.send(MainMessage::ResultMessage(ResultMessage::Success(
             SuccessResultMessage {
                 user_info: self.user_info.clone(),
+                task_info,
                 endpoint_type_name: EndpointTypeName { r#type, name },
+                timestamp: timestamp(),
                 response_time,
+                response_info,
+                #[cfg(feature = "otel")]
+                span_context: take_current_span_context(),
             },
         )))
         .await;
     }
 
-    pub async fn add_failure(&self, r#type: String, name: String) {
+    pub(crate) async fn report_failure(
+        &self,
+        task_info: Option<EventsTaskInfo>,
+        r#type: String,
+        name: String,
+        reason: Option<String>,
+        response_time: Option<f64>,
+        response_info: ResponseInfo,
+    ) {
         self.send(MainMessage::ResultMessage(ResultMessage::Failure(
             FailureResultMessage {
                 user_info: self.user_info.clone(),
+                task_info,
                 endpoint_type_name: EndpointTypeName { r#type, name },
+                timestamp: timestamp(),
+                reason,
+                response_time,
+                response_info,
+                #[cfg(feature = "otel")]
+                span_context: take_current_span_context(),
             },
         )))
         .await;
     }
 
-    pub async fn add_error(&self, r#type: String, name: String, error: String) {
+    pub(crate) async fn report_error(
+        &self,
+        task_info: Option<EventsTaskInfo>,
+        r#type: String,
+        name: String,
+        error: String,
+    ) {
         self.send(MainMessage::ResultMessage(ResultMessage::Error(
             ErrorResultMessage {
                 user_info: self.user_info.clone(),
+                task_info,
                 endpoint_type_name: EndpointTypeName { r#type, name },
+                timestamp: timestamp(),
                 error,
+                #[cfg(feature = "otel")]
+                span_context: take_current_span_context(),
             },
         )))
         .await;
@@ -64,10 +130,54 @@ impl EventsHandler {
         .await;
     }
 
-    pub(crate) async fn add_task_executed(&self, task_info: EventsTaskInfo) {
+    pub(crate) async fn add_user_failed_to_start(&self, error: String) {
+        self.send(MainMessage::UserFailedToStart(UserFailedToStartMessage {
+            user_info: self.user_info.clone(),
+            error,
+        }))
+        .await;
+    }
+
+    pub(crate) async fn add_task_executed(&self, task_info: EventsTaskInfo, duration: f64) {
         self.send(MainMessage::TaskExecuted(TaskExecutedMessage {
             user_info: self.user_info.clone(),
             task_info,
+            duration,
+        }))
+        .await;
+    }
+
+    pub(crate) async fn add_task_timed_out(
+        &self,
+        task_info: EventsTaskInfo,
+        timeout_in_millis: u64,
+    ) {
+        self.send(MainMessage::TaskTimedOut(TaskTimedOutMessage {
+            user_info: self.user_info.clone(),
+            task_info,
+            timeout_in_millis,
+        }))
+        .await;
+    }
+
+    pub(crate) async fn add_checks(
+        &self,
+        task_info: Option<EventsTaskInfo>,
+        checks: Vec<(String, bool)>,
+    ) {
+        self.send(MainMessage::Check(CheckMessage {
+            user_info: self.user_info.clone(),
+            task_info,
+            checks,
+        }))
+        .await;
+    }
+
+    pub(crate) async fn add_metric_update(&self, name: String, update: MetricUpdate) {
+        self.send(MainMessage::Metric(MetricMessage {
+            user_info: self.user_info.clone(),
+            name,
+            update,
         }))
         .await;
     }
@@ -86,10 +196,24 @@ impl EventsHandler {
         .await;
     }
 
-    pub(crate) async fn add_user_panicked(&self, error: String) {
+    pub(crate) async fn add_user_panicked(
+        &self,
+        message: String,
+        task_name: Option<&'static str>,
+        backtrace: Option<String>,
+    ) {
         self.send(MainMessage::UserPanicked(UserPanickedMessage {
             user_info: self.user_info.clone(),
-            _error: error,
+            message,
+            task_name,
+            backtrace,
+        }))
+        .await;
+    }
+
+    pub(crate) async fn add_user_restarted(&self) {
+        self.send(MainMessage::UserRestarted(UserRestartedMessage {
+            user_info: self.user_info.clone(),
         }))
         .await;
     }
@@ -104,3 +228,115 @@ impl EventsHandler {
         self.user_info.id
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test::{controller::TestController, user::UserController},
+        Context,
+    };
+    use std::sync::Arc;
+    use tokio::sync::mpsc::{self, Receiver};
+    use tokio_util::sync::CancellationToken;
+
+    fn events_handler() -> (EventsHandler, Receiver<MainMessage>) {
+        let (results_tx, results_rx) = mpsc::channel(10);
+        (
+            EventsHandler::new(EventsUserInfo::new(0, "ShopUser"), results_tx),
+            results_rx,
+        )
+    }
+
+    // the task name and the endpoint name of the received result
+    fn received(results_rx: &mut Receiver<MainMessage>) -> (Option<&'static str>, String) {
+        match results_rx.try_recv() {
+            Ok(MainMessage::ResultMessage(ResultMessage::Success(msg))) => (
+                msg.task_info.map(|task_info| task_info.name),
+                msg.endpoint_type_name.name,
+            ),
+            Ok(MainMessage::ResultMessage(ResultMessage::Failure(msg))) => (
+                msg.task_info.map(|task_info| task_info.name),
+                msg.endpoint_type_name.name,
+            ),
+            Ok(MainMessage::ResultMessage(ResultMessage::Error(msg))) => (
+                msg.task_info.map(|task_info| task_info.name),
+                msg.endpoint_type_name.name,
+            ),
+            _ => panic!("expected a result"),
+        }
+    }
+
+    #[tokio::test]
+    async fn public_reporting_has_no_task() {
+        let (events_handler, mut results_rx) = events_handler();
+
+        events_handler
+            .add_success(String::from("GET"), String::from("/items"), 0.1)
+            .await;
+        events_handler
+            .add_failure(String::from("GET"), String::from("/cart"))
+            .await;
+        events_handler
+            .add_error(
+                String::from("GET"),
+                String::from("/pay"),
+                String::from("refused"),
+            )
+            .await;
+
+        assert_eq!(received(&mut results_rx), (None, String::from("/items")));
+        assert_eq!(received(&mut results_rx), (None, String::from("/cart")));
+        assert_eq!(received(&mut results_rx), (None, String::from("/pay")));
+    }
+
+    #[tokio::test]
+    async fn context_reports_the_current_task() {
+        let (events_handler, mut results_rx) = events_handler();
+        let context = Context::new(
+            Arc::new(TestController::new(CancellationToken::new())),
+            events_handler,
+            UserController::new(CancellationToken::new()),
+        );
+
+        context
+            .add_success(String::from("GET"), String::from("/start"), 0.1)
+            .await;
+        context.set_current_task(Some(EventsTaskInfo {
+            name: "checkout",
+            tags: &[],
+        }));
+        context
+            .add_success(String::from("GET"), String::from("/items"), 0.1)
+            .await;
+        context
+            .add_failure(String::from("GET"), String::from("/cart"))
+            .await;
+        context
+            .add_error(
+                String::from("GET"),
+                String::from("/pay"),
+                String::from("refused"),
+            )
+            .await;
+        context.set_current_task(None);
+        context
+            .add_success(String::from("GET"), String::from("/stop"), 0.1)
+            .await;
+
+        assert_eq!(received(&mut results_rx), (None, String::from("/start")));
+        assert_eq!(
+            received(&mut results_rx),
+            (Some("checkout"), String::from("/items"))
+        );
+        assert_eq!(
+            received(&mut results_rx),
+            (Some("checkout"), String::from("/cart"))
+        );
+        assert_eq!(
+            received(&mut results_rx),
+            (Some("checkout"), String::from("/pay"))
+        );
+        assert_eq!(received(&mut results_rx), (None, String::from("/stop")));
+    }
+}
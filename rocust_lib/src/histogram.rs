@@ -0,0 +1,99 @@
+use hdrhistogram::Histogram;
+
+// durations are given in seconds and recorded in microseconds
+const MICROS_PER_SEC: f64 = 1_000_000.0;
+
+// keeps the distribution of values with a bounded memory usage, used for percentiles
+// values are recorded as integers in units of 1 / scale, negative values are recorded as 0
+#[derive(Debug, Clone)]
+pub(crate) struct ValueHistogram {
+    histogram: Histogram<u64>,
+    scale: f64,
+}
+
+// for durations
+impl Default for ValueHistogram {
+    fn default() -> Self {
+        Self::new(MICROS_PER_SEC)
+    }
+}
+
+impl ValueHistogram {
+    pub(crate) fn new(scale: f64) -> Self {
+        Self {
+            // auto resizing, 3 significant figures
+            histogram: Histogram::new(3).expect("3 significant figures are valid"),
+            scale,
+        }
+    }
+
+    pub(crate) fn record(&mut self, value: f64) {
+        let units = (value * self.scale).max(0.0).round() as u64;
+        let _ = self.histogram.record(units);
+    }
+
+    fn value_at_quantile(&self, quantile: f64) -> f64 {
+        if self.histogram.is_empty() {
+            return 0.0;
+        }
+        self.histogram.value_at_quantile(quantile) as f64 / self.scale
+    }
+
+    // median, 90%, 95% and 99%, the histogram is not exact, so they are kept within the recorded min and max
+    pub(crate) fn percentiles(&self, min: f64, max: f64) -> [f64; 4] {
+        [0.5, 0.9, 0.95, 0.99].map(|quantile| self.value_at_quantile(quantile).max(min).min(max))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_histogram_has_no_percentiles() {
+        assert_eq!(ValueHistogram::default().value_at_quantile(0.5), 0.0);
+    }
+
+    #[test]
+    fn percentiles_are_within_the_precision() {
+        let mut histogram = ValueHistogram::default();
+        // 1ms to 1s
+        for millis in 1..=1000 {
+            histogram.record(millis as f64 / 1000.0);
+        }
+
+        for (quantile, expected) in [(0.5, 0.5), (0.9, 0.9), (0.99, 0.99), (1.0, 1.0)] {
+            let value = histogram.value_at_quantile(quantile);
+            assert!(
+                (value - expected).abs() <= expected / 1000.0,
+                "{} at {}",
+                value,
+                quantile
+            );
+        }
+    }
+
+    #[test]
+    fn negative_durations_are_recorded_as_zero() {
+        let mut histogram = ValueHistogram::default();
+        histogram.record(-1.0);
+
+        assert_eq!(histogram.value_at_quantile(1.0), 0.0);
+    }
+
+    #[test]
+    fn percentiles_are_within_min_and_max() {
+        let mut histogram = ValueHistogram::default();
+        histogram.record(0.1234567);
+
+        assert_eq!(histogram.percentiles(0.1234567, 0.1234567), [0.1234567; 4]);
+    }
+
+    #[test]
+    fn values_are_recorded_in_units_of_the_scale() {
+        let mut histogram = ValueHistogram::new(1_000_000_000.0);
+        histogram.record(0.000_000_5);
+
+        assert_eq!(histogram.value_at_quantile(1.0), 0.000_000_5);
+    }
+}
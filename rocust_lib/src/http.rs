@@ -0,0 +1,451 @@
+use crate::{futures::TimedExt, results::ResponseInfo, Context};
+use bytes::Bytes;
+use reqwest::{
+    header::{HeaderMap, HeaderName, HeaderValue},
+    Client, Method, RequestBuilder, StatusCode, Url,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{borrow::Cow, time::Duration};
+
+// decides if a response is reported as a success or a failure
+#[derive(Debug, Clone, Copy, Default)]
+pub enum StatusPolicy {
+    // 2xx is a success
+    #[default]
+    Success2xx,
+    // 1xx, 2xx and 3xx are a success
+    SuccessBelow400,
+    // true is a success
+    Custom(fn(StatusCode) -> bool),
+}
+
+impl StatusPolicy {
+    pub fn is_success(&self, status: StatusCode) -> bool {
+        match self {
+            StatusPolicy::Success2xx => status.is_success(),
+            StatusPolicy::SuccessBelow400 => status.as_u16() < 400,
+            StatusPolicy::Custom(f) => f(status),
+        }
+    }
+}
+
+/// Wraps a reqwest client and reports every request to the given Context.
+/// The method is used as the type and the path of the url as the name, unless a name is given.
+/// Create one per user from its Context in User::new, so every user has its own connection pool.
+#[derive(Debug, Clone)]
+pub struct RocustHttpClient {
+    client: Client,
+    base_url: Option<String>,
+    status_policy: StatusPolicy,
+}
+
+impl RocustHttpClient {
+    // uses the base url of the test config
+    pub fn new(context: &Context) -> Self {
+        Self::from_client(Client::new(), context)
+    }
+
+    // for custom reqwest clients (cookies, proxies, timeouts, ...)
+    pub fn from_client(client: Client, context: &Context) -> Self {
+        Self {
+            client,
+            base_url: context.get_base_url().map(String::from),
+            status_policy: StatusPolicy::default(),
+        }
+    }
+
+    pub fn with_base_url(self, base_url: String) -> Self {
+        Self {
+            base_url: Some(base_url),
+            ..self
+        }
+    }
+
+    pub fn with_status_policy(self, status_policy: StatusPolicy) -> Self {
+        Self {
+            status_policy,
+            ..self
+        }
+    }
+
+    pub fn get_client(&self) -> &Client {
+        &self.client
+    }
+
+    pub fn get_base_url(&self) -> Option<&str> {
+        self.base_url.as_deref()
+    }
+
+    // absolute urls are used as they are, relative ones are joined with the base url
+    fn url(&self, url: &str) -> String {
+        if url.starts_with("http://") || url.starts_with("https://") {
+            return String::from(url);
+        }
+        match &self.base_url {
+            Some(base_url) => format!(
+                "{}/{}",
+                base_url.trim_end_matches('/'),
+                url.trim_start_matches('/')
+            ),
+            None => String::from(url),
+        }
+    }
+
+    pub fn request(&self, method: Method, url: &str) -> RocustRequestBuilder {
+        RocustRequestBuilder {
+            builder: self.client.request(method, self.url(url)),
+            name: None,
+            status_policy: self.status_policy,
+        }
+    }
+
+    pub fn get(&self, url: &str) -> RocustRequestBuilder {
+        self.request(Method::GET, url)
+    }
+
+    pub fn post(&self, url: &str) -> RocustRequestBuilder {
+        self.request(Method::POST, url)
+    }
+
+    pub fn put(&self, url: &str) -> RocustRequestBuilder {
+        self.request(Method::PUT, url)
+    }
+
+    pub fn patch(&self, url: &str) -> RocustRequestBuilder {
+        self.request(Method::PATCH, url)
+    }
+
+    pub fn delete(&self, url: &str) -> RocustRequestBuilder {
+        self.request(Method::DELETE, url)
+    }
+
+    pub fn head(&self, url: &str) -> RocustRequestBuilder {
+        self.request(Method::HEAD, url)
+    }
+}
+
+#[derive(Debug)]
+pub struct RocustRequestBuilder {
+    builder: RequestBuilder,
+    name: Option<String>,
+    status_policy: StatusPolicy,
+}
+
+impl RocustRequestBuilder {
+    // groups requests with different urls under one name, e.g. /items/{id}
+    pub fn name(self, name: &str) -> Self {
+        Self {
+            name: Some(String::from(name)),
+            ..self
+        }
+    }
+
+    pub fn status_policy(self, status_policy: StatusPolicy) -> Self {
+        Self {
+            status_policy,
+            ..self
+        }
+    }
+
+    // for everything that is not wrapped here
+    pub fn with<F>(self, f: F) -> Self
+    where
+        F: FnOnce(RequestBuilder) -> RequestBuilder,
+    {
+        Self {
+            builder: f(self.builder),
+            ..self
+        }
+    }
+
+    pub fn header(self, key: HeaderName, value: HeaderValue) -> Self {
+        self.with(|builder| builder.header(key, value))
+    }
+
+    pub fn headers(self, headers: HeaderMap) -> Self {
+        self.with(|builder| builder.headers(headers))
+    }
+
+    pub fn bearer_auth(self, token: &str) -> Self {
+        self.with(|builder| builder.bearer_auth(token))
+    }
+
+    pub fn query<T: Serialize + ?Sized>(self, query: &T) -> Self {
+        self.with(|builder| builder.query(query))
+    }
+
+    pub fn json<T: Serialize + ?Sized>(self, json: &T) -> Self {
+        self.with(|builder| builder.json(json))
+    }
+
+    pub fn body<T: Into<reqwest::Body>>(self, body: T) -> Self {
+        self.with(|builder| builder.body(body))
+    }
+
+    pub fn timeout(self, timeout: Duration) -> Self {
+        self.with(|builder| builder.timeout(timeout))
+    }
+
+    /// Sends the request, reads the whole body and reports the result to the context.
+    /// The response time includes reading the body.
+    pub async fn send(self, context: &Context) -> Result<RocustResponse, reqwest::Error> {
+        let (client, request) = self.builder.build_split();
+        // nothing was sent, so nothing is reported
+        let request = request?;
+
+        let r#type = String::from(request.method().as_str());
+        let name = match self.name {
+            Some(name) => name,
+            None => String::from(request.url().path()),
+        };
+        // streamed bodies have no known size
+        let bytes_sent = request
+            .body()
+            .and_then(|body| body.as_bytes())
+            .map(|body| body.len() as u64)
+            .unwrap_or(0);
+
+        let (result, elapsed) = async {
+            let response = client.execute(request).await?;
+            let status = response.status();
+            let headers = response.headers().clone();
+            let url = response.url().clone();
+            let body = response.bytes().await?;
+            Ok::<_, reqwest::Error>(RocustResponse {
+                status,
+                headers,
+                url,
+                body,
+            })
+        }
+        .timed()
+        .await;
+
+        match &result {
+            Ok(response) => {
+                let response_info = ResponseInfo::new()
+                    .status_code(response.status.as_u16())
+                    .bytes_received(response.size() as u64)
+                    .bytes_sent(bytes_sent);
+                if self.status_policy.is_success(response.status) {
+                    context
+                        .add_success_with_info(r#type, name, elapsed.as_secs_f64(), response_info)
+                        .await;
+                } else {
+                    context
+                        .add_failure_with_details(
+                            r#type,
+                            name,
+                            Some(format!("Unexpected status code {}", response.status)),
+                            Some(elapsed.as_secs_f64()),
+                            response_info,
+                        )
+                        .await;
+                }
+            }
+            Err(error) => {
+                context.add_error(r#type, name, error.to_string()).await;
+            }
+        }
+
+        result
+    }
+}
+
+// the body is already read, so the response time is known
+#[derive(Debug, Clone)]
+pub struct RocustResponse {
+    status: StatusCode,
+    headers: HeaderMap,
+    url: Url,
+    body: Bytes,
+}
+
+impl RocustResponse {
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    pub fn bytes(&self) -> &Bytes {
+        &self.body
+    }
+
+    // size of the body in bytes
+    pub fn size(&self) -> usize {
+        self.body.len()
+    }
+
+    pub fn text(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.body)
+    }
+
+    pub fn json<T: DeserializeOwned>(&self) -> Result<T, serde_json::Error> {
+        serde_json::from_slice(&self.body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        events::EventsHandler,
+        messages::{MainMessage, ResultMessage},
+        test::{
+            controller::TestController,
+            user::{EventsUserInfo, UserController},
+        },
+    };
+    use std::sync::Arc;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+        sync::mpsc::{self, Receiver},
+    };
+    use tokio_util::sync::CancellationToken;
+
+    fn context(base_url: Option<String>) -> (Context, Receiver<MainMessage>) {
+        let (results_tx, results_rx) = mpsc::channel(100);
+        let context = Context::new(
+            Arc::new(TestController::new(CancellationToken::new())),
+            EventsHandler::new(EventsUserInfo::new(0, "HttpUser"), results_tx),
+            UserController::new(CancellationToken::new()),
+        )
+        .base_url(base_url);
+        (context, results_rx)
+    }
+
+    // answers a single request with the given status and "hello" as body, returns the base url
+    async fn serve(status: &'static str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buf = [0; 1024];
+            let _ = stream.read(&mut buf).await;
+            let response = format!(
+                "HTTP/1.1 {}\r\nContent-Length: 5\r\nConnection: close\r\n\r\nhello",
+                status
+            );
+            let _ = stream.write_all(response.as_bytes()).await;
+        });
+        format!("http://{}", addr)
+    }
+
+    #[test]
+    fn status_policies() {
+        let ok = StatusCode::OK;
+        let found = StatusCode::FOUND;
+        let not_found = StatusCode::NOT_FOUND;
+
+        assert!(StatusPolicy::Success2xx.is_success(ok));
+        assert!(!StatusPolicy::Success2xx.is_success(found));
+        assert!(StatusPolicy::SuccessBelow400.is_success(found));
+        assert!(!StatusPolicy::SuccessBelow400.is_success(not_found));
+        let custom = StatusPolicy::Custom(|status| status == StatusCode::NOT_FOUND);
+        assert!(custom.is_success(not_found));
+        assert!(!custom.is_success(ok));
+    }
+
+    #[test]
+    fn relative_urls_are_joined_with_the_base_url() {
+        let (with_base_url, _) = context(Some(String::from("http://localhost:8080/")));
+        let client = RocustHttpClient::new(&with_base_url);
+
+        assert_eq!(client.get_base_url(), Some("http://localhost:8080/"));
+        assert_eq!(client.url("/items"), "http://localhost:8080/items");
+        assert_eq!(client.url("items"), "http://localhost:8080/items");
+        assert_eq!(client.url("https://example.com/"), "https://example.com/");
+        let (without_base_url, _) = context(None);
+        let client = RocustHttpClient::new(&without_base_url);
+        assert_eq!(client.url("/items"), "/items");
+    }
+
+    #[tokio::test]
+    async fn response_is_reported_as_success() {
+        let (context, mut results_rx) = context(Some(serve("200 OK").await));
+        let client = RocustHttpClient::new(&context);
+
+        let response = client
+            .get("/items/1")
+            .name("/items/{id}")
+            .send(&context)
+            .await
+            .unwrap();
+
+        assert_eq!(response.text(), "hello");
+        match results_rx.try_recv() {
+            Ok(MainMessage::ResultMessage(ResultMessage::Success(msg))) => {
+                assert_eq!(msg.endpoint_type_name.r#type, "GET");
+                assert_eq!(msg.endpoint_type_name.name, "/items/{id}");
+                assert_eq!(msg.response_info.status_code, Some(200));
+                assert_eq!(msg.response_info.bytes_received, Some(5));
+            }
+            _ => panic!("expected a success"),
+        }
+    }
+
+    #[tokio::test]
+    async fn unexpected_status_is_reported_as_failure() {
+        let (context, mut results_rx) = context(Some(serve("503 Service Unavailable").await));
+        let client = RocustHttpClient::new(&context);
+
+        let response = client.post("/cart").body("item").send(&context).await;
+
+        assert_eq!(response.unwrap().status(), StatusCode::SERVICE_UNAVAILABLE);
+        match results_rx.try_recv() {
+            Ok(MainMessage::ResultMessage(ResultMessage::Failure(msg))) => {
+                assert_eq!(msg.endpoint_type_name.r#type, "POST");
+                assert_eq!(msg.endpoint_type_name.name, "/cart");
+                assert_eq!(
+                    msg.reason.as_deref(),
+                    Some("Unexpected status code 503 Service Unavailable")
+                );
+                assert_eq!(msg.response_info.status_code, Some(503));
+                assert_eq!(msg.response_info.bytes_sent, Some(4));
+                assert!(msg.response_time.is_some());
+            }
+            _ => panic!("expected a failure"),
+        }
+    }
+
+    #[tokio::test]
+    async fn connection_error_is_reported_as_error() {
+        // nothing listens on the port after the listener is dropped
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        drop(listener);
+        let (context, mut results_rx) = context(Some(base_url));
+        let client = RocustHttpClient::new(&context);
+
+        let response = client.get("/items").send(&context).await;
+
+        assert!(response.is_err());
+        match results_rx.try_recv() {
+            Ok(MainMessage::ResultMessage(ResultMessage::Error(msg))) => {
+                assert_eq!(msg.endpoint_type_name.r#type, "GET");
+                assert_eq!(msg.endpoint_type_name.name, "/items");
+                assert!(!msg.error.is_empty());
+            }
+            _ => panic!("expected an error"),
+        }
+    }
+
+    #[tokio::test]
+    async fn invalid_url_is_not_reported() {
+        let (context, mut results_rx) = context(None);
+        let client = RocustHttpClient::new(&context);
+
+        let response = client.get("/items").send(&context).await;
+
+        assert!(response.is_err());
+        assert!(results_rx.try_recv().is_err());
+    }
+}
@@ -1,17 +1,33 @@
+pub mod checks;
+pub mod custom_metrics;
+pub mod errors;
 pub mod events;
 pub(crate) mod fs;
 pub mod futures;
+pub(crate) mod histogram;
+#[cfg(feature = "http")]
+pub mod http;
 pub(crate) mod messages;
+#[cfg(feature = "otel")]
+pub mod otel;
+pub mod panics;
 pub(crate) mod prometheus_exporter;
+pub(crate) mod prometheus_pusher;
 pub mod results;
 pub(crate) mod server;
+pub mod task_stats;
 pub mod tasks;
 pub mod test;
+pub mod thresholds;
 pub mod traits;
 pub(crate) mod utils;
 
-pub use test::{config::TestConfig, user::context::Context, Test};
-pub use traits::{Shared, User};
+pub use test::{
+    config::TestConfig,
+    user::context::{Context, Outcome},
+    Test,
+};
+pub use traits::{Shared, StartError, User};
 
 #[macro_export]
 macro_rules! run {
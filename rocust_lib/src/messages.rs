@@ -1,13 +1,30 @@
-use crate::{results::EndpointTypeName, tasks::EventsTaskInfo, test::user::EventsUserInfo};
+use crate::{
+    custom_metrics::MetricUpdate,
+    results::{EndpointTypeName, ResponseInfo},
+    tasks::EventsTaskInfo,
+    test::user::EventsUserInfo,
+};
+#[cfg(feature = "otel")]
+use opentelemetry::trace::SpanContext;
 
 pub enum MainMessage {
     ResultMessage(ResultMessage),
     UserSpawned(UserSpawnedMessage),
+    UserFailedToStart(UserFailedToStartMessage),
     UserSelfStopped(UserSelfStoppedMessage),
     UserFinished(UserFinishedMessage),
     UserPanicked(UserPanickedMessage),
+    UserRestarted(UserRestartedMessage),
     UserUnknownStatus(UserUnknownStatusMessage),
     TaskExecuted(TaskExecutedMessage),
+    TaskTimedOut(TaskTimedOutMessage),
+    Check(CheckMessage),
+    Metric(MetricMessage),
+}
+
+pub struct UserFailedToStartMessage {
+    pub(crate) user_info: EventsUserInfo,
+    pub(crate) error: String,
 }
 
 pub struct UserSelfStoppedMessage {
@@ -20,7 +37,13 @@ pub struct UserFinishedMessage {
 
 pub struct UserPanickedMessage {
     pub(crate) user_info: EventsUserInfo,
-    pub(crate) _error: String,
+    pub(crate) message: String,
+    pub(crate) task_name: Option<&'static str>,
+    pub(crate) backtrace: Option<String>,
+}
+
+pub struct UserRestartedMessage {
+    pub(crate) user_info: EventsUserInfo,
 }
 
 pub struct UserUnknownStatusMessage {
@@ -30,6 +53,27 @@ pub struct UserUnknownStatusMessage {
 pub struct TaskExecutedMessage {
     pub(crate) user_info: EventsUserInfo,
     pub(crate) task_info: EventsTaskInfo,
+    // in seconds, sleeping between tasks is not included
+    pub(crate) duration: f64,
+}
+
+pub struct TaskTimedOutMessage {
+    pub(crate) user_info: EventsUserInfo,
+    pub(crate) task_info: EventsTaskInfo,
+    pub(crate) timeout_in_millis: u64,
+}
+
+pub struct CheckMessage {
+    pub(crate) user_info: EventsUserInfo,
+    pub(crate) task_info: Option<EventsTaskInfo>,
+    // name and passed
+    pub(crate) checks: Vec<(String, bool)>,
+}
+
+pub struct MetricMessage {
+    pub(crate) user_info: EventsUserInfo,
+    pub(crate) name: String,
+    pub(crate) update: MetricUpdate,
 }
 
 pub struct UserSpawnedMessage {
@@ -44,17 +88,81 @@ pub enum ResultMessage {
 
 pub struct SuccessResultMessage {
     pub(crate) user_info: EventsUserInfo,
+    pub(crate) task_info: Option<EventsTaskInfo>,
     pub(crate) endpoint_type_name: EndpointTypeName,
+    // milliseconds since the epoch, taken when the user reported the result
+    pub(crate) timestamp: u64,
     pub(crate) response_time: f64,
+    pub(crate) response_info: ResponseInfo,
+    // set by Context::traced for the first result of its future
+    #[cfg(feature = "otel")]
+    pub(crate) span_context: Option<SpanContext>,
 }
 
 pub struct FailureResultMessage {
     pub(crate) user_info: EventsUserInfo,
+    pub(crate) task_info: Option<EventsTaskInfo>,
     pub(crate) endpoint_type_name: EndpointTypeName,
+    // milliseconds since the epoch, taken when the user reported the result
+    pub(crate) timestamp: u64,
+    pub(crate) reason: Option<String>,
+    pub(crate) response_time: Option<f64>,
+    pub(crate) response_info: ResponseInfo,
+    // set by Context::traced for the first result of its future
+    #[cfg(feature = "otel")]
+    pub(crate) span_context: Option<SpanContext>,
 }
 
 pub struct ErrorResultMessage {
     pub(crate) user_info: EventsUserInfo,
+    pub(crate) task_info: Option<EventsTaskInfo>,
     pub(crate) endpoint_type_name: EndpointTypeName,
+    // milliseconds since the epoch, taken when the user reported the result
+    pub(crate) timestamp: u64,
     pub(crate) error: String,
+    // set by Context::traced for the first result of its future
+    #[cfg(feature = "otel")]
+    pub(crate) span_context: Option<SpanContext>,
+}
+
+// a success, a failure and an error of the same endpoint, used by the tests of the writers and exporters
+#[cfg(test)]
+pub(crate) fn test_results(endpoint_name: &str) -> Vec<ResultMessage> {
+    let user_info = EventsUserInfo::new(3, "ShopUser");
+    let endpoint_type_name = EndpointTypeName {
+        r#type: String::from("GET"),
+        name: String::from(endpoint_name),
+    };
+    vec![
+        ResultMessage::Success(SuccessResultMessage {
+            user_info: user_info.clone(),
+            task_info: None,
+            endpoint_type_name: endpoint_type_name.clone(),
+            timestamp: 1000,
+            response_time: 0.25,
+            response_info: ResponseInfo::new(),
+            #[cfg(feature = "otel")]
+            span_context: None,
+        }),
+        ResultMessage::Failure(FailureResultMessage {
+            user_info: user_info.clone(),
+            task_info: None,
+            endpoint_type_name: endpoint_type_name.clone(),
+            timestamp: 1000,
+            reason: Some(String::from("status 500, retry")),
+            response_time: Some(0.5),
+            response_info: ResponseInfo::new(),
+            #[cfg(feature = "otel")]
+            span_context: None,
+        }),
+        ResultMessage::Error(ErrorResultMessage {
+            user_info,
+            task_info: None,
+            endpoint_type_name,
+            timestamp: 1000,
+            error: String::from("connection refused"),
+            #[cfg(feature = "otel")]
+            span_context: None,
+        }),
+    ]
 }
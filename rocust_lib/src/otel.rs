@@ -0,0 +1,514 @@
+use crate::{messages::ResultMessage, test::config::OtelOutput, Context};
+use opentelemetry::{
+    metrics::{Counter, Histogram, MeterProvider as _, MetricsError, Unit},
+    propagation::TextMapPropagator,
+    trace::{
+        SpanContext, SpanKind, Status, TraceContextExt, TraceError, TraceFlags, TraceState,
+        Tracer as _, TracerProvider as _,
+    },
+    Context as OtelContext, KeyValue,
+};
+use opentelemetry_otlp::{SpanExporterBuilder, WithExportConfig};
+use opentelemetry_sdk::{
+    metrics::{
+        reader::{DefaultAggregationSelector, DefaultTemporalitySelector},
+        MeterProvider, PeriodicReader,
+    },
+    propagation::TraceContextPropagator,
+    runtime,
+    trace::{Config, IdGenerator, RandomIdGenerator, Tracer, TracerProvider},
+    Resource,
+};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    future::Future,
+    time::{Duration, SystemTime},
+};
+use thiserror::Error as ThisError;
+
+#[derive(Debug, ThisError)]
+pub enum OtelError {
+    #[error("Error creating metrics exporter: {0}")]
+    MetricsError(#[from] MetricsError),
+    #[error("Error creating span exporter: {0}")]
+    TraceError(#[from] TraceError),
+}
+
+// exported on a separate thread, so the test does not wait for the collector
+pub(crate) struct OtelExporter {
+    meter_provider: MeterProvider,
+    tracer_provider: Option<TracerProvider>,
+    tracer: Option<Tracer>,
+    requests_counter: Counter<u64>,
+    failures_counter: Counter<u64>,
+    errors_counter: Counter<u64>,
+    response_time_histogram: Histogram<f64>,
+}
+
+impl OtelExporter {
+    pub(crate) fn new(
+        otel_output: &OtelOutput,
+        export_interval: Duration,
+    ) -> Result<Self, OtelError> {
+        let resource = Resource::new(vec![KeyValue::new(
+            "service.name",
+            otel_output.service_name.clone(),
+        )]);
+
+        let metrics_exporter = opentelemetry_otlp::new_exporter()
+            .http()
+            .with_endpoint(&otel_output.endpoint)
+            .build_metrics_exporter(
+                Box::new(DefaultAggregationSelector::new()),
+                Box::new(DefaultTemporalitySelector::new()),
+            )?;
+        let reader = PeriodicReader::builder(metrics_exporter, runtime::TokioCurrentThread)
+            .with_interval(export_interval)
+            .build();
+        let meter_provider = MeterProvider::builder()
+            .with_reader(reader)
+            .with_resource(resource.clone())
+            .build();
+
+        let tracer_provider = if otel_output.request_spans {
+            let span_exporter = SpanExporterBuilder::from(
+                opentelemetry_otlp::new_exporter()
+                    .http()
+                    .with_endpoint(&otel_output.endpoint),
+            )
+            .build_span_exporter()?;
+            Some(
+                TracerProvider::builder()
+                    .with_batch_exporter(span_exporter, runtime::TokioCurrentThread)
+                    .with_config(Config::default().with_resource(resource))
+                    .build(),
+            )
+        } else {
+            None
+        };
+
+        Ok(Self::with_providers(meter_provider, tracer_provider))
+    }
+
+    fn with_providers(
+        meter_provider: MeterProvider,
+        tracer_provider: Option<TracerProvider>,
+    ) -> Self {
+        let meter = meter_provider.meter("rocust");
+        let tracer = tracer_provider
+            .as_ref()
+            .map(|tracer_provider| tracer_provider.tracer("rocust"));
+        Self {
+            requests_counter: meter
+                .u64_counter("rocust.requests")
+                .with_description("Total number of requests, errors included")
+                .init(),
+            failures_counter: meter
+                .u64_counter("rocust.failures")
+                .with_description("Total number of failed requests")
+                .init(),
+            errors_counter: meter
+                .u64_counter("rocust.errors")
+                .with_description("Total number of errors")
+                .init(),
+            response_time_histogram: meter
+                .f64_histogram("rocust.response_time")
+                .with_description("Response times of successful requests")
+                .with_unit(Unit::new("s"))
+                .init(),
+            meter_provider,
+            tracer_provider,
+            tracer,
+        }
+    }
+
+    pub(crate) fn add_result(&self, result_msg: &ResultMessage) {
+        let (user_info, task_info, endpoint_type_name, status_code) = match result_msg {
+            ResultMessage::Success(msg) => (
+                &msg.user_info,
+                &msg.task_info,
+                &msg.endpoint_type_name,
+                msg.response_info.status_code,
+            ),
+            ResultMessage::Failure(msg) => (
+                &msg.user_info,
+                &msg.task_info,
+                &msg.endpoint_type_name,
+                msg.response_info.status_code,
+            ),
+            ResultMessage::Error(msg) => (
+                &msg.user_info,
+                &msg.task_info,
+                &msg.endpoint_type_name,
+                None,
+            ),
+        };
+
+        let mut attributes = vec![
+            KeyValue::new("rocust.user.name", user_info.name),
+            KeyValue::new("rocust.endpoint.type", endpoint_type_name.r#type.clone()),
+            KeyValue::new("rocust.endpoint.name", endpoint_type_name.name.clone()),
+        ];
+        if let Some(task_info) = task_info {
+            attributes.push(KeyValue::new("rocust.task.name", task_info.name));
+        }
+        if let Some(status_code) = status_code {
+            attributes.push(KeyValue::new(
+                "http.response.status_code",
+                status_code as i64,
+            ));
+        }
+
+        // errors are requests too, same as in the prometheus exporter
+        self.requests_counter.add(1, &attributes);
+        match result_msg {
+            ResultMessage::Success(msg) => {
+                self.response_time_histogram
+                    .record(msg.response_time, &attributes);
+            }
+            ResultMessage::Failure(_) => {
+                self.failures_counter.add(1, &attributes);
+            }
+            ResultMessage::Error(_) => {
+                self.errors_counter.add(1, &attributes);
+            }
+        }
+
+        if let Some(tracer) = &self.tracer {
+            attributes.push(KeyValue::new("rocust.user.id", user_info.id as i64));
+            self.add_span(tracer, result_msg, attributes);
+        }
+    }
+
+    // the result is reported after the request, so the span is created with its start and end time
+    fn add_span(&self, tracer: &Tracer, result_msg: &ResultMessage, attributes: Vec<KeyValue>) {
+        let (endpoint_type_name, response_time, status, span_context) = match result_msg {
+            ResultMessage::Success(msg) => (
+                &msg.endpoint_type_name,
+                Some(msg.response_time),
+                Status::Ok,
+                &msg.span_context,
+            ),
+            ResultMessage::Failure(msg) => (
+                &msg.endpoint_type_name,
+                msg.response_time,
+                Status::error(msg.reason.clone().unwrap_or_default()),
+                &msg.span_context,
+            ),
+            ResultMessage::Error(msg) => (
+                &msg.endpoint_type_name,
+                None,
+                Status::error(msg.error.clone()),
+                &msg.span_context,
+            ),
+        };
+
+        let end_time = SystemTime::now();
+        let start_time = response_time
+            .and_then(|response_time| Duration::try_from_secs_f64(response_time).ok())
+            .and_then(|response_time| end_time.checked_sub(response_time))
+            .unwrap_or(end_time);
+
+        let mut span_builder = tracer
+            .span_builder(format!(
+                "{} {}",
+                endpoint_type_name.r#type, endpoint_type_name.name
+            ))
+            .with_kind(SpanKind::Client)
+            .with_start_time(start_time)
+            .with_attributes(attributes)
+            .with_status(status);
+        if let Some(span_context) = span_context {
+            span_builder.trace_id = Some(span_context.trace_id());
+            span_builder.span_id = Some(span_context.span_id());
+        }
+        let mut span = span_builder.start(tracer);
+        opentelemetry::trace::Span::end_with_timestamp(&mut span, end_time);
+    }
+
+    // exports the remaining results, blocks until the collector responded or timed out
+    pub(crate) async fn shutdown(self) {
+        let result = tokio::task::spawn_blocking(move || {
+            if let Some(tracer_provider) = self.tracer_provider {
+                for result in tracer_provider.force_flush() {
+                    if let Err(error) = result {
+                        tracing::error!(%error, "Error exporting spans");
+                    }
+                }
+            }
+            if let Err(error) = self.meter_provider.shutdown() {
+                tracing::error!(%error, "Error exporting metrics");
+            }
+        })
+        .await;
+        if let Err(error) = result {
+            tracing::error!(%error, "Error joining otel exporter");
+        }
+    }
+}
+
+tokio::task_local! {
+    // set while the future of Context::traced is running, taken by the first result it reports
+    static CURRENT_SPAN_CONTEXT: RefCell<Option<SpanContext>>;
+}
+
+// the span context of the running Context::traced, if no result has taken it yet
+pub(crate) fn take_current_span_context() -> Option<SpanContext> {
+    CURRENT_SPAN_CONTEXT
+        .try_with(|span_context| span_context.borrow_mut().take())
+        .ok()
+        .flatten()
+}
+
+/// The trace and span id sent with a request, see `Context::trace_context_headers`.
+#[derive(Debug, Clone)]
+pub struct TraceContext {
+    span_context: SpanContext,
+}
+
+impl Context {
+    /// Returns the W3C trace context headers (`traceparent`) to send with a request and the trace context they contain.
+    /// Pass the trace context to `Context::traced` with the future that sends the request and reports its result,
+    /// the span of that result gets the same trace and span id, so the spans of the server are linked to it.
+    /// Requires `OtelOutput::request_spans`.
+    pub fn trace_context_headers(&self) -> (HashMap<String, String>, TraceContext) {
+        let id_generator = RandomIdGenerator::default();
+        let span_context = SpanContext::new(
+            id_generator.new_trace_id(),
+            id_generator.new_span_id(),
+            TraceFlags::SAMPLED,
+            false,
+            TraceState::default(),
+        );
+        let mut headers = HashMap::new();
+        TraceContextPropagator::new().inject_context(
+            &OtelContext::new().with_remote_span_context(span_context.clone()),
+            &mut headers,
+        );
+        (headers, TraceContext { span_context })
+    }
+
+    /// Runs the given future, the first result it reports gets the trace and span id of the trace context.
+    /// Results reported outside of the future, and any further results of the future, are not linked to it.
+    pub async fn traced<F>(&self, trace_context: TraceContext, future: F) -> F::Output
+    where
+        F: Future,
+    {
+        CURRENT_SPAN_CONTEXT
+            .scope(RefCell::new(Some(trace_context.span_context)), future)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        events::EventsHandler,
+        messages::{test_results, MainMessage},
+        test::{
+            controller::TestController,
+            user::{EventsUserInfo, UserController},
+        },
+    };
+    use axum::{extract::State, http::Uri, Router};
+    use opentelemetry::{
+        metrics::Result as MetricsResult,
+        trace::{SpanId, TraceId},
+    };
+    use opentelemetry_sdk::metrics::{
+        data::{ResourceMetrics, Sum, Temporality},
+        reader::{AggregationSelector, MetricReader, TemporalitySelector},
+        Aggregation, InstrumentKind, ManualReader, Pipeline,
+    };
+    use std::{
+        net::TcpListener,
+        sync::{Arc, Mutex, Weak},
+    };
+    use tokio::sync::mpsc;
+    use tokio_util::sync::CancellationToken;
+
+    async fn record(
+        State(paths): State<Arc<Mutex<Vec<String>>>>,
+        uri: Uri,
+        body: axum::body::Bytes,
+    ) {
+        if !body.is_empty() {
+            paths.lock().unwrap().push(String::from(uri.path()));
+        }
+    }
+
+    // a local collector that records the paths of the exports
+    fn start_stand_in() -> (String, Arc<Mutex<Vec<String>>>) {
+        let paths = Arc::new(Mutex::new(Vec::new()));
+        let app = Router::new().fallback(record).with_state(paths.clone());
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = axum::Server::from_tcp(listener)
+            .unwrap()
+            .serve(app.into_make_service());
+        tokio::spawn(server);
+        (format!("http://{}", addr), paths)
+    }
+
+    // the meter provider takes the reader, the test keeps a handle to collect the metrics
+    #[derive(Debug, Clone)]
+    struct SharedReader(Arc<ManualReader>);
+
+    impl AggregationSelector for SharedReader {
+        fn aggregation(&self, kind: InstrumentKind) -> Aggregation {
+            self.0.aggregation(kind)
+        }
+    }
+
+    impl TemporalitySelector for SharedReader {
+        fn temporality(&self, kind: InstrumentKind) -> Temporality {
+            self.0.temporality(kind)
+        }
+    }
+
+    impl MetricReader for SharedReader {
+        fn register_pipeline(&self, pipeline: Weak<Pipeline>) {
+            self.0.register_pipeline(pipeline)
+        }
+
+        fn collect(&self, rm: &mut ResourceMetrics) -> MetricsResult<()> {
+            self.0.collect(rm)
+        }
+
+        fn force_flush(&self) -> MetricsResult<()> {
+            self.0.force_flush()
+        }
+
+        fn shutdown(&self) -> MetricsResult<()> {
+            self.0.shutdown()
+        }
+    }
+
+    #[test]
+    fn errors_are_counted_as_requests() {
+        let reader = SharedReader(Arc::new(ManualReader::builder().build()));
+        let meter_provider = MeterProvider::builder().with_reader(reader.clone()).build();
+        let otel_exporter = OtelExporter::with_providers(meter_provider, None);
+
+        for result_msg in &test_results("/items") {
+            otel_exporter.add_result(result_msg);
+        }
+
+        let mut resource_metrics = ResourceMetrics {
+            resource: Resource::empty(),
+            scope_metrics: Vec::new(),
+        };
+        reader.collect(&mut resource_metrics).unwrap();
+        let total = |name: &str| -> u64 {
+            resource_metrics
+                .scope_metrics
+                .iter()
+                .flat_map(|scope_metrics| &scope_metrics.metrics)
+                .filter(|metric| metric.name == name)
+                .filter_map(|metric| metric.data.as_any().downcast_ref::<Sum<u64>>())
+                .flat_map(|sum| &sum.data_points)
+                .map(|data_point| data_point.value)
+                .sum()
+        };
+        // same as the prometheus exporter
+        assert_eq!(total("rocust.requests"), 3);
+        assert_eq!(total("rocust.failures"), 1);
+        assert_eq!(total("rocust.errors"), 1);
+    }
+
+    #[tokio::test]
+    async fn results_are_exported_on_shutdown() {
+        let (endpoint, paths) = start_stand_in();
+        let otel_output = OtelOutput::new(endpoint).request_spans(true);
+        let otel_exporter = OtelExporter::new(&otel_output, Duration::from_secs(60)).unwrap();
+
+        let mut results = test_results("/items");
+        if let ResultMessage::Success(msg) = &mut results[0] {
+            msg.span_context = Some(SpanContext::new(
+                TraceId::from_bytes(1u128.to_be_bytes()),
+                SpanId::from_bytes(2u64.to_be_bytes()),
+                TraceFlags::SAMPLED,
+                false,
+                TraceState::default(),
+            ));
+        }
+        for result_msg in &results {
+            otel_exporter.add_result(result_msg);
+        }
+        otel_exporter.shutdown().await;
+
+        let mut paths = paths.lock().unwrap().clone();
+        // spans may be exported in more than one batch
+        paths.sort();
+        paths.dedup();
+        assert_eq!(paths, vec!["/v1/metrics", "/v1/traces"]);
+    }
+
+    #[tokio::test]
+    async fn only_the_first_result_of_the_traced_future_gets_the_span_context() {
+        let (results_tx, mut results_rx) = mpsc::channel(100);
+        let context = Context::new(
+            Arc::new(TestController::new(CancellationToken::new())),
+            EventsHandler::new(EventsUserInfo::new(0, "ShopUser"), results_tx),
+            UserController::new(CancellationToken::new()),
+        );
+
+        let (headers, trace_context) = context.trace_context_headers();
+        let (_, unused_trace_context) = context.trace_context_headers();
+        drop(unused_trace_context);
+        context
+            .add_success(String::from("GET"), String::from("/before"), 0.1)
+            .await;
+        context
+            .traced(trace_context.clone(), async {
+                context
+                    .add_error(
+                        String::from("GET"),
+                        String::from("/items"),
+                        String::from("connection refused"),
+                    )
+                    .await;
+                context
+                    .add_success(String::from("GET"), String::from("/other"), 0.1)
+                    .await;
+            })
+            .await;
+        context
+            .add_failure(String::from("GET"), String::from("/after"))
+            .await;
+
+        let mut span_contexts = Vec::new();
+        while let Ok(message) = results_rx.try_recv() {
+            match message {
+                MainMessage::ResultMessage(ResultMessage::Success(msg)) => {
+                    span_contexts.push((msg.endpoint_type_name.name, msg.span_context))
+                }
+                MainMessage::ResultMessage(ResultMessage::Failure(msg)) => {
+                    span_contexts.push((msg.endpoint_type_name.name, msg.span_context))
+                }
+                MainMessage::ResultMessage(ResultMessage::Error(msg)) => {
+                    span_contexts.push((msg.endpoint_type_name.name, msg.span_context))
+                }
+                _ => {}
+            }
+        }
+        assert_eq!(
+            span_contexts,
+            vec![
+                (String::from("/before"), None),
+                (
+                    String::from("/items"),
+                    Some(trace_context.span_context.clone())
+                ),
+                (String::from("/other"), None),
+                (String::from("/after"), None),
+            ]
+        );
+
+        let traceparent = headers.get("traceparent").unwrap();
+        assert!(traceparent.contains(&trace_context.span_context.trace_id().to_string()));
+        assert!(traceparent.contains(&trace_context.span_context.span_id().to_string()));
+    }
+}
@@ -0,0 +1,233 @@
+use serde::Serialize;
+use std::{
+    any::Any,
+    backtrace::Backtrace,
+    cmp::Reverse,
+    collections::{BTreeSet, HashMap},
+    panic,
+    sync::{Arc, Mutex, Once},
+};
+
+tokio::task_local! {
+    // set for every user task, so the panic hook knows where to put the backtrace
+    static PANIC_SLOT: Arc<Mutex<PanicSlot>>;
+}
+
+static INSTALL_BACKTRACE_HOOK: Once = Once::new();
+
+// shared between a user and its supervisor, the user is gone when the supervisor reads it
+#[derive(Debug, Default)]
+pub(crate) struct PanicSlot {
+    pub(crate) task_name: Option<&'static str>,
+    pub(crate) backtrace: Option<String>,
+}
+
+impl PanicSlot {
+    pub(crate) fn set_task_name(slot: &Mutex<PanicSlot>, task_name: Option<&'static str>) {
+        if let Ok(mut slot) = slot.lock() {
+            slot.task_name = task_name;
+        }
+    }
+
+    pub(crate) fn take(slot: &Mutex<PanicSlot>) -> PanicSlot {
+        match slot.lock() {
+            Ok(mut slot) => std::mem::take(&mut *slot),
+            Err(_) => PanicSlot::default(),
+        }
+    }
+
+    pub(crate) async fn scope<F>(slot: Arc<Mutex<PanicSlot>>, f: F) -> F::Output
+    where
+        F: std::future::Future,
+    {
+        PANIC_SLOT.scope(slot, f).await
+    }
+}
+
+// the hook runs on the panicking thread while the user task is still being polled, so the task local is available.
+// the previous hook is still called, so panics are printed as usual
+pub(crate) fn install_backtrace_hook() {
+    INSTALL_BACKTRACE_HOOK.call_once(|| {
+        let previous_hook = panic::take_hook();
+        panic::set_hook(Box::new(move |panic_info| {
+            let _ = PANIC_SLOT.try_with(|slot| {
+                // try_lock, we never want to block or panic in a panic hook
+                if let Ok(mut slot) = slot.try_lock() {
+                    slot.backtrace = Some(Backtrace::force_capture().to_string());
+                }
+            });
+            previous_hook(panic_info);
+        }));
+    });
+}
+
+pub(crate) fn get_panic_message(payload: Box<dyn Any + Send>) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        return message.to_string();
+    }
+    if let Some(message) = payload.downcast_ref::<String>() {
+        return message.clone();
+    }
+    String::from("Box<dyn Any>")
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PanicStats {
+    message: String,
+    occurrences: u64,
+    user_names: BTreeSet<&'static str>,
+    task_names: BTreeSet<&'static str>,
+    // the backtrace of the first occurrence, if backtraces are captured
+    backtrace: Option<String>,
+}
+
+impl PanicStats {
+    fn new(message: String) -> Self {
+        Self {
+            message,
+            occurrences: 0,
+            user_names: BTreeSet::new(),
+            task_names: BTreeSet::new(),
+            backtrace: None,
+        }
+    }
+
+    pub fn get_message(&self) -> &str {
+        &self.message
+    }
+
+    pub fn get_occurrences(&self) -> u64 {
+        self.occurrences
+    }
+
+    pub fn get_user_names(&self) -> &BTreeSet<&'static str> {
+        &self.user_names
+    }
+
+    pub fn get_task_names(&self) -> &BTreeSet<&'static str> {
+        &self.task_names
+    }
+
+    pub fn get_backtrace(&self) -> Option<&str> {
+        self.backtrace.as_deref()
+    }
+}
+
+// panics deduplicated by message
+#[derive(Debug, Default, Clone)]
+pub struct AllPanics {
+    panics: HashMap<String, PanicStats>,
+}
+
+impl AllPanics {
+    pub(crate) fn add_panic(
+        &mut self,
+        message: &str,
+        user_name: &'static str,
+        task_name: Option<&'static str>,
+        backtrace: Option<&str>,
+    ) {
+        let panic_stats = self
+            .panics
+            .entry(message.to_string())
+            .or_insert_with(|| PanicStats::new(message.to_string()));
+
+        panic_stats.occurrences += 1;
+        panic_stats.user_names.insert(user_name);
+        if let Some(task_name) = task_name {
+            panic_stats.task_names.insert(task_name);
+        }
+        if panic_stats.backtrace.is_none() {
+            panic_stats.backtrace = backtrace.map(String::from);
+        }
+    }
+
+    // most frequent first
+    pub fn get_panics(&self) -> Vec<&PanicStats> {
+        let mut panics: Vec<&PanicStats> = self.panics.values().collect();
+        panics.sort_by_key(|panic_stats| Reverse(panic_stats.occurrences));
+        panics
+    }
+
+    pub(crate) fn ser_panics(&self) -> Vec<PanicStats> {
+        self.get_panics().into_iter().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn panic_message_from_payload() {
+        assert_eq!(
+            get_panic_message(Box::new("static message")),
+            "static message"
+        );
+        assert_eq!(
+            get_panic_message(Box::new(String::from("formatted message"))),
+            "formatted message"
+        );
+        assert_eq!(get_panic_message(Box::new(42)), "Box<dyn Any>");
+    }
+
+    #[test]
+    fn panics_are_deduplicated_by_message() {
+        let mut all_panics = AllPanics::default();
+        all_panics.add_panic("boom", "ShopUser", Some("checkout"), None);
+        all_panics.add_panic("boom", "AdminUser", Some("login"), Some("first backtrace"));
+        all_panics.add_panic("boom", "ShopUser", None, Some("second backtrace"));
+        all_panics.add_panic("index out of bounds", "ShopUser", None, None);
+
+        let panics = all_panics.get_panics();
+        assert_eq!(panics.len(), 2);
+        // most frequent first
+        let boom = panics[0];
+        assert_eq!(boom.get_message(), "boom");
+        assert_eq!(boom.get_occurrences(), 3);
+        assert_eq!(
+            boom.get_user_names().iter().copied().collect::<Vec<_>>(),
+            vec!["AdminUser", "ShopUser"]
+        );
+        assert_eq!(
+            boom.get_task_names().iter().copied().collect::<Vec<_>>(),
+            vec!["checkout", "login"]
+        );
+        // only the first captured backtrace is kept
+        assert_eq!(boom.get_backtrace(), Some("first backtrace"));
+        assert_eq!(panics[1].get_occurrences(), 1);
+    }
+
+    #[tokio::test]
+    async fn hook_captures_backtrace_in_the_panic_slot() {
+        install_backtrace_hook();
+        let slot = Arc::new(Mutex::new(PanicSlot::default()));
+        let task_slot = slot.clone();
+
+        let result = tokio::spawn(PanicSlot::scope(slot.clone(), async move {
+            PanicSlot::set_task_name(&task_slot, Some("checkout"));
+            panic!("boom");
+        }))
+        .await;
+
+        let error = result.err().unwrap();
+        assert_eq!(get_panic_message(error.into_panic()), "boom");
+        let slot = PanicSlot::take(&slot);
+        assert_eq!(slot.task_name, Some("checkout"));
+        assert!(slot.backtrace.is_some());
+    }
+
+    #[tokio::test]
+    async fn panics_outside_of_a_slot_are_ignored_by_the_hook() {
+        install_backtrace_hook();
+        let slot = Arc::new(Mutex::new(PanicSlot::default()));
+
+        let result = tokio::spawn(async {
+            panic!("boom");
+        })
+        .await;
+
+        assert!(result.is_err());
+        assert!(PanicSlot::take(&slot).backtrace.is_none());
+    }
+}
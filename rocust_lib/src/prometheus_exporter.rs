@@ -1,55 +1,238 @@
+use crate::{
+    custom_metrics::{AllMetrics, Metric},
+    results::ResponseInfo,
+    test::config::PrometheusLabelPolicy,
+    TestConfig,
+};
 use prometheus_client::{
-    encoding::{text, EncodeLabelSet},
-    metrics::{counter::Counter, family::Family, gauge::Gauge},
+    encoding::{text, EncodeLabelSet, LabelSetEncoder},
+    metrics::{
+        counter::Counter,
+        family::{Family, MetricConstructor},
+        gauge::Gauge,
+        histogram::{exponential_buckets, Histogram},
+        info::Info,
+    },
     registry::Registry,
 };
-use std::{fmt::Error as FmtError, sync::atomic::AtomicU64};
+use std::{
+    borrow::Cow,
+    collections::HashSet,
+    fmt::Error as FmtError,
+    sync::{atomic::AtomicU64, Mutex},
+    time::Duration,
+};
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash, EncodeLabelSet)]
+// exported instead of new endpoint names once PrometheusLabelPolicy::max_endpoint_names is reached
+pub(crate) const OTHER_ENDPOINT_NAME: &str = "other";
+
+// labels with a user id are encoded by hand, so the user id can be omitted. see PrometheusLabelPolicy::drop_user_id
+type Labels<'a> = Vec<(&'static str, Cow<'a, str>)>;
+
+fn push_user_id(labels: &mut Labels, user_id: Option<u64>) {
+    if let Some(user_id) = user_id {
+        labels.push(("user_id", Cow::from(user_id.to_string())));
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub(crate) struct RequestLabel {
     pub endpoint_type: String,
     pub endpoint_name: String,
-    pub user_id: u64,
+    pub user_id: Option<u64>,
     pub user_name: &'static str,
+    // empty if the result was not reported from a task
+    pub task_name: &'static str,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash, EncodeLabelSet)]
+impl EncodeLabelSet for RequestLabel {
+    fn encode(&self, encoder: LabelSetEncoder) -> Result<(), FmtError> {
+        let mut labels: Labels = vec![
+            ("endpoint_type", Cow::from(&self.endpoint_type)),
+            ("endpoint_name", Cow::from(&self.endpoint_name)),
+        ];
+        push_user_id(&mut labels, self.user_id);
+        labels.push(("user_name", Cow::from(self.user_name)));
+        labels.push(("task_name", Cow::from(self.task_name)));
+        labels.encode(encoder)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct StatusCodeLabel {
+    pub endpoint_type: String,
+    pub endpoint_name: String,
+    pub user_id: Option<u64>,
+    pub user_name: &'static str,
+    pub task_name: &'static str,
+    pub status_code: u16,
+}
+
+impl EncodeLabelSet for StatusCodeLabel {
+    fn encode(&self, encoder: LabelSetEncoder) -> Result<(), FmtError> {
+        let mut labels: Labels = vec![
+            ("endpoint_type", Cow::from(&self.endpoint_type)),
+            ("endpoint_name", Cow::from(&self.endpoint_name)),
+        ];
+        push_user_id(&mut labels, self.user_id);
+        labels.push(("user_name", Cow::from(self.user_name)));
+        labels.push(("task_name", Cow::from(self.task_name)));
+        labels.push(("status_code", Cow::from(self.status_code.to_string())));
+        labels.encode(encoder)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub(crate) struct TaskLabel {
-    pub user_id: u64,
+    pub user_id: Option<u64>,
     pub user_name: &'static str,
     pub task_name: &'static str,
 }
 
+impl EncodeLabelSet for TaskLabel {
+    fn encode(&self, encoder: LabelSetEncoder) -> Result<(), FmtError> {
+        let mut labels: Labels = Vec::new();
+        push_user_id(&mut labels, self.user_id);
+        labels.push(("user_name", Cow::from(self.user_name)));
+        labels.push(("task_name", Cow::from(self.task_name)));
+        labels.encode(encoder)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash, EncodeLabelSet)]
-pub(crate) struct UserCountLabel {
+pub(crate) struct TaskDurationLabel {
+    pub user_name: &'static str,
+    pub task_name: &'static str,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, EncodeLabelSet)]
+pub(crate) struct CheckLabel {
+    pub check_name: String,
     pub user_name: &'static str,
+    pub task_name: &'static str,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, EncodeLabelSet)]
+pub(crate) struct CustomMetricLabel {
+    pub metric_name: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, EncodeLabelSet)]
+pub(crate) struct CustomTrendLabel {
+    pub metric_name: String,
+    pub quantile: &'static str,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, EncodeLabelSet)]
+pub(crate) struct CheckThresholdLabel {
+    pub check_name: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, EncodeLabelSet)]
+struct TestStateLabel {
+    state: &'static str,
+}
+
+// exported as a state set, the current state is 1, all others are 0
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum TestState {
+    Spawning,
+    Running,
+    Stopping,
+    Stopped,
+}
+
+impl TestState {
+    const ALL: [TestState; 4] = [
+        TestState::Spawning,
+        TestState::Running,
+        TestState::Stopping,
+        TestState::Stopped,
+    ];
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            TestState::Spawning => "spawning",
+            TestState::Running => "running",
+            TestState::Stopping => "stopping",
+            TestState::Stopped => "stopped",
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, EncodeLabelSet)]
+pub(crate) struct UserCountLabel {
+    pub user_name: &'static str,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub(crate) struct UserLabel {
-    pub user_id: u64,
+    pub user_id: Option<u64>,
     pub user_name: &'static str,
 }
 
+impl EncodeLabelSet for UserLabel {
+    fn encode(&self, encoder: LabelSetEncoder) -> Result<(), FmtError> {
+        let mut labels: Labels = Vec::new();
+        push_user_id(&mut labels, self.user_id);
+        labels.push(("user_name", Cow::from(self.user_name)));
+        labels.encode(encoder)
+    }
+}
+
+// the buckets are given by the config, so a fn pointer can not be used
+#[derive(Debug, Clone)]
+struct HistogramConstructor {
+    buckets: Vec<f64>,
+}
+
+impl MetricConstructor<Histogram> for HistogramConstructor {
+    fn new_metric(&self) -> Histogram {
+        Histogram::new(self.buckets.iter().copied())
+    }
+}
+
 pub(crate) struct PrometheusExporter {
     registry: Registry,
+    label_policy: PrometheusLabelPolicy,
+    // the distinct endpoint names exported so far, only used if max_endpoint_names is set
+    endpoint_names: Mutex<HashSet<String>>,
     request_counter: Family<RequestLabel, Counter<u64>>,
     failure_counter: Family<RequestLabel, Counter<u64>>,
     error_counter: Family<RequestLabel, Counter<u64>>,
-    response_time_gauge: Family<RequestLabel, Gauge<f64, AtomicU64>>,
+    // opt-in, see TestConfig::response_time_gauge
+    response_time_gauge: Option<Family<RequestLabel, Gauge<f64, AtomicU64>>>,
+    response_time_histogram: Family<RequestLabel, Histogram, HistogramConstructor>,
+    status_code_counter: Family<StatusCodeLabel, Counter<u64>>,
+    bytes_received_counter: Family<RequestLabel, Counter<u64>>,
+    bytes_sent_counter: Family<RequestLabel, Counter<u64>>,
     task_counter: Family<TaskLabel, Counter<u64>>,
+    task_duration_histogram: Family<TaskDurationLabel, Histogram>,
+    task_timeout_counter: Family<TaskLabel, Counter<u64>>,
+    check_pass_counter: Family<CheckLabel, Counter<u64>>,
+    check_fail_counter: Family<CheckLabel, Counter<u64>>,
+    custom_counter: Family<CustomMetricLabel, Counter<f64, AtomicU64>>,
+    custom_gauge: Family<CustomMetricLabel, Gauge<f64, AtomicU64>>,
+    custom_trend_gauge: Family<CustomTrendLabel, Gauge<f64, AtomicU64>>,
+    custom_rate_gauge: Family<CustomMetricLabel, Gauge<f64, AtomicU64>>,
     panic_counter: Family<UserLabel, Counter<u64>>,
     suicide_counter: Family<UserLabel, Counter<u64>>,
+    restart_counter: Family<UserLabel, Counter<u64>>,
+    start_failure_counter: Family<UserCountLabel, Counter<u64>>,
     user_count_gauge: Family<UserCountLabel, Gauge>,
+    target_users_gauge: Gauge,
+    spawned_users_gauge: Gauge,
+    elapsed_time_gauge: Gauge<f64, AtomicU64>,
+    test_state_gauge: Family<TestStateLabel, Gauge>,
 }
 
 impl PrometheusExporter {
-    pub(crate) fn new() -> Self {
+    pub(crate) fn new(test_config: &TestConfig) -> Self {
         let mut registry = Registry::default();
         let request_counter = Family::<RequestLabel, Counter<u64>>::default();
         registry.register(
             "rocust_requests",
-            "Total number of requests",
+            "Total number of requests, errors included",
             request_counter.clone(),
         );
         let failure_counter = Family::<RequestLabel, Counter<u64>>::default();
@@ -64,11 +247,45 @@ impl PrometheusExporter {
             "Total number of errors",
             error_counter.clone(),
         );
-        let response_time_gauge = Family::<RequestLabel, Gauge<f64, AtomicU64>>::default();
+        let response_time_gauge = if test_config.response_time_gauge {
+            let response_time_gauge = Family::<RequestLabel, Gauge<f64, AtomicU64>>::default();
+            registry.register(
+                "rocust_response_time",
+                "Last response time in seconds",
+                response_time_gauge.clone(),
+            );
+            Some(response_time_gauge)
+        } else {
+            None
+        };
+        let response_time_histogram =
+            Family::<RequestLabel, Histogram, HistogramConstructor>::new_with_constructor(
+                HistogramConstructor {
+                    buckets: test_config.response_time_buckets.get_bounds(),
+                },
+            );
         registry.register(
-            "rocust_response_time",
-            "Response time",
-            response_time_gauge.clone(),
+            "rocust_response_time_seconds",
+            "Response time of successful requests in seconds",
+            response_time_histogram.clone(),
+        );
+        let status_code_counter = Family::<StatusCodeLabel, Counter<u64>>::default();
+        registry.register(
+            "rocust_status_codes",
+            "Total number of responses by status code",
+            status_code_counter.clone(),
+        );
+        let bytes_received_counter = Family::<RequestLabel, Counter<u64>>::default();
+        registry.register(
+            "rocust_bytes_received",
+            "Total number of bytes received",
+            bytes_received_counter.clone(),
+        );
+        let bytes_sent_counter = Family::<RequestLabel, Counter<u64>>::default();
+        registry.register(
+            "rocust_bytes_sent",
+            "Total number of bytes sent",
+            bytes_sent_counter.clone(),
         );
         let task_counter = Family::<TaskLabel, Counter<u64>>::default();
         registry.register(
@@ -76,6 +293,59 @@ impl PrometheusExporter {
             "Total number of tasks, tasks with suicide or panic are not included",
             task_counter.clone(),
         );
+        // 5ms to ~82s
+        let task_duration_histogram =
+            Family::<TaskDurationLabel, Histogram>::new_with_constructor(|| {
+                Histogram::new(exponential_buckets(0.005, 2.0, 15))
+            });
+        registry.register(
+            "rocust_task_duration_seconds",
+            "Duration of tasks in seconds, sleeping between tasks is not included",
+            task_duration_histogram.clone(),
+        );
+        let task_timeout_counter = Family::<TaskLabel, Counter<u64>>::default();
+        registry.register(
+            "rocust_task_timeouts",
+            "Total number of timed out tasks, timed out tasks are also counted as errors",
+            task_timeout_counter.clone(),
+        );
+        let check_pass_counter = Family::<CheckLabel, Counter<u64>>::default();
+        registry.register(
+            "rocust_check_passes",
+            "Total number of passed checks",
+            check_pass_counter.clone(),
+        );
+        let check_fail_counter = Family::<CheckLabel, Counter<u64>>::default();
+        registry.register(
+            "rocust_check_fails",
+            "Total number of failed checks",
+            check_fail_counter.clone(),
+        );
+        // user defined metrics, labeled by their name
+        let custom_counter = Family::<CustomMetricLabel, Counter<f64, AtomicU64>>::default();
+        registry.register(
+            "rocust_custom_counters",
+            "User defined counters",
+            custom_counter.clone(),
+        );
+        let custom_gauge = Family::<CustomMetricLabel, Gauge<f64, AtomicU64>>::default();
+        registry.register(
+            "rocust_custom_gauges",
+            "User defined gauges",
+            custom_gauge.clone(),
+        );
+        let custom_trend_gauge = Family::<CustomTrendLabel, Gauge<f64, AtomicU64>>::default();
+        registry.register(
+            "rocust_custom_trends",
+            "Quantiles of user defined trends, updated every update interval",
+            custom_trend_gauge.clone(),
+        );
+        let custom_rate_gauge = Family::<CustomMetricLabel, Gauge<f64, AtomicU64>>::default();
+        registry.register(
+            "rocust_custom_rates",
+            "Percentage of true values of user defined rates",
+            custom_rate_gauge.clone(),
+        );
         let panic_counter = Family::<UserLabel, Counter<u64>>::default();
         registry.register(
             "rocust_panics",
@@ -87,7 +357,21 @@ impl PrometheusExporter {
         registry.register(
             "rocust_suicides",
             "Total number of suicides by users",
-            panic_counter.clone(),
+            suicide_counter.clone(),
+        );
+
+        let restart_counter = Family::<UserLabel, Counter<u64>>::default();
+        registry.register(
+            "rocust_restarts",
+            "Total number of restarts of panicked users",
+            restart_counter.clone(),
+        );
+
+        let start_failure_counter = Family::<UserCountLabel, Counter<u64>>::default();
+        registry.register(
+            "rocust_start_failures",
+            "Total number of users that failed to start",
+            start_failure_counter.clone(),
         );
 
         let user_count_gauge = Family::<UserCountLabel, Gauge>::default();
@@ -97,17 +381,88 @@ impl PrometheusExporter {
             user_count_gauge.clone(),
         );
 
-        Self {
+        let target_users_gauge = Gauge::default();
+        registry.register(
+            "rocust_target_users",
+            "Number of users the test will spawn",
+            target_users_gauge.clone(),
+        );
+
+        let spawned_users_gauge = Gauge::default();
+        registry.register(
+            "rocust_spawned_users",
+            "Total number of spawned users, restarts are not included",
+            spawned_users_gauge.clone(),
+        );
+
+        let elapsed_time_gauge = Gauge::<f64, AtomicU64>::default();
+        registry.register(
+            "rocust_elapsed_seconds",
+            "Elapsed time since the test started in seconds, updated every update interval",
+            elapsed_time_gauge.clone(),
+        );
+
+        let test_state_gauge = Family::<TestStateLabel, Gauge>::default();
+        registry.register(
+            "rocust_test_state",
+            "State of the test, the current state is 1",
+            test_state_gauge.clone(),
+        );
+
+        // the configured thresholds do not change
+        let check_threshold_gauge = Family::<CheckThresholdLabel, Gauge<f64, AtomicU64>>::default();
+        for check_threshold in &test_config.check_thresholds {
+            check_threshold_gauge
+                .get_or_create(&CheckThresholdLabel {
+                    check_name: check_threshold.check_name.clone(),
+                })
+                .set(check_threshold.min_pass_rate);
+        }
+        registry.register(
+            "rocust_check_threshold_min_pass_rate",
+            "Configured minimum pass rate of checks",
+            check_threshold_gauge,
+        );
+
+        let build_info = Info::new(vec![
+            ("name", env!("CARGO_PKG_NAME")),
+            ("version", env!("CARGO_PKG_VERSION")),
+        ]);
+        registry.register("rocust_build", "Rocust build information", build_info);
+
+        let prometheus_exporter = Self {
             registry,
+            label_policy: test_config.prometheus_label_policy.clone(),
+            endpoint_names: Mutex::new(HashSet::new()),
             request_counter,
             failure_counter,
             error_counter,
             response_time_gauge,
+            response_time_histogram,
+            status_code_counter,
+            bytes_received_counter,
+            bytes_sent_counter,
             task_counter,
+            task_duration_histogram,
+            task_timeout_counter,
+            check_pass_counter,
+            check_fail_counter,
+            custom_counter,
+            custom_gauge,
+            custom_trend_gauge,
+            custom_rate_gauge,
             panic_counter,
             suicide_counter,
+            restart_counter,
+            start_failure_counter,
             user_count_gauge,
-        }
+            target_users_gauge,
+            spawned_users_gauge,
+            elapsed_time_gauge,
+            test_state_gauge,
+        };
+        prometheus_exporter.set_test_state(TestState::Spawning);
+        prometheus_exporter
     }
 
     pub(crate) fn get_metrics(&self) -> Result<String, FmtError> {
@@ -116,28 +471,175 @@ impl PrometheusExporter {
         Ok(buffer)
     }
 
-    pub(crate) fn add_success(&self, label: RequestLabel, response_time: f64) {
+    fn apply_user_id_policy(&self, user_id: Option<u64>) -> Option<u64> {
+        user_id.filter(|_| !self.label_policy.drop_user_id)
+    }
+
+    fn apply_endpoint_name_policy(&self, endpoint_name: String) -> String {
+        let endpoint_name = self.label_policy.rewrite_endpoint_name(endpoint_name);
+        let max_endpoint_names = match self.label_policy.max_endpoint_names {
+            Some(max_endpoint_names) => max_endpoint_names,
+            None => return endpoint_name,
+        };
+        match self.endpoint_names.lock() {
+            Ok(mut endpoint_names) => {
+                if endpoint_names.contains(&endpoint_name) {
+                    return endpoint_name;
+                }
+                if endpoint_names.len() >= max_endpoint_names {
+                    return String::from(OTHER_ENDPOINT_NAME);
+                }
+                endpoint_names.insert(endpoint_name.clone());
+                endpoint_name
+            }
+            Err(_) => endpoint_name,
+        }
+    }
+
+    // applied before every get_or_create, so dropped labels never create a series
+    fn apply_request_label_policy(&self, label: RequestLabel) -> RequestLabel {
+        RequestLabel {
+            endpoint_name: self.apply_endpoint_name_policy(label.endpoint_name),
+            user_id: self.apply_user_id_policy(label.user_id),
+            ..label
+        }
+    }
+
+    fn apply_task_label_policy(&self, label: TaskLabel) -> TaskLabel {
+        TaskLabel {
+            user_id: self.apply_user_id_policy(label.user_id),
+            ..label
+        }
+    }
+
+    fn apply_user_label_policy(&self, label: UserLabel) -> UserLabel {
+        UserLabel {
+            user_id: self.apply_user_id_policy(label.user_id),
+            ..label
+        }
+    }
+
+    pub(crate) fn add_success(
+        &self,
+        label: RequestLabel,
+        response_time: f64,
+        response_info: &ResponseInfo,
+    ) {
+        let label = self.apply_request_label_policy(label);
         self.request_counter.get_or_create(&label).inc();
-        self.response_time_gauge
+        self.response_time_histogram
             .get_or_create(&label)
-            .set(response_time);
+            .observe(response_time);
+        if let Some(response_time_gauge) = &self.response_time_gauge {
+            response_time_gauge.get_or_create(&label).set(response_time);
+        }
+        self.add_response_info(label, response_info);
     }
 
-    pub(crate) fn add_failure(&self, label: RequestLabel) {
+    pub(crate) fn add_failure(&self, label: RequestLabel, response_info: &ResponseInfo) {
+        let label = self.apply_request_label_policy(label);
         self.request_counter.get_or_create(&label).inc();
         self.failure_counter.get_or_create(&label).inc();
+        self.add_response_info(label, response_info);
+    }
+
+    fn add_response_info(&self, label: RequestLabel, response_info: &ResponseInfo) {
+        if let Some(bytes_received) = response_info.bytes_received {
+            self.bytes_received_counter
+                .get_or_create(&label)
+                .inc_by(bytes_received);
+        }
+        if let Some(bytes_sent) = response_info.bytes_sent {
+            self.bytes_sent_counter
+                .get_or_create(&label)
+                .inc_by(bytes_sent);
+        }
+        if let Some(status_code) = response_info.status_code {
+            self.status_code_counter
+                .get_or_create(&StatusCodeLabel {
+                    endpoint_type: label.endpoint_type,
+                    endpoint_name: label.endpoint_name,
+                    user_id: label.user_id,
+                    user_name: label.user_name,
+                    task_name: label.task_name,
+                    status_code,
+                })
+                .inc();
+        }
     }
 
     pub(crate) fn add_error(&self, label: RequestLabel) {
+        let label = self.apply_request_label_policy(label);
         self.request_counter.get_or_create(&label).inc();
         self.error_counter.get_or_create(&label).inc();
     }
 
     // tasks with suicide or panic are not included
     pub(crate) fn add_task(&self, label: TaskLabel) {
+        let label = self.apply_task_label_policy(label);
         self.task_counter.get_or_create(&label).inc();
     }
 
+    pub(crate) fn observe_task_duration(&self, label: TaskDurationLabel, duration: f64) {
+        self.task_duration_histogram
+            .get_or_create(&label)
+            .observe(duration);
+    }
+
+    pub(crate) fn add_task_timeout(&self, label: TaskLabel) {
+        let label = self.apply_task_label_policy(label);
+        self.task_timeout_counter.get_or_create(&label).inc();
+    }
+
+    pub(crate) fn add_check(&self, label: CheckLabel, passed: bool) {
+        if passed {
+            self.check_pass_counter.get_or_create(&label).inc();
+        } else {
+            self.check_fail_counter.get_or_create(&label).inc();
+        }
+    }
+
+    // trends are updated in update_custom_trends
+    pub(crate) fn update_custom_metric(
+        &self,
+        label: CustomMetricLabel,
+        metric: &Metric,
+        delta: f64,
+    ) {
+        match metric {
+            Metric::Counter(_) => {
+                self.custom_counter.get_or_create(&label).inc_by(delta);
+            }
+            Metric::Gauge(stats) => {
+                self.custom_gauge
+                    .get_or_create(&label)
+                    .set(stats.get_value());
+            }
+            Metric::Rate(stats) => {
+                self.custom_rate_gauge
+                    .get_or_create(&label)
+                    .set(stats.get_rate());
+            }
+            Metric::Trend(_) => {}
+        }
+    }
+
+    // calculating the quantiles on every value is too expensive
+    pub(crate) fn update_custom_trends(&self, all_metrics: &AllMetrics) {
+        for (name, metric) in all_metrics.get_metrics() {
+            if let Metric::Trend(stats) = metric {
+                for (quantile, value) in stats.quantiles() {
+                    self.custom_trend_gauge
+                        .get_or_create(&CustomTrendLabel {
+                            metric_name: name.clone(),
+                            quantile,
+                        })
+                        .set(value);
+                }
+            }
+        }
+    }
+
     pub(crate) fn add_user(&self, label: UserCountLabel) {
         self.user_count_gauge.get_or_create(&label).inc();
     }
@@ -147,10 +649,262 @@ impl PrometheusExporter {
     }
 
     pub(crate) fn add_panic(&self, label: UserLabel) {
+        let label = self.apply_user_label_policy(label);
         self.panic_counter.get_or_create(&label).inc();
     }
 
     pub(crate) fn add_suicide(&self, label: UserLabel) {
+        let label = self.apply_user_label_policy(label);
         self.suicide_counter.get_or_create(&label).inc();
     }
+
+    pub(crate) fn add_restart(&self, label: UserLabel) {
+        let label = self.apply_user_label_policy(label);
+        self.restart_counter.get_or_create(&label).inc();
+    }
+
+    pub(crate) fn set_target_users(&self, target_users: u64) {
+        self.target_users_gauge.set(target_users as i64);
+    }
+
+    pub(crate) fn set_spawned_users(&self, spawned_users: u64) {
+        self.spawned_users_gauge.set(spawned_users as i64);
+    }
+
+    pub(crate) fn set_elapsed_time(&self, elapsed_time: &Duration) {
+        self.elapsed_time_gauge.set(elapsed_time.as_secs_f64());
+    }
+
+    pub(crate) fn set_test_state(&self, test_state: TestState) {
+        for state in TestState::ALL {
+            self.test_state_gauge
+                .get_or_create(&TestStateLabel {
+                    state: state.as_str(),
+                })
+                .set((state == test_state) as i64);
+        }
+    }
+
+    pub(crate) fn add_start_failure(&self, label: UserCountLabel) {
+        self.start_failure_counter.get_or_create(&label).inc();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::config::{EndpointNameRewrite, ResponseTimeBuckets};
+
+    // number of series of a metric family in the text encoding
+    fn count_series(metrics: &str, name: &str) -> usize {
+        let prefix = format!("{}{{", name);
+        metrics
+            .lines()
+            .filter(|line| line.starts_with(&prefix))
+            .count()
+    }
+
+    // 1000 users requesting 1000 distinct items
+    fn report(prometheus_exporter: &PrometheusExporter) -> String {
+        for user_id in 0..1000 {
+            let label = RequestLabel {
+                endpoint_type: String::from("GET"),
+                endpoint_name: format!("/items/{}", user_id),
+                user_id: Some(user_id),
+                user_name: "ItemUser",
+                task_name: "get_item",
+            };
+            prometheus_exporter.add_success(label.clone(), 0.1, &ResponseInfo::default());
+            prometheus_exporter.add_error(label);
+            prometheus_exporter.add_task(TaskLabel {
+                user_id: Some(user_id),
+                user_name: "ItemUser",
+                task_name: "get_item",
+            });
+        }
+        prometheus_exporter
+            .get_metrics()
+            .expect("metrics should be encoded")
+    }
+
+    #[test]
+    fn series_are_unbounded_without_label_policy() {
+        let prometheus_exporter = PrometheusExporter::new(&TestConfig::default());
+        let metrics = report(&prometheus_exporter);
+
+        assert_eq!(count_series(&metrics, "rocust_requests_total"), 1000);
+        assert_eq!(count_series(&metrics, "rocust_tasks_total"), 1000);
+    }
+
+    #[test]
+    fn dropping_user_id_and_rewriting_endpoint_names_bounds_series() {
+        let label_policy = PrometheusLabelPolicy::default()
+            .drop_user_id()
+            .endpoint_name_rewrite(
+                EndpointNameRewrite::new(r"^/items/\d+$", "/items/{id}")
+                    .expect("pattern should be valid"),
+            );
+        let prometheus_exporter =
+            PrometheusExporter::new(&TestConfig::default().prometheus_label_policy(label_policy));
+        let metrics = report(&prometheus_exporter);
+
+        assert_eq!(count_series(&metrics, "rocust_requests_total"), 1);
+        assert_eq!(count_series(&metrics, "rocust_errors_total"), 1);
+        assert_eq!(count_series(&metrics, "rocust_tasks_total"), 1);
+        assert!(metrics.contains(r#"endpoint_name="/items/{id}""#));
+        assert!(!metrics.contains("user_id"));
+        assert!(metrics.contains(
+            r#"rocust_requests_total{endpoint_type="GET",endpoint_name="/items/{id}",user_name="ItemUser",task_name="get_item"} 2000"#
+        ));
+    }
+
+    #[test]
+    fn endpoint_names_are_capped_with_an_other_bucket() {
+        let label_policy = PrometheusLabelPolicy::default()
+            .drop_user_id()
+            .max_endpoint_names(10);
+        let prometheus_exporter =
+            PrometheusExporter::new(&TestConfig::default().prometheus_label_policy(label_policy));
+        let metrics = report(&prometheus_exporter);
+
+        // 10 distinct endpoint names and other
+        assert_eq!(count_series(&metrics, "rocust_requests_total"), 11);
+        assert!(metrics.contains(&format!(r#"endpoint_name="{}""#, OTHER_ENDPOINT_NAME)));
+        // known endpoint names are still exported after the limit is reached
+        assert!(metrics.contains(
+            r#"rocust_requests_total{endpoint_type="GET",endpoint_name="/items/0",user_name="ItemUser",task_name="get_item"} 2"#
+        ));
+    }
+
+    #[test]
+    fn every_family_is_present() {
+        let test_config = TestConfig::default()
+            .response_time_gauge(true)
+            .check_threshold(String::from("status is 200"), 0.99);
+        let prometheus_exporter = PrometheusExporter::new(&test_config);
+        let metrics = prometheus_exporter
+            .get_metrics()
+            .expect("metrics should be encoded");
+
+        let families = [
+            "rocust_requests",
+            "rocust_failures",
+            "rocust_errors",
+            "rocust_response_time",
+            "rocust_response_time_seconds",
+            "rocust_status_codes",
+            "rocust_bytes_received",
+            "rocust_bytes_sent",
+            "rocust_tasks",
+            "rocust_task_duration_seconds",
+            "rocust_task_timeouts",
+            "rocust_check_passes",
+            "rocust_check_fails",
+            "rocust_custom_counters",
+            "rocust_custom_gauges",
+            "rocust_custom_trends",
+            "rocust_custom_rates",
+            "rocust_panics",
+            "rocust_suicides",
+            "rocust_restarts",
+            "rocust_start_failures",
+            "rocust_user_count",
+            "rocust_target_users",
+            "rocust_spawned_users",
+            "rocust_elapsed_seconds",
+            "rocust_test_state",
+            "rocust_check_threshold_min_pass_rate",
+            "rocust_build",
+        ];
+        for family in families {
+            assert!(
+                metrics.contains(&format!("# TYPE {} ", family)),
+                "{} is missing",
+                family
+            );
+        }
+
+        assert!(metrics.contains(r#"rocust_test_state{state="spawning"} 1"#));
+        assert!(metrics.contains(r#"rocust_test_state{state="running"} 0"#));
+        assert!(metrics
+            .contains(r#"rocust_check_threshold_min_pass_rate{check_name="status is 200"} 0.99"#));
+        assert!(metrics.contains(&format!(
+            r#"rocust_build_info{{name="rocust_lib",version="{}"}} 1"#,
+            env!("CARGO_PKG_VERSION")
+        )));
+    }
+
+    #[test]
+    fn suicides_are_not_counted_as_panics() {
+        let prometheus_exporter = PrometheusExporter::new(&TestConfig::default());
+        prometheus_exporter.add_suicide(UserLabel {
+            user_id: Some(0),
+            user_name: "SuicideUser",
+        });
+        let metrics = prometheus_exporter
+            .get_metrics()
+            .expect("metrics should be encoded");
+
+        assert!(metrics.contains(r#"rocust_suicides_total{user_id="0",user_name="SuicideUser"} 1"#));
+        assert_eq!(count_series(&metrics, "rocust_panics_total"), 0);
+    }
+
+    #[test]
+    fn response_times_are_observed_in_the_configured_buckets() {
+        let test_config = TestConfig::default()
+            .response_time_buckets(ResponseTimeBuckets::Explicit(vec![0.1, 0.5]));
+        let prometheus_exporter = PrometheusExporter::new(&test_config);
+        let label = RequestLabel {
+            endpoint_type: String::from("GET"),
+            endpoint_name: String::from("/items"),
+            user_id: None,
+            user_name: "ItemUser",
+            task_name: "get_item",
+        };
+        prometheus_exporter.add_success(label.clone(), 0.2, &ResponseInfo::default());
+        prometheus_exporter.add_success(label, 0.05, &ResponseInfo::default());
+        let metrics = prometheus_exporter
+            .get_metrics()
+            .expect("metrics should be encoded");
+
+        let buckets: Vec<&str> = metrics
+            .lines()
+            .filter(|line| line.starts_with("rocust_response_time_seconds_bucket{"))
+            .collect();
+        assert_eq!(buckets.len(), 3);
+        assert!(buckets[0].contains(r#"le="0.1""#) && buckets[0].ends_with("} 1"));
+        assert!(buckets[1].contains(r#"le="0.5""#) && buckets[1].ends_with("} 2"));
+        assert!(buckets[2].contains(r#"le="+Inf""#) && buckets[2].ends_with("} 2"));
+        // the gauge is opt-in
+        assert_eq!(count_series(&metrics, "rocust_response_time"), 0);
+    }
+
+    #[test]
+    fn errors_are_counted_as_requests() {
+        let prometheus_exporter = PrometheusExporter::new(&TestConfig::default());
+        let label = RequestLabel {
+            endpoint_type: String::from("GET"),
+            endpoint_name: String::from("/items"),
+            user_id: None,
+            user_name: "ItemUser",
+            task_name: "get_item",
+        };
+        prometheus_exporter.add_success(label.clone(), 0.2, &ResponseInfo::default());
+        prometheus_exporter.add_failure(label.clone(), &ResponseInfo::default());
+        prometheus_exporter.add_error(label);
+        let metrics = prometheus_exporter
+            .get_metrics()
+            .expect("metrics should be encoded");
+
+        let total = |name: &str| {
+            metrics
+                .lines()
+                .find(|line| line.starts_with(&format!("{}{{", name)))
+                .and_then(|line| line.rsplit(' ').next())
+                .map(String::from)
+        };
+        assert_eq!(total("rocust_requests_total").as_deref(), Some("3"));
+        assert_eq!(total("rocust_failures_total").as_deref(), Some("1"));
+        assert_eq!(total("rocust_errors_total").as_deref(), Some("1"));
+    }
 }
@@ -0,0 +1,282 @@
+use crate::{prometheus_exporter::PrometheusExporter, test::config::PrometheusPushgateway};
+use hyper::{
+    client::HttpConnector,
+    header::CONTENT_TYPE,
+    http::{uri::InvalidUri, Error as HttpError},
+    Body, Client, Error as HyperError, Method, Request, StatusCode, Uri,
+};
+use std::{collections::HashSet, fmt::Error as FmtError, time::Duration};
+use thiserror::Error as ThisError;
+
+const TEXT_CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+const PUSH_TIMEOUT: Duration = Duration::from_secs(5);
+
+const BASE64_URL_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+#[derive(Debug, ThisError)]
+pub enum PushError {
+    #[error("Invalid pushgateway url: {0}")]
+    InvalidUri(#[from] InvalidUri),
+    #[error("Error encoding metrics: {0}")]
+    EncodeError(#[from] FmtError),
+    #[error("Error building request: {0}")]
+    RequestError(#[from] HttpError),
+    #[error("Error sending metrics: {0}")]
+    HyperError(#[from] HyperError),
+    #[error("Pushgateway did not respond within {0:?}")]
+    Timeout(Duration),
+    #[error("Pushgateway responded with {0}")]
+    UnexpectedStatus(StatusCode),
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct PrometheusPusher {
+    client: Client<HttpConnector>,
+    uri: Uri,
+}
+
+impl PrometheusPusher {
+    pub(crate) fn new(prometheus_pushgateway: &PrometheusPushgateway) -> Result<Self, PushError> {
+        Ok(Self {
+            client: Client::new(),
+            uri: grouping_key_url(prometheus_pushgateway).parse()?,
+        })
+    }
+
+    pub(crate) async fn push(
+        &self,
+        prometheus_exporter: &PrometheusExporter,
+    ) -> Result<(), PushError> {
+        let metrics = to_text_format(&prometheus_exporter.get_metrics()?);
+        let request = Request::builder()
+            .method(Method::PUT)
+            .uri(self.uri.clone())
+            .header(CONTENT_TYPE, TEXT_CONTENT_TYPE)
+            .body(Body::from(metrics))?;
+        let response = tokio::time::timeout(PUSH_TIMEOUT, self.client.request(request))
+            .await
+            .map_err(|_| PushError::Timeout(PUSH_TIMEOUT))??;
+        if !response.status().is_success() {
+            return Err(PushError::UnexpectedStatus(response.status()));
+        }
+        Ok(())
+    }
+}
+
+fn grouping_key_url(prometheus_pushgateway: &PrometheusPushgateway) -> String {
+    let mut url = format!(
+        "{}/metrics/{}",
+        prometheus_pushgateway.url.trim_end_matches('/'),
+        path_segments("job", &prometheus_pushgateway.job)
+    );
+    for (name, value) in &prometheus_pushgateway.grouping_labels {
+        url.push('/');
+        url.push_str(&path_segments(name, value));
+    }
+    url
+}
+
+// the pushgateway does not accept '/' in a value, even if it is percent encoded
+fn path_segments(name: &str, value: &str) -> String {
+    if value.is_empty() || value.contains('/') {
+        return format!("{}@base64/{}", name, base64_url(value));
+    }
+    format!("{}/{}", name, percent_encode(value))
+}
+
+fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| {
+            if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) {
+                String::from(byte as char)
+            } else {
+                format!("%{:02X}", byte)
+            }
+        })
+        .collect()
+}
+
+// an empty value is given as a single padding character
+fn base64_url(value: &str) -> String {
+    if value.is_empty() {
+        return String::from("=");
+    }
+    let mut encoded = String::new();
+    for chunk in value.as_bytes().chunks(3) {
+        let bytes = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let bits = (bytes[0] as u32) << 16 | (bytes[1] as u32) << 8 | bytes[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                let index = (bits >> (18 - 6 * i)) & 63;
+                encoded.push(BASE64_URL_ALPHABET[index as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+// the exporter encodes OpenMetrics, the pushgateway expects the prometheus text format.
+// counters and infos are named after their samples, infos become gauges and the EOF marker is removed
+fn to_text_format(open_metrics: &str) -> String {
+    let mut counters = HashSet::new();
+    let mut infos = HashSet::new();
+    for line in open_metrics.lines() {
+        if let Some(rest) = line.strip_prefix("# TYPE ") {
+            match rest.split_once(' ') {
+                Some((name, "counter")) => {
+                    counters.insert(name);
+                }
+                Some((name, "info")) => {
+                    infos.insert(name);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let mut text = String::new();
+    for line in open_metrics.lines() {
+        if line == "# EOF" || line.starts_with("# UNIT ") {
+            continue;
+        }
+        let converted = ["# HELP ", "# TYPE "].iter().find_map(|prefix| {
+            let (name, rest) = line.strip_prefix(prefix)?.split_once(' ')?;
+            if counters.contains(name) {
+                Some(format!("{}{}_total {}", prefix, name, rest))
+            } else if infos.contains(name) {
+                let rest = if rest == "info" { "gauge" } else { rest };
+                Some(format!("{}{}_info {}", prefix, name, rest))
+            } else {
+                None
+            }
+        });
+        match converted {
+            Some(converted) => text.push_str(&converted),
+            None => text.push_str(line),
+        }
+        text.push('\n');
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TestConfig;
+    use axum::{extract::State, http::HeaderMap, Router};
+    use std::{
+        net::{SocketAddr, TcpListener},
+        sync::{Arc, Mutex},
+    };
+
+    #[derive(Debug, Clone)]
+    struct Push {
+        method: Method,
+        path: String,
+        content_type: Option<String>,
+        body: String,
+    }
+
+    #[derive(Clone)]
+    struct StandIn {
+        pushes: Arc<Mutex<Vec<Push>>>,
+        status: StatusCode,
+    }
+
+    async fn record(
+        State(stand_in): State<StandIn>,
+        method: Method,
+        uri: Uri,
+        headers: HeaderMap,
+        body: String,
+    ) -> StatusCode {
+        stand_in.pushes.lock().unwrap().push(Push {
+            method,
+            path: String::from(uri.path()),
+            content_type: headers
+                .get(CONTENT_TYPE)
+                .and_then(|content_type| content_type.to_str().ok())
+                .map(String::from),
+            body,
+        });
+        stand_in.status
+    }
+
+    // a local http server that records every request and responds with the given status
+    fn start_stand_in(status: StatusCode) -> (SocketAddr, Arc<Mutex<Vec<Push>>>) {
+        let pushes = Arc::new(Mutex::new(Vec::new()));
+        let app = Router::new().fallback(record).with_state(StandIn {
+            pushes: pushes.clone(),
+            status,
+        });
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = axum::Server::from_tcp(listener)
+            .unwrap()
+            .serve(app.into_make_service());
+        tokio::spawn(server);
+        (addr, pushes)
+    }
+
+    #[tokio::test]
+    async fn pushes_text_format_to_the_grouping_key() {
+        let (addr, pushes) = start_stand_in(StatusCode::OK);
+        let prometheus_pushgateway = PrometheusPushgateway::new(format!("http://{}/", addr))
+            .job(String::from("ci load test"))
+            .grouping_label(String::from("branch"), String::from("feature/x"));
+        let prometheus_pusher = PrometheusPusher::new(&prometheus_pushgateway).unwrap();
+        let prometheus_exporter = PrometheusExporter::new(&TestConfig::default());
+
+        prometheus_pusher.push(&prometheus_exporter).await.unwrap();
+        prometheus_pusher.push(&prometheus_exporter).await.unwrap();
+
+        let pushes = pushes.lock().unwrap();
+        assert_eq!(pushes.len(), 2);
+        let push = &pushes[0];
+        assert_eq!(push.method, Method::PUT);
+        assert_eq!(
+            push.path,
+            "/metrics/job/ci%20load%20test/branch@base64/ZmVhdHVyZS94"
+        );
+        assert_eq!(push.content_type.as_deref(), Some(TEXT_CONTENT_TYPE));
+        assert!(push.body.contains("# TYPE rocust_requests_total counter"));
+        assert!(push.body.contains("# TYPE rocust_build_info gauge"));
+        assert!(push
+            .body
+            .contains("# TYPE rocust_response_time_seconds histogram"));
+        assert!(!push.body.contains("# EOF"));
+    }
+
+    #[tokio::test]
+    async fn unexpected_status_is_an_error() {
+        let (addr, pushes) = start_stand_in(StatusCode::BAD_REQUEST);
+        let prometheus_pusher =
+            PrometheusPusher::new(&PrometheusPushgateway::new(format!("http://{}", addr))).unwrap();
+        let prometheus_exporter = PrometheusExporter::new(&TestConfig::default());
+
+        let result = prometheus_pusher.push(&prometheus_exporter).await;
+
+        assert!(matches!(
+            result,
+            Err(PushError::UnexpectedStatus(StatusCode::BAD_REQUEST))
+        ));
+        assert_eq!(pushes.lock().unwrap()[0].path, "/metrics/job/rocust");
+    }
+
+    #[test]
+    fn values_are_encoded_as_path_segments() {
+        assert_eq!(path_segments("instance", "ci-1"), "instance/ci-1");
+        assert_eq!(path_segments("instance", "a b"), "instance/a%20b");
+        assert_eq!(path_segments("instance", ""), "instance@base64/=");
+        assert_eq!(path_segments("path", "/a"), "path@base64/L2E=");
+    }
+}
@@ -1,10 +1,16 @@
+use crate::{custom_metrics::SerMetric, histogram::ValueHistogram};
 use csv::{Error as CsvError, IntoInnerError as CsvIntoInnerError, Writer as CsvWriter};
 use prettytable::{row, Cell, Row, Table};
 use serde::{ser::SerializeStruct, Serialize};
-use std::{collections::HashMap, string::FromUtf8Error, time::Duration};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Display,
+    string::FromUtf8Error,
+    time::Duration,
+};
 use thiserror::Error as ThisError;
 
-const CONSOLE_HEADERS: [&str; 11] = [
+const CONSOLE_HEADERS: [&str; 16] = [
     "TYPE",
     "NAME",
     "TOTAL REQ",
@@ -16,11 +22,16 @@ const CONSOLE_HEADERS: [&str; 11] = [
     "AVG RES TIME",
     "MIN RES TIME",
     "MAX RES TIME",
+    "AVG FAILED RES TIME",
+    "AVG BYTES RECV",
+    "AVG BYTES SENT",
+    "STATUS CLASSES",
+    "STATUS CODES",
 ];
 
 const CONSOLE_AGR_TYPE_NAME: [&str; 2] = ["", "AGR"];
 
-const FILE_HEADERS: [&str; 11] = [
+const FILE_HEADERS: [&str; 18] = [
     "type",
     "name",
     "total_requests",
@@ -32,25 +43,138 @@ const FILE_HEADERS: [&str; 11] = [
     "average_response_time",
     "min_response_time",
     "max_response_time",
+    "average_failed_response_time",
+    "total_bytes_received",
+    "total_bytes_sent",
+    "average_bytes_received",
+    "average_bytes_sent",
+    "status_code_classes",
+    "status_codes",
 ];
 
-const FILE_AGR_TYPE_NAME: [&str; 2] = ["", "aggregated"];
+pub(crate) const FILE_AGR_TYPE_NAME: [&str; 2] = ["", "aggregated"];
+
+// endpoint type of the errors recorded for timed out tasks, the endpoint name is the task name
+pub const TASK_ENDPOINT_TYPE: &str = "TASK";
+
+// endpoint type of the results recorded by Context::transaction, the endpoint name is the transaction name
+pub const TRANSACTION_ENDPOINT_TYPE: &str = "TRANSACTION";
+
+/// Optional details of a response, reported with Context::add_success_with_info and Context::add_failure_with_info.
+/// Fields that are not set are not counted.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ResponseInfo {
+    pub status_code: Option<u16>,
+    pub bytes_received: Option<u64>,
+    pub bytes_sent: Option<u64>,
+}
+
+impl ResponseInfo {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn status_code(self, status_code: u16) -> Self {
+        Self {
+            status_code: Some(status_code),
+            ..self
+        }
+    }
+
+    pub fn bytes_received(self, bytes_received: u64) -> Self {
+        Self {
+            bytes_received: Some(bytes_received),
+            ..self
+        }
+    }
+
+    pub fn bytes_sent(self, bytes_sent: u64) -> Self {
+        Self {
+            bytes_sent: Some(bytes_sent),
+            ..self
+        }
+    }
+}
+
+// 1xx, 2xx, 3xx, 4xx, 5xx
+fn status_code_class(status_code: u16) -> &'static str {
+    match status_code {
+        100..=199 => "1xx",
+        200..=299 => "2xx",
+        300..=399 => "3xx",
+        400..=499 => "4xx",
+        500..=599 => "5xx",
+        _ => "other",
+    }
+}
+
+// 200:10 404:2 or 2xx:10 4xx:2
+pub(crate) fn status_codes_string<K: Display>(
+    status_codes: &BTreeMap<K, u32>,
+    separator: &str,
+) -> String {
+    status_codes
+        .iter()
+        .map(|(status_code, count)| format!("{}:{}", status_code, count))
+        .collect::<Vec<_>>()
+        .join(separator)
+}
 
 #[derive(Debug, Default, Clone, Serialize)]
 pub struct Results {
     total_requests: u32,
     total_failed_requests: u32,
     total_errors: u32,
+    // timeouts are also counted as errors
+    total_timeouts: u32,
     total_response_time: f64,
     average_response_time: f64,
     min_response_time: f64,
     median_response_time: f64,
+    percentile_90_response_time: f64,
+    percentile_95_response_time: f64,
+    percentile_99_response_time: f64,
     max_response_time: f64,
     requests_per_second: f64,
     failed_requests_per_second: f64,
+    // failed requests are not included in the response times above
+    total_failed_response_time: f64,
+    average_failed_response_time: f64,
+    // failed requests that were reported with a response time
+    #[serde(skip)]
+    total_timed_failed_requests: u32,
+    total_bytes_received: u64,
+    total_bytes_sent: u64,
+    // averaged over all requests
+    average_bytes_received: f64,
+    average_bytes_sent: f64,
+    status_code_classes: BTreeMap<&'static str, u32>,
+    status_codes: BTreeMap<u16, u32>,
+    // only kept for the results of the whole test, see AllResults::with_percentiles
+    #[serde(skip)]
+    histogram: Option<ValueHistogram>,
 }
 
 impl Results {
+    fn with_histogram() -> Self {
+        Self {
+            histogram: Some(ValueHistogram::default()),
+            ..Self::default()
+        }
+    }
+
+    fn add_response_info(&mut self, response_info: &ResponseInfo) {
+        if let Some(status_code) = response_info.status_code {
+            *self
+                .status_code_classes
+                .entry(status_code_class(status_code))
+                .or_default() += 1;
+            *self.status_codes.entry(status_code).or_default() += 1;
+        }
+        self.total_bytes_received += response_info.bytes_received.unwrap_or(0);
+        self.total_bytes_sent += response_info.bytes_sent.unwrap_or(0);
+    }
+
     fn add_success(&mut self, response_time: f64) {
         self.total_response_time += response_time;
         self.total_requests += 1;
@@ -60,17 +184,29 @@ impl Results {
         if response_time > self.max_response_time {
             self.max_response_time = response_time;
         }
+        if let Some(histogram) = &mut self.histogram {
+            histogram.record(response_time);
+        }
     }
 
-    fn add_failure(&mut self) {
+    fn add_failure(&mut self, response_time: Option<f64>) {
         self.total_requests += 1;
         self.total_failed_requests += 1;
+        if let Some(response_time) = response_time {
+            self.total_failed_response_time += response_time;
+            self.total_timed_failed_requests += 1;
+        }
     }
 
     fn add_error(&mut self) {
         self.total_errors += 1;
     }
 
+    fn add_timeout(&mut self) {
+        self.total_errors += 1;
+        self.total_timeouts += 1;
+    }
+
     fn calculate_average_response_time(&mut self) {
         // (total_requests - total_failed_requests) = total_successful_requests
         let total_successful_requests = self.total_requests - self.total_failed_requests;
@@ -88,8 +224,37 @@ impl Results {
         self.failed_requests_per_second = failed_requests_per_second;
     }
 
+    fn calculate_average_failed_response_time(&mut self) {
+        if self.total_timed_failed_requests > 0 {
+            self.average_failed_response_time =
+                self.total_failed_response_time / self.total_timed_failed_requests as f64;
+        }
+    }
+
+    fn calculate_average_bytes(&mut self) {
+        if self.total_requests > 0 {
+            self.average_bytes_received =
+                self.total_bytes_received as f64 / self.total_requests as f64;
+            self.average_bytes_sent = self.total_bytes_sent as f64 / self.total_requests as f64;
+        }
+    }
+
+    fn calculate_percentile_response_times(&mut self) {
+        if let Some(histogram) = &self.histogram {
+            [
+                self.median_response_time,
+                self.percentile_90_response_time,
+                self.percentile_95_response_time,
+                self.percentile_99_response_time,
+            ] = histogram.percentiles(self.min_response_time, self.max_response_time);
+        }
+    }
+
     fn calculate_on_update_interval(&mut self, elapsed: &Duration) {
         self.calculate_average_response_time();
+        self.calculate_percentile_response_times();
+        self.calculate_average_failed_response_time();
+        self.calculate_average_bytes();
         self.calculate_requests_per_second(elapsed);
         self.calculate_failed_requests_per_second(elapsed);
     }
@@ -106,6 +271,10 @@ impl Results {
         self.total_errors
     }
 
+    pub fn get_total_timeouts(&self) -> u32 {
+        self.total_timeouts
+    }
+
     pub fn get_total_response_time(&self) -> f64 {
         self.total_response_time
     }
@@ -122,6 +291,18 @@ impl Results {
         self.median_response_time
     }
 
+    pub fn get_percentile_90_response_time(&self) -> f64 {
+        self.percentile_90_response_time
+    }
+
+    pub fn get_percentile_95_response_time(&self) -> f64 {
+        self.percentile_95_response_time
+    }
+
+    pub fn get_percentile_99_response_time(&self) -> f64 {
+        self.percentile_99_response_time
+    }
+
     pub fn get_max_response_time(&self) -> f64 {
         self.max_response_time
     }
@@ -134,6 +315,39 @@ impl Results {
         self.failed_requests_per_second
     }
 
+    pub fn get_total_failed_response_time(&self) -> f64 {
+        self.total_failed_response_time
+    }
+
+    pub fn get_average_failed_response_time(&self) -> f64 {
+        self.average_failed_response_time
+    }
+
+    pub fn get_total_bytes_received(&self) -> u64 {
+        self.total_bytes_received
+    }
+
+    pub fn get_total_bytes_sent(&self) -> u64 {
+        self.total_bytes_sent
+    }
+
+    pub fn get_average_bytes_received(&self) -> f64 {
+        self.average_bytes_received
+    }
+
+    pub fn get_average_bytes_sent(&self) -> f64 {
+        self.average_bytes_sent
+    }
+
+    // counts by 1xx, 2xx, 3xx, 4xx, 5xx and other
+    pub fn get_status_code_classes(&self) -> &BTreeMap<&'static str, u32> {
+        &self.status_code_classes
+    }
+
+    pub fn get_status_codes(&self) -> &BTreeMap<u16, u32> {
+        &self.status_codes
+    }
+
     fn into_ser_results(self, endpoint_type_name: EndpointTypeName) -> SerResults {
         SerResults {
             endpoint_type_name,
@@ -142,6 +356,27 @@ impl Results {
     }
 }
 
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct Totals {
+    pub(crate) elapsed_secs: f64,
+    pub(crate) requests: u32,
+    pub(crate) failed_requests: u32,
+    pub(crate) errors: u32,
+    pub(crate) response_time: f64,
+}
+
+impl Totals {
+    pub(crate) fn new(elapsed_time: &Duration, results: &Results) -> Self {
+        Self {
+            elapsed_secs: elapsed_time.as_secs_f64(),
+            requests: results.get_total_requests(),
+            failed_requests: results.get_total_failed_requests(),
+            errors: results.get_total_errors(),
+            response_time: results.get_total_response_time(),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub(crate) struct SerResults {
     endpoint_type_name: EndpointTypeName,
@@ -167,16 +402,62 @@ pub struct EndpointTypeName {
     pub name: String,
 }
 
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct TaskEndpointTypeName {
+    pub task_name: &'static str,
+    pub endpoint_type_name: EndpointTypeName,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct SerTaskResults {
+    task_endpoint_type_name: TaskEndpointTypeName,
+    results: Results,
+}
+
+impl Serialize for SerTaskResults {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let mut state = serializer.serialize_struct("SerTaskResults", 4)?;
+        state.serialize_field("task_name", &self.task_endpoint_type_name.task_name)?;
+        state.serialize_field(
+            "type",
+            &self.task_endpoint_type_name.endpoint_type_name.r#type,
+        )?;
+        state.serialize_field(
+            "name",
+            &self.task_endpoint_type_name.endpoint_type_name.name,
+        )?;
+        state.serialize_field("results", &self.results)?;
+        state.end()
+    }
+}
+
 #[derive(Debug, Clone, Serialize)]
 pub(crate) struct SerAllResults {
     aggrigated_results: Results,
     endpoint_results: Vec<SerResults>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    task_results: Vec<SerTaskResults>,
+    // user defined metrics
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    metrics: Vec<SerMetric>,
+}
+
+impl SerAllResults {
+    pub(crate) fn with_metrics(self, metrics: Vec<SerMetric>) -> Self {
+        Self { metrics, ..self }
+    }
 }
 
 #[derive(Debug, Default, Clone)]
 pub struct AllResults {
     aggrigated_results: Results,
     endpoint_results: HashMap<EndpointTypeName, Results>,
+    // only filled if the task name is given, see TestConfig::results_per_task
+    task_results: HashMap<TaskEndpointTypeName, Results>,
+    percentiles: bool,
 }
 
 #[derive(Debug, ThisError)]
@@ -197,46 +478,96 @@ impl From<CsvIntoInnerError<CsvWriter<Vec<u8>>>> for CSVError {
     }
 }
 
-// TODO: refactor repeated code
 impl AllResults {
-    pub(crate) fn add_success(
-        &mut self,
-        endpoint_type_name: &EndpointTypeName,
-        response_time: f64,
-    ) {
-        self.aggrigated_results.add_success(response_time);
-        if let Some(endpoint_results) = self.endpoint_results.get_mut(endpoint_type_name) {
-            endpoint_results.add_success(response_time);
-        } else {
-            let mut endpoint_results = Results::default();
-            endpoint_results.add_success(response_time);
-            self.endpoint_results
-                .insert(endpoint_type_name.clone(), endpoint_results);
+    // the histograms are too large to be kept for every user
+    pub(crate) fn with_percentiles() -> Self {
+        Self {
+            aggrigated_results: Results::with_histogram(),
+            percentiles: true,
+            ..Self::default()
         }
     }
 
-    pub(crate) fn add_failure(&mut self, endpoint_type_name: &EndpointTypeName) {
-        self.aggrigated_results.add_failure();
-        if let Some(endpoint_results) = self.endpoint_results.get_mut(endpoint_type_name) {
-            endpoint_results.add_failure();
+    fn new_results(percentiles: bool) -> Results {
+        if percentiles {
+            Results::with_histogram()
         } else {
-            let mut endpoint_results = Results::default();
-            endpoint_results.add_failure();
-            self.endpoint_results
-                .insert(endpoint_type_name.clone(), endpoint_results);
+            Results::default()
         }
     }
 
-    pub(crate) fn add_error(&mut self, endpoint_type_name: &EndpointTypeName, _error: &str) {
-        self.aggrigated_results.add_error();
+    // calls f on the aggregated results, the endpoint results and the task results if a task name is given
+    fn apply<F>(
+        &mut self,
+        endpoint_type_name: &EndpointTypeName,
+        task_name: Option<&'static str>,
+        f: F,
+    ) where
+        F: Fn(&mut Results),
+    {
+        f(&mut self.aggrigated_results);
         if let Some(endpoint_results) = self.endpoint_results.get_mut(endpoint_type_name) {
-            endpoint_results.add_error();
+            f(endpoint_results);
         } else {
-            let mut endpoint_results = Results::default();
-            endpoint_results.add_error();
+            let mut endpoint_results = Self::new_results(self.percentiles);
+            f(&mut endpoint_results);
             self.endpoint_results
                 .insert(endpoint_type_name.clone(), endpoint_results);
         }
+        if let Some(task_name) = task_name {
+            let task_endpoint_type_name = TaskEndpointTypeName {
+                task_name,
+                endpoint_type_name: endpoint_type_name.clone(),
+            };
+            let percentiles = self.percentiles;
+            f(self
+                .task_results
+                .entry(task_endpoint_type_name)
+                .or_insert_with(|| Self::new_results(percentiles)));
+        }
+    }
+
+    pub(crate) fn add_success(
+        &mut self,
+        endpoint_type_name: &EndpointTypeName,
+        task_name: Option<&'static str>,
+        response_time: f64,
+        response_info: &ResponseInfo,
+    ) {
+        self.apply(endpoint_type_name, task_name, |results| {
+            results.add_success(response_time);
+            results.add_response_info(response_info);
+        });
+    }
+
+    pub(crate) fn add_failure(
+        &mut self,
+        endpoint_type_name: &EndpointTypeName,
+        task_name: Option<&'static str>,
+        response_time: Option<f64>,
+        response_info: &ResponseInfo,
+    ) {
+        self.apply(endpoint_type_name, task_name, |results| {
+            results.add_failure(response_time);
+            results.add_response_info(response_info);
+        });
+    }
+
+    pub(crate) fn add_error(
+        &mut self,
+        endpoint_type_name: &EndpointTypeName,
+        task_name: Option<&'static str>,
+        _error: &str,
+    ) {
+        self.apply(endpoint_type_name, task_name, Results::add_error);
+    }
+
+    pub(crate) fn add_timeout(
+        &mut self,
+        endpoint_type_name: &EndpointTypeName,
+        task_name: Option<&'static str>,
+    ) {
+        self.apply(endpoint_type_name, task_name, Results::add_timeout);
     }
 
     pub(crate) fn calculate_on_update_interval(&mut self, elapsed: &Duration) {
@@ -245,41 +576,9 @@ impl AllResults {
         for (_, endpoint_results) in self.endpoint_results.iter_mut() {
             endpoint_results.calculate_on_update_interval(elapsed);
         }
-    }
-
-    pub(crate) fn history_header_csv_string() -> Result<String, CSVError> {
-        let mut wtr = CsvWriter::from_writer(vec![]);
-        let headers_with_timestamp = [&["timestamp"], &FILE_HEADERS[..]].concat();
-        wtr.write_record(&headers_with_timestamp)?;
-        let data = String::from_utf8(wtr.into_inner()?)?;
-        Ok(data)
-    }
-
-    pub(crate) fn current_aggrigated_results_with_timestamp_csv_string(
-        &self,
-        timestamp: &str,
-    ) -> Result<String, CSVError> {
-        let mut wtr = CsvWriter::from_writer(vec![]);
-        wtr.write_record([
-            timestamp,
-            FILE_AGR_TYPE_NAME[0],
-            FILE_AGR_TYPE_NAME[1],
-            &self.aggrigated_results.total_requests.to_string(),
-            &self.aggrigated_results.total_failed_requests.to_string(),
-            &self.aggrigated_results.total_errors.to_string(),
-            &self.aggrigated_results.requests_per_second.to_string(),
-            &self
-                .aggrigated_results
-                .failed_requests_per_second
-                .to_string(),
-            &self.aggrigated_results.total_response_time.to_string(),
-            &self.aggrigated_results.average_response_time.to_string(),
-            &self.aggrigated_results.min_response_time.to_string(),
-            &self.aggrigated_results.median_response_time.to_string(),
-            &self.aggrigated_results.max_response_time.to_string(),
-        ])?;
-        let data = String::from_utf8(wtr.into_inner()?)?;
-        Ok(data)
+        for (_, task_results) in self.task_results.iter_mut() {
+            task_results.calculate_on_update_interval(elapsed);
+        }
     }
 
     pub(crate) fn current_results_csv_string(&self) -> Result<String, CSVError> {
@@ -298,6 +597,13 @@ impl AllResults {
                 &results.average_response_time.to_string(),
                 &results.min_response_time.to_string(),
                 &results.max_response_time.to_string(),
+                &results.average_failed_response_time.to_string(),
+                &results.total_bytes_received.to_string(),
+                &results.total_bytes_sent.to_string(),
+                &results.average_bytes_received.to_string(),
+                &results.average_bytes_sent.to_string(),
+                &status_codes_string(&results.status_code_classes, " "),
+                &status_codes_string(&results.status_codes, " "),
             ])?;
         }
         wtr.write_record([
@@ -315,6 +621,16 @@ impl AllResults {
             &self.aggrigated_results.average_response_time.to_string(),
             &self.aggrigated_results.min_response_time.to_string(),
             &self.aggrigated_results.max_response_time.to_string(),
+            &self
+                .aggrigated_results
+                .average_failed_response_time
+                .to_string(),
+            &self.aggrigated_results.total_bytes_received.to_string(),
+            &self.aggrigated_results.total_bytes_sent.to_string(),
+            &self.aggrigated_results.average_bytes_received.to_string(),
+            &self.aggrigated_results.average_bytes_sent.to_string(),
+            &status_codes_string(&self.aggrigated_results.status_code_classes, " "),
+            &status_codes_string(&self.aggrigated_results.status_codes, " "),
         ])?;
         let data = String::from_utf8(wtr.into_inner()?)?;
         Ok(data)
@@ -338,6 +654,11 @@ impl AllResults {
                 format!("{:.1$}", results.average_response_time, precision),
                 format!("{:.1$}", results.min_response_time, precision),
                 format!("{:.1$}", results.max_response_time, precision),
+                format!("{:.1$}", results.average_failed_response_time, precision),
+                format!("{:.1$}", results.average_bytes_received, precision),
+                format!("{:.1$}", results.average_bytes_sent, precision),
+                status_codes_string(&results.status_code_classes, "\n"),
+                status_codes_string(&results.status_codes, "\n"),
             ]);
         }
         table.add_row(row![
@@ -373,6 +694,20 @@ impl AllResults {
                 "{:.1$}",
                 self.aggrigated_results.max_response_time, precision
             ),
+            format!(
+                "{:.1$}",
+                self.aggrigated_results.average_failed_response_time, precision
+            ),
+            format!(
+                "{:.1$}",
+                self.aggrigated_results.average_bytes_received, precision
+            ),
+            format!(
+                "{:.1$}",
+                self.aggrigated_results.average_bytes_sent, precision
+            ),
+            status_codes_string(&self.aggrigated_results.status_code_classes, "\n"),
+            status_codes_string(&self.aggrigated_results.status_codes, "\n"),
         ]);
         table.to_string()
     }
@@ -411,6 +746,20 @@ impl AllResults {
     pub fn get_endpoint_results(&self) -> &HashMap<EndpointTypeName, Results> {
         &self.endpoint_results
     }
+
+    pub fn get_task_results(&self) -> &HashMap<TaskEndpointTypeName, Results> {
+        &self.task_results
+    }
+
+    pub fn get_by_task_name(&self, task_name: &str) -> Vec<(&EndpointTypeName, &Results)> {
+        let mut results = Vec::new();
+        for (task_endpoint_type_name, result) in &self.task_results {
+            if task_endpoint_type_name.task_name == task_name {
+                results.push((&task_endpoint_type_name.endpoint_type_name, result));
+            }
+        }
+        results
+    }
 }
 
 impl From<AllResults> for SerAllResults {
@@ -421,9 +770,132 @@ impl From<AllResults> for SerAllResults {
             .into_iter()
             .map(|(endpoint_type_name, results)| results.into_ser_results(endpoint_type_name))
             .collect();
+        let task_results = all_results
+            .task_results
+            .into_iter()
+            .map(|(task_endpoint_type_name, results)| SerTaskResults {
+                task_endpoint_type_name,
+                results,
+            })
+            .collect();
         SerAllResults {
             aggrigated_results,
             endpoint_results,
+            task_results,
+            metrics: Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn endpoint_type_name(name: &str) -> EndpointTypeName {
+        EndpointTypeName {
+            r#type: String::from("GET"),
+            name: String::from(name),
+        }
+    }
+
+    #[test]
+    fn results_are_broken_down_by_task() {
+        let mut all_results = AllResults::default();
+        let info = ResponseInfo::new();
+        all_results.add_success(&endpoint_type_name("/items"), Some("browse"), 0.1, &info);
+        all_results.add_success(&endpoint_type_name("/items"), Some("checkout"), 0.2, &info);
+        all_results.add_failure(&endpoint_type_name("/pay"), Some("checkout"), None, &info);
+        all_results.add_error(&endpoint_type_name("/pay"), Some("checkout"), "refused");
+        // without a task name only the endpoint results are updated
+        all_results.add_success(&endpoint_type_name("/items"), None, 0.3, &info);
+
+        assert_eq!(all_results.get_aggrigated_results().get_total_requests(), 4);
+        let items = all_results.get_by_type_and_name("GET", "/items").unwrap();
+        assert_eq!(items.get_total_requests(), 3);
+
+        let browse = all_results.get_by_task_name("browse");
+        assert_eq!(browse.len(), 1);
+        assert_eq!(browse[0].1.get_total_requests(), 1);
+
+        let mut checkout = all_results.get_by_task_name("checkout");
+        checkout.sort_by_key(|(endpoint_type_name, _)| endpoint_type_name.name.clone());
+        assert_eq!(checkout.len(), 2);
+        assert_eq!(checkout[0].0.name, "/items");
+        assert_eq!(checkout[0].1.get_total_requests(), 1);
+        assert_eq!(checkout[1].0.name, "/pay");
+        assert_eq!(checkout[1].1.get_total_failed_requests(), 1);
+        assert_eq!(checkout[1].1.get_total_errors(), 1);
+        assert_eq!(all_results.get_task_results().len(), 3);
+    }
+
+    #[test]
+    fn timeouts_are_counted_as_errors() {
+        let mut all_results = AllResults::default();
+        let endpoint_type_name = EndpointTypeName {
+            r#type: String::from(TASK_ENDPOINT_TYPE),
+            name: String::from("checkout"),
+        };
+        all_results.add_timeout(&endpoint_type_name, Some("checkout"));
+        all_results.add_error(&endpoint_type_name, Some("checkout"), "refused");
+
+        let results = all_results
+            .get_by_type_and_name(TASK_ENDPOINT_TYPE, "checkout")
+            .unwrap();
+        assert_eq!(results.get_total_errors(), 2);
+        assert_eq!(results.get_total_timeouts(), 1);
+        assert_eq!(results.get_total_requests(), 0);
+        assert_eq!(all_results.get_aggrigated_results().get_total_timeouts(), 1);
+        assert_eq!(all_results.get_by_task_name("checkout").len(), 1);
+    }
+
+    #[test]
+    fn status_code_classes_are_in_the_table_and_the_csv() {
+        let mut all_results = AllResults::default();
+        for status_code in [200, 201, 404, 503] {
+            all_results.add_success(
+                &endpoint_type_name("/items"),
+                None,
+                0.1,
+                &ResponseInfo::new().status_code(status_code),
+            );
         }
+        all_results.calculate_on_update_interval(&Duration::from_secs(1));
+
+        let table = all_results.table_string(2);
+        assert!(table.contains("STATUS CLASSES"));
+        assert!(table.contains("2xx:2"));
+
+        let csv = all_results.current_results_csv_string().unwrap();
+        let mut lines = csv.lines();
+        let header: Vec<_> = lines.next().unwrap().split(',').collect();
+        let row: Vec<_> = lines.next().unwrap().split(',').collect();
+        assert_eq!(header.len(), FILE_HEADERS.len());
+        assert_eq!(row.len(), header.len());
+        let classes = header
+            .iter()
+            .position(|column| *column == "status_code_classes")
+            .unwrap();
+        assert_eq!(row[classes], "2xx:2 4xx:1 5xx:1");
+        assert_eq!(row[classes + 1], "200:1 201:1 404:1 503:1");
+        let aggregated: Vec<_> = lines.next().unwrap().split(',').collect();
+        assert_eq!(aggregated[classes], "2xx:2 4xx:1 5xx:1");
+    }
+
+    #[test]
+    fn failed_response_times_are_recorded_separately() {
+        let mut all_results = AllResults::default();
+        let info = ResponseInfo::new();
+        all_results.add_success(&endpoint_type_name("/cart"), None, 1.0, &info);
+        all_results.add_failure(&endpoint_type_name("/cart"), None, Some(3.0), &info);
+        // failures without a response time are only counted
+        all_results.add_failure(&endpoint_type_name("/cart"), None, None, &info);
+        all_results.calculate_on_update_interval(&Duration::from_secs(1));
+
+        let cart = all_results.get_by_type_and_name("GET", "/cart").unwrap();
+        assert_eq!(cart.get_total_requests(), 3);
+        assert_eq!(cart.get_total_failed_requests(), 2);
+        assert_eq!(cart.get_average_response_time(), 1.0);
+        assert_eq!(cart.get_total_failed_response_time(), 3.0);
+        assert_eq!(cart.get_average_failed_response_time(), 3.0);
     }
 }
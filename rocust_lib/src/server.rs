@@ -1,4 +1,7 @@
 use crate::{
+    custom_metrics::AllMetrics,
+    errors::{AllErrors, SerErrorStats},
+    panics::{AllPanics, PanicStats},
     prometheus_exporter::PrometheusExporter,
     results::{AllResults, SerAllResults},
     test::controller::TestController,
@@ -13,48 +16,40 @@ use hyper::{Error as HyperError, StatusCode};
 use std::{net::SocketAddr, sync::Arc};
 use tokio::sync::RwLock;
 
+// everything the handlers need, cloned for every request
 #[derive(Clone)]
-struct ServerState {
-    test_controller: TestController,
-    all_results_arc_rwlock: Arc<RwLock<AllResults>>,
-    prometheus_exporter_arc: Arc<PrometheusExporter>,
+pub(crate) struct ServerState {
+    pub(crate) test_controller: TestController,
+    pub(crate) all_results_arc_rwlock: Arc<RwLock<AllResults>>,
+    pub(crate) all_panics_arc_rwlock: Arc<RwLock<AllPanics>>,
+    pub(crate) all_errors_arc_rwlock: Arc<RwLock<AllErrors>>,
+    pub(crate) all_failures_arc_rwlock: Arc<RwLock<AllErrors>>,
+    pub(crate) all_metrics_arc_rwlock: Arc<RwLock<AllMetrics>>,
+    pub(crate) prometheus_exporter_arc: Arc<PrometheusExporter>,
 }
 
 pub struct Server {
-    test_controller: TestController,
-    all_results_arc_rwlock: Arc<RwLock<AllResults>>,
-    prometheus_exporter_arc: Arc<PrometheusExporter>,
+    server_state: ServerState,
     addr: SocketAddr,
 }
 
 impl Server {
-    pub(crate) fn new(
-        test_controller: TestController,
-        all_results_arc_rwlock: Arc<RwLock<AllResults>>,
-        prometheus_exporter_arc: Arc<PrometheusExporter>,
-        addr: SocketAddr,
-    ) -> Self {
-        Self {
-            test_controller,
-            all_results_arc_rwlock,
-            prometheus_exporter_arc,
-            addr,
-        }
+    pub(crate) fn new(server_state: ServerState, addr: SocketAddr) -> Self {
+        Self { server_state, addr }
     }
 
     pub async fn run(&self) -> Result<(), HyperError> {
         let app = Router::new()
             .route("/results", get(get_results))
+            .route("/panics", get(get_panics))
+            .route("/errors", get(get_errors))
+            .route("/failures", get(get_failures))
             .route("/metrics", get(metrics))
             .route("/stop", get(stop))
-            .with_state(ServerState {
-                test_controller: self.test_controller.clone(),
-                all_results_arc_rwlock: self.all_results_arc_rwlock.clone(),
-                prometheus_exporter_arc: self.prometheus_exporter_arc.clone(),
-            });
+            .with_state(self.server_state.clone());
         axum::Server::bind(&self.addr)
             .serve(app.into_make_service())
-            .with_graceful_shutdown(self.test_controller.cancelled())
+            .with_graceful_shutdown(self.server_state.test_controller.cancelled())
             .await
     }
 }
@@ -71,9 +66,35 @@ async fn get_results(State(server_state): State<ServerState>) -> impl IntoRespon
         .await
         .clone()
         .into();
+    let ser_all_results = ser_all_results.with_metrics(
+        server_state
+            .all_metrics_arc_rwlock
+            .read()
+            .await
+            .ser_metrics(),
+    );
     Json(ser_all_results)
 }
 
+async fn get_panics(State(server_state): State<ServerState>) -> impl IntoResponse {
+    let panics: Vec<PanicStats> = server_state.all_panics_arc_rwlock.read().await.ser_panics();
+    Json(panics)
+}
+
+async fn get_errors(State(server_state): State<ServerState>) -> impl IntoResponse {
+    let errors: Vec<SerErrorStats> = server_state.all_errors_arc_rwlock.read().await.ser_errors();
+    Json(errors)
+}
+
+async fn get_failures(State(server_state): State<ServerState>) -> impl IntoResponse {
+    let failures: Vec<SerErrorStats> = server_state
+        .all_failures_arc_rwlock
+        .read()
+        .await
+        .ser_errors();
+    Json(failures)
+}
+
 async fn metrics(State(server_state): State<ServerState>) -> impl IntoResponse {
     let mut response: Response<String> = Response::default();
 
@@ -0,0 +1,264 @@
+use crate::histogram::ValueHistogram;
+use prettytable::{row, Cell, Row, Table};
+use serde::{ser::SerializeStruct, Serialize};
+use std::{collections::HashMap, time::Duration};
+
+const CONSOLE_HEADERS: [&str; 11] = [
+    "USER",
+    "TASK",
+    "TOTAL EXEC",
+    "EXEC/S",
+    "AVG DURATION",
+    "MIN DURATION",
+    "MEDIAN DURATION",
+    "90% DURATION",
+    "95% DURATION",
+    "99% DURATION",
+    "MAX DURATION",
+];
+
+// durations of whole tasks, a task may contain multiple requests
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct TaskStats {
+    total_executions: u32,
+    total_duration: f64,
+    average_duration: f64,
+    min_duration: f64,
+    median_duration: f64,
+    percentile_90_duration: f64,
+    percentile_95_duration: f64,
+    percentile_99_duration: f64,
+    max_duration: f64,
+    executions_per_second: f64,
+    #[serde(skip)]
+    histogram: ValueHistogram,
+}
+
+impl TaskStats {
+    fn add_execution(&mut self, duration: f64) {
+        self.total_executions += 1;
+        self.total_duration += duration;
+        if self.min_duration == 0.0 || duration < self.min_duration {
+            self.min_duration = duration;
+        }
+        if duration > self.max_duration {
+            self.max_duration = duration;
+        }
+        self.histogram.record(duration);
+    }
+
+    fn calculate_on_update_interval(&mut self, elapsed: &Duration) {
+        self.average_duration = self.total_duration / self.total_executions as f64;
+        self.executions_per_second = self.total_executions as f64 / elapsed.as_secs_f64();
+        [
+            self.median_duration,
+            self.percentile_90_duration,
+            self.percentile_95_duration,
+            self.percentile_99_duration,
+        ] = self
+            .histogram
+            .percentiles(self.min_duration, self.max_duration);
+    }
+
+    pub fn get_total_executions(&self) -> u32 {
+        self.total_executions
+    }
+
+    pub fn get_total_duration(&self) -> f64 {
+        self.total_duration
+    }
+
+    pub fn get_average_duration(&self) -> f64 {
+        self.average_duration
+    }
+
+    pub fn get_min_duration(&self) -> f64 {
+        self.min_duration
+    }
+
+    pub fn get_median_duration(&self) -> f64 {
+        self.median_duration
+    }
+
+    pub fn get_percentile_90_duration(&self) -> f64 {
+        self.percentile_90_duration
+    }
+
+    pub fn get_percentile_95_duration(&self) -> f64 {
+        self.percentile_95_duration
+    }
+
+    pub fn get_percentile_99_duration(&self) -> f64 {
+        self.percentile_99_duration
+    }
+
+    pub fn get_max_duration(&self) -> f64 {
+        self.max_duration
+    }
+
+    pub fn get_executions_per_second(&self) -> f64 {
+        self.executions_per_second
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct UserTaskName {
+    pub user_name: &'static str,
+    pub task_name: &'static str,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct SerTaskStats {
+    user_task_name: UserTaskName,
+    task_stats: TaskStats,
+}
+
+impl Serialize for SerTaskStats {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let mut state = serializer.serialize_struct("SerTaskStats", 3)?;
+        state.serialize_field("user_name", &self.user_task_name.user_name)?;
+        state.serialize_field("task_name", &self.user_task_name.task_name)?;
+        state.serialize_field("stats", &self.task_stats)?;
+        state.end()
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct AllTaskStats {
+    task_stats: HashMap<UserTaskName, TaskStats>,
+}
+
+impl AllTaskStats {
+    pub(crate) fn add_execution(&mut self, user_task_name: UserTaskName, duration: f64) {
+        self.task_stats
+            .entry(user_task_name)
+            .or_default()
+            .add_execution(duration);
+    }
+
+    pub(crate) fn calculate_on_update_interval(&mut self, elapsed: &Duration) {
+        for task_stats in self.task_stats.values_mut() {
+            task_stats.calculate_on_update_interval(elapsed);
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.task_stats.is_empty()
+    }
+
+    pub(crate) fn table_string(&self, precision: usize) -> String {
+        let mut table = Table::new();
+        table.add_row(Row::new(
+            CONSOLE_HEADERS.iter().map(|s| Cell::new(s)).collect(),
+        ));
+        for (user_task_name, task_stats) in &self.task_stats {
+            table.add_row(row![
+                user_task_name.user_name,
+                user_task_name.task_name,
+                format!("{:.1$}", task_stats.total_executions, precision),
+                format!("{:.1$}", task_stats.executions_per_second, precision),
+                format!("{:.1$}", task_stats.average_duration, precision),
+                format!("{:.1$}", task_stats.min_duration, precision),
+                format!("{:.1$}", task_stats.median_duration, precision),
+                format!("{:.1$}", task_stats.percentile_90_duration, precision),
+                format!("{:.1$}", task_stats.percentile_95_duration, precision),
+                format!("{:.1$}", task_stats.percentile_99_duration, precision),
+                format!("{:.1$}", task_stats.max_duration, precision),
+            ]);
+        }
+        table.to_string()
+    }
+
+    pub fn get_by_user_and_task_name(
+        &self,
+        user_name: &'static str,
+        task_name: &'static str,
+    ) -> Option<&TaskStats> {
+        self.task_stats.get(&UserTaskName {
+            user_name,
+            task_name,
+        })
+    }
+
+    pub fn get_task_stats(&self) -> &HashMap<UserTaskName, TaskStats> {
+        &self.task_stats
+    }
+
+    pub(crate) fn ser_task_stats(&self) -> Vec<SerTaskStats> {
+        self.task_stats
+            .iter()
+            .map(|(user_task_name, task_stats)| SerTaskStats {
+                user_task_name: user_task_name.clone(),
+                task_stats: task_stats.clone(),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user_task_name(user_name: &'static str, task_name: &'static str) -> UserTaskName {
+        UserTaskName {
+            user_name,
+            task_name,
+        }
+    }
+
+    #[test]
+    fn executions_are_grouped_by_user_and_task() {
+        let mut all_task_stats = AllTaskStats::default();
+        for duration in [0.1, 0.2, 0.3, 0.4] {
+            all_task_stats.add_execution(user_task_name("Shopper", "checkout"), duration);
+        }
+        all_task_stats.add_execution(user_task_name("Shopper", "browse"), 1.0);
+        all_task_stats.add_execution(user_task_name("Admin", "checkout"), 2.0);
+        all_task_stats.calculate_on_update_interval(&Duration::from_secs(2));
+
+        assert_eq!(all_task_stats.get_task_stats().len(), 3);
+        let checkout = all_task_stats
+            .get_by_user_and_task_name("Shopper", "checkout")
+            .unwrap();
+        assert_eq!(checkout.get_total_executions(), 4);
+        assert_eq!(checkout.get_executions_per_second(), 2.0);
+        assert!((checkout.get_average_duration() - 0.25).abs() < 1e-9);
+        assert_eq!(checkout.get_min_duration(), 0.1);
+        assert_eq!(checkout.get_max_duration(), 0.4);
+        assert!((checkout.get_median_duration() - 0.2).abs() < 0.001);
+        let admin = all_task_stats
+            .get_by_user_and_task_name("Admin", "checkout")
+            .unwrap();
+        assert_eq!(admin.get_total_executions(), 1);
+    }
+
+    #[test]
+    fn percentiles_are_not_above_the_max() {
+        let mut all_task_stats = AllTaskStats::default();
+        all_task_stats.add_execution(user_task_name("Shopper", "checkout"), 1.2345);
+        all_task_stats.calculate_on_update_interval(&Duration::from_secs(1));
+
+        let checkout = all_task_stats
+            .get_by_user_and_task_name("Shopper", "checkout")
+            .unwrap();
+        assert_eq!(checkout.get_percentile_99_duration(), 1.2345);
+        assert_eq!(checkout.get_median_duration(), 1.2345);
+    }
+
+    #[test]
+    fn stats_are_serialized_with_user_and_task_name() {
+        let mut all_task_stats = AllTaskStats::default();
+        all_task_stats.add_execution(user_task_name("Shopper", "checkout"), 0.5);
+        all_task_stats.calculate_on_update_interval(&Duration::from_secs(1));
+
+        let json = serde_json::to_value(all_task_stats.ser_task_stats()).unwrap();
+
+        assert_eq!(json[0]["user_name"], "Shopper");
+        assert_eq!(json[0]["task_name"], "checkout");
+        assert_eq!(json[0]["stats"]["total_executions"], 1);
+        assert!(json[0]["stats"].get("histogram").is_none());
+    }
+}
@@ -1,5 +1,5 @@
 use crate::{test::user::context::Context, traits::Prioritised};
-use std::{future::Future, pin::Pin};
+use std::{future::Future, pin::Pin, time::Duration};
 
 type AsyncTaskFunctionSig<T> =
     for<'a> fn(&'a mut T, &'a Context) -> Pin<Box<dyn Future<Output = ()> + Send + 'a>>;
@@ -15,6 +15,8 @@ where
 {
     pub(crate) priority: u64,
     pub(crate) name: &'static str,
+    pub(crate) tags: &'static [&'static str],
+    pub(crate) timeout: Option<Duration>,
     pub(crate) func: AsyncTaskFunctionSig<T>,
 }
 
@@ -23,14 +25,47 @@ impl<T> AsyncTask<T> {
         AsyncTask {
             priority,
             name,
+            tags: &[],
+            timeout: None,
             func,
         }
     }
 
+    pub fn with_tags(self, tags: &'static [&'static str]) -> Self {
+        Self { tags, ..self }
+    }
+
+    // overrides the task timeout of the user type and the test config
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        Self {
+            timeout: Some(timeout),
+            ..self
+        }
+    }
+
     pub fn get_priority(&self) -> u64 {
         self.priority
     }
 
+    pub fn get_timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    pub fn get_name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn get_tags(&self) -> &'static [&'static str] {
+        self.tags
+    }
+
+    pub(crate) fn get_events_task_info(&self) -> EventsTaskInfo {
+        EventsTaskInfo {
+            name: self.name,
+            tags: self.tags,
+        }
+    }
+
     pub async fn call(&self, user: &mut T, context: &Context) {
         (self.func)(user, context).await;
     }
@@ -61,8 +96,10 @@ impl<T> BlockingTask<T> {
     }
 }
 
+#[derive(Debug, Clone, Copy)]
 pub(crate) struct EventsTaskInfo {
     pub(crate) name: &'static str,
+    pub(crate) tags: &'static [&'static str],
 }
 
 impl<T> Prioritised for AsyncTask<T> {
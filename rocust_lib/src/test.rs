@@ -1,15 +1,33 @@
 pub mod config;
 pub(crate) mod controller;
+mod html_report;
+mod junit_report;
+mod line_writers;
+mod raw_log_writer;
+mod results_history_writer;
 pub mod spawn_coordinator;
+mod statsd_sender;
+mod summary;
 pub mod user;
 mod writers;
 
+#[cfg(feature = "otel")]
+use crate::otel::OtelExporter;
 use crate::{
+    checks::AllChecks,
+    custom_metrics::AllMetrics,
+    errors::{AllErrors, ErrorKind},
     messages::{MainMessage, ResultMessage},
-    prometheus_exporter::{PrometheusExporter, RequestLabel, TaskLabel, UserCountLabel, UserLabel},
-    results::AllResults,
-    server::Server,
+    panics::{self, AllPanics},
+    prometheus_exporter::{
+        CheckLabel, CustomMetricLabel, PrometheusExporter, RequestLabel, TaskDurationLabel,
+        TaskLabel, TestState, UserCountLabel, UserLabel,
+    },
+    results::{AllResults, EndpointTypeName, TASK_ENDPOINT_TYPE},
+    server::{Server, ServerState},
+    task_stats::{AllTaskStats, UserTaskName},
     test::config::SupportedExtension,
+    thresholds::{self, StopCondition, StopConditionMet},
     utils,
 };
 use rand::Rng;
@@ -25,7 +43,11 @@ use tokio_util::sync::CancellationToken;
 use self::{
     config::TestConfig,
     controller::TestController,
+    html_report::{HtmlReport, ResultsTimeline},
+    raw_log_writer::RawLogWriter,
     spawn_coordinator::SpawnCoordinator,
+    statsd_sender::StatsdSender,
+    summary::{Summary, SummaryError},
     user::{UserStatsCollection, UserStatus},
     writers::Writers,
 };
@@ -37,24 +59,93 @@ pub struct Test {
     writers: Writers,
     total_users_spawned_arc_rwlock: Arc<RwLock<u64>>,
     all_results_arc_rwlock: Arc<RwLock<AllResults>>,
+    all_panics_arc_rwlock: Arc<RwLock<AllPanics>>,
+    all_errors_arc_rwlock: Arc<RwLock<AllErrors>>,
+    // failures with a reason
+    all_failures_arc_rwlock: Arc<RwLock<AllErrors>>,
+    all_checks_arc_rwlock: Arc<RwLock<AllChecks>>,
+    all_metrics_arc_rwlock: Arc<RwLock<AllMetrics>>,
+    all_task_stats_arc_rwlock: Arc<RwLock<AllTaskStats>>,
     user_stats_collection: UserStatsCollection,
     start_timestamp_arc_rwlock: Arc<RwLock<Instant>>,
+    // only recorded if the html report is written
+    results_timeline_arc_rwlock: Arc<RwLock<ResultsTimeline>>,
+    // the stop condition that stopped the test, if any
+    stop_condition_met_arc_rwlock: Arc<RwLock<Option<StopConditionMet>>>,
     prometheus_exporter_arc: Arc<PrometheusExporter>,
+    statsd_sender: Option<StatsdSender>,
+    raw_log_writer: Option<RawLogWriter>,
+    #[cfg(feature = "otel")]
+    otel_exporter: Option<OtelExporter>,
+    total_users_failed_to_start: u64,
 }
 
 impl Test {
     #[must_use]
     pub async fn new(test_config: TestConfig) -> Self {
         let writers = Writers::new(&test_config).await;
+        if test_config.capture_panic_backtraces {
+            panics::install_backtrace_hook();
+        }
+        let all_errors = AllErrors::new(ErrorKind::Error, test_config.max_distinct_errors);
+        let all_failures = AllErrors::new(ErrorKind::Failure, test_config.max_distinct_errors);
+        let prometheus_exporter = PrometheusExporter::new(&test_config);
+        let trend_scale = test_config.trend_scale;
+        let statsd_sender = if let Some(statsd_output) = &test_config.statsd_output {
+            match StatsdSender::new(statsd_output).await {
+                Ok(statsd_sender) => Some(statsd_sender),
+                Err(error) => {
+                    tracing::error!(%error, "Failed to create statsd sender");
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        let raw_log_writer = if let Some(raw_log) = &test_config.raw_log {
+            match RawLogWriter::new(raw_log).await {
+                Ok(raw_log_writer) => Some(raw_log_writer),
+                Err(error) => {
+                    tracing::error!(%error, "Failed to create raw log writer");
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        #[cfg(feature = "otel")]
+        let otel_exporter = test_config.otel_output.as_ref().and_then(|otel_output| {
+            let export_interval = Duration::from_secs(test_config.update_interval_in_secs);
+            match OtelExporter::new(otel_output, export_interval) {
+                Ok(otel_exporter) => Some(otel_exporter),
+                Err(error) => {
+                    tracing::error!(%error, "Failed to create otel exporter");
+                    None
+                }
+            }
+        });
         Self {
             test_config,
             token: CancellationToken::new(),
             writers,
             total_users_spawned_arc_rwlock: Arc::new(RwLock::new(0)),
-            all_results_arc_rwlock: Arc::new(RwLock::new(AllResults::default())),
+            all_results_arc_rwlock: Arc::new(RwLock::new(AllResults::with_percentiles())),
+            all_panics_arc_rwlock: Arc::new(RwLock::new(AllPanics::default())),
+            all_errors_arc_rwlock: Arc::new(RwLock::new(all_errors)),
+            all_failures_arc_rwlock: Arc::new(RwLock::new(all_failures)),
+            all_checks_arc_rwlock: Arc::new(RwLock::new(AllChecks::default())),
+            all_metrics_arc_rwlock: Arc::new(RwLock::new(AllMetrics::new(trend_scale))),
+            all_task_stats_arc_rwlock: Arc::new(RwLock::new(AllTaskStats::default())),
             user_stats_collection: UserStatsCollection::new(),
             start_timestamp_arc_rwlock: Arc::new(RwLock::new(Instant::now())),
-            prometheus_exporter_arc: Arc::new(PrometheusExporter::new()),
+            results_timeline_arc_rwlock: Arc::new(RwLock::new(ResultsTimeline::default())),
+            stop_condition_met_arc_rwlock: Arc::new(RwLock::new(None)),
+            prometheus_exporter_arc: Arc::new(prometheus_exporter),
+            statsd_sender,
+            raw_log_writer,
+            #[cfg(feature = "otel")]
+            otel_exporter,
+            total_users_failed_to_start: 0,
         }
     }
 
@@ -75,12 +166,32 @@ impl Test {
     }
 
     async fn print_stats_to_stdout(
-        precision: usize,
-        print_to_stdout: bool,
+        test_config: &TestConfig,
         all_results: &AllResults,
+        all_errors: &AllErrors,
+        all_failures: &AllErrors,
+        all_checks: &AllChecks,
+        all_metrics: &AllMetrics,
+        all_task_stats: &AllTaskStats,
     ) {
-        if print_to_stdout {
-            let table_string = all_results.table_string(precision);
+        if test_config.print_to_stdout {
+            let precision = test_config.precision;
+            let mut table_string = all_results.table_string(precision);
+            if !all_errors.is_empty() {
+                table_string.push_str(&all_errors.table_string());
+            }
+            if !all_failures.is_empty() {
+                table_string.push_str(&all_failures.table_string());
+            }
+            if !all_checks.is_empty() {
+                table_string.push_str(&all_checks.table_string(precision));
+            }
+            if !all_metrics.is_empty() {
+                table_string.push_str(&all_metrics.table_string(precision));
+            }
+            if !all_task_stats.is_empty() {
+                table_string.push_str(&all_task_stats.table_string(precision));
+            }
             let mut stdout = io::stdout();
             let _ = stdout.write_all(table_string.as_bytes()).await;
         }
@@ -134,20 +245,21 @@ impl Test {
     }
 
     fn strat_server(&self) -> JoinHandle<()> {
-        let test_controller = self.create_test_controller();
-        let all_results_arc_rwlock = self.all_results_arc_rwlock.clone();
-        let prometheus_exporter_arc = self.prometheus_exporter_arc.clone();
+        let server_state = ServerState {
+            test_controller: self.create_test_controller(),
+            all_results_arc_rwlock: self.all_results_arc_rwlock.clone(),
+            all_panics_arc_rwlock: self.all_panics_arc_rwlock.clone(),
+            all_errors_arc_rwlock: self.all_errors_arc_rwlock.clone(),
+            all_failures_arc_rwlock: self.all_failures_arc_rwlock.clone(),
+            all_metrics_arc_rwlock: self.all_metrics_arc_rwlock.clone(),
+            prometheus_exporter_arc: self.prometheus_exporter_arc.clone(),
+        };
         let addr = self.test_config.server_address;
         match addr {
             Some(addr) => {
                 tracing::info!(address = ?addr, "Starting server");
                 tokio::spawn(async move {
-                    let server = Server::new(
-                        test_controller,
-                        all_results_arc_rwlock,
-                        prometheus_exporter_arc,
-                        addr,
-                    );
+                    let server = Server::new(server_state, addr);
                     // no tokio::select! here because axum is running with graceful shutdown
                     let res = server.run().await;
                     if let Err(error) = res {
@@ -167,8 +279,15 @@ impl Test {
         let token = self.token.clone();
         let total_users_spawned_arc_rwlock = self.total_users_spawned_arc_rwlock.clone();
         let all_results_arc_rwlock = self.all_results_arc_rwlock.clone();
+        let all_errors_arc_rwlock = self.all_errors_arc_rwlock.clone();
+        let all_failures_arc_rwlock = self.all_failures_arc_rwlock.clone();
+        let all_checks_arc_rwlock = self.all_checks_arc_rwlock.clone();
+        let all_metrics_arc_rwlock = self.all_metrics_arc_rwlock.clone();
+        let all_task_stats_arc_rwlock = self.all_task_stats_arc_rwlock.clone();
         let prometheus_exporter_arc = self.prometheus_exporter_arc.clone();
         let start_timestamp_arc_rwlock = self.start_timestamp_arc_rwlock.clone();
+        let results_timeline_arc_rwlock = self.results_timeline_arc_rwlock.clone();
+        let stop_condition_met_arc_rwlock = self.stop_condition_met_arc_rwlock.clone();
         let test_config = self.test_config.clone();
         let writers = self.writers.clone();
         tokio::spawn(async move {
@@ -176,6 +295,7 @@ impl Test {
             loop {
                 tokio::select! {
                     _ = token.cancelled() => {
+                        prometheus_exporter_arc.set_test_state(TestState::Stopping);
                         break;
                     }
                     _ = tokio::time::sleep(std::time::Duration::from_secs(test_config.update_interval_in_secs)) => {
@@ -185,6 +305,7 @@ impl Test {
                             tracing::info!(total_users_spawned=*total_users_spawned_gaurd,total_spawnable_users=total_spawnable_user_count,  "Spawning users");
                             if *total_users_spawned_gaurd == total_spawnable_user_count {
                                 tracing::info!(total_spawnable_users=total_spawnable_user_count, "All users spawned");
+                                prometheus_exporter_arc.set_test_state(TestState::Running);
                                 print_total_spawned_users = false;
                             }
                         }
@@ -193,11 +314,56 @@ impl Test {
 
                         let elapsed_time = Test::calculate_elapsed_time(&*start_timestamp_arc_rwlock.read().await);
 
+                        prometheus_exporter_arc.set_elapsed_time(&elapsed_time);
+
                         all_results_gaurd.calculate_on_update_interval(&elapsed_time);
 
-                        Test::print_stats_to_stdout(test_config.precision, test_config.print_to_stdout, &*all_results_gaurd).await;
+                        if let Some(stop_condition_met) = Test::evaluate_stop_conditions(&test_config.stop_conditions, &all_results_gaurd) {
+                            tracing::warn!(stop_condition = %stop_condition_met.stop_condition, observed = stop_condition_met.observed, "Stop condition met, stopping test");
+                            *stop_condition_met_arc_rwlock.write().await = Some(stop_condition_met);
+                            token.cancel();
+                        }
+
+                        if test_config.html_report_file.is_some() {
+                            let total_users_spawned = *total_users_spawned_arc_rwlock.read().await;
+                            results_timeline_arc_rwlock.write().await.record(&elapsed_time, total_users_spawned, &all_results_gaurd);
+                        }
+
+                        let mut all_task_stats_gaurd = all_task_stats_arc_rwlock.write().await;
+
+                        all_task_stats_gaurd.calculate_on_update_interval(&elapsed_time);
 
-                        writers.write_on_update_interval(&*all_results_gaurd, &*prometheus_exporter_arc).await;
+                        let mut all_metrics_gaurd = all_metrics_arc_rwlock.write().await;
+
+                        all_metrics_gaurd.calculate_on_update_interval(&elapsed_time);
+
+                        prometheus_exporter_arc.update_custom_trends(&all_metrics_gaurd);
+
+                        let all_errors_gaurd = all_errors_arc_rwlock.read().await;
+
+                        let all_failures_gaurd = all_failures_arc_rwlock.read().await;
+
+                        let all_checks_gaurd = all_checks_arc_rwlock.read().await;
+
+                        Test::print_stats_to_stdout(&test_config, &*all_results_gaurd, &all_errors_gaurd, &all_failures_gaurd, &all_checks_gaurd, &all_metrics_gaurd, &all_task_stats_gaurd).await;
+
+                        drop(all_checks_gaurd);
+
+                        drop(all_metrics_gaurd);
+
+                        drop(all_task_stats_gaurd);
+
+                        writers.write_on_update_interval(&*all_results_gaurd, &all_errors_gaurd, &all_failures_gaurd, &*prometheus_exporter_arc, &elapsed_time).await;
+
+                        let line_protocols = writers.line_protocols(&all_results_gaurd);
+
+                        drop(all_failures_gaurd);
+
+                        drop(all_errors_gaurd);
+
+                        drop(all_results_gaurd);
+
+                        writers.send_on_update_interval(&prometheus_exporter_arc, line_protocols).await;
                     }
                 }
             }
@@ -208,6 +374,9 @@ impl Test {
         while let Some(msg) = results_rx.recv().await {
             match msg {
                 MainMessage::ResultMessage(result_msg) => {
+                    if let Some(raw_log_writer) = &self.raw_log_writer {
+                        raw_log_writer.log_result(&result_msg).await;
+                    }
                     self.on_result_message(result_msg).await;
                 }
 
@@ -215,9 +384,25 @@ impl Test {
                     self.on_user_spawned_message(user_spawned_msg).await;
                 }
 
+                MainMessage::UserFailedToStart(user_failed_to_start_msg) => {
+                    self.on_user_failed_to_start_message(user_failed_to_start_msg);
+                }
+
                 // tasks with suicide or panic are not included
                 MainMessage::TaskExecuted(user_fired_task_msg) => {
-                    self.on_task_excuted_message(user_fired_task_msg);
+                    self.on_task_excuted_message(user_fired_task_msg).await;
+                }
+
+                MainMessage::TaskTimedOut(task_timed_out_msg) => {
+                    self.on_task_timed_out_message(task_timed_out_msg).await;
+                }
+
+                MainMessage::Check(check_msg) => {
+                    self.on_check_message(check_msg).await;
+                }
+
+                MainMessage::Metric(metric_msg) => {
+                    self.on_metric_message(metric_msg).await;
                 }
 
                 MainMessage::UserSelfStopped(user_self_stopped_msg) => {
@@ -229,7 +414,11 @@ impl Test {
                 }
 
                 MainMessage::UserPanicked(user_panicked_msg) => {
-                    self.on_user_panicked_message(user_panicked_msg);
+                    self.on_user_panicked_message(user_panicked_msg).await;
+                }
+
+                MainMessage::UserRestarted(user_restarted_msg) => {
+                    self.on_user_restarted_message(user_restarted_msg);
                 }
 
                 MainMessage::UserUnknownStatus(user_unknown_status_msg) => {
@@ -243,47 +432,97 @@ impl Test {
     // will not be refactored because of borrow checker issues :->
     async fn on_result_message(&mut self, result_msg: ResultMessage) {
         let mut all_results_gaurd = self.all_results_arc_rwlock.write().await;
+        let results_per_task = self.test_config.results_per_task;
+
+        if let Some(statsd_sender) = &self.statsd_sender {
+            statsd_sender.send_result(&result_msg);
+        }
+        #[cfg(feature = "otel")]
+        if let Some(otel_exporter) = &self.otel_exporter {
+            otel_exporter.add_result(&result_msg);
+        }
 
         match result_msg {
             ResultMessage::Success(sucess_result_msg) => {
+                let task_name = sucess_result_msg.task_info.map(|task_info| task_info.name);
+                let results_task_name = task_name.filter(|_| results_per_task);
+
                 all_results_gaurd.add_success(
                     &sucess_result_msg.endpoint_type_name,
+                    results_task_name,
                     sucess_result_msg.response_time,
+                    &sucess_result_msg.response_info,
                 );
 
                 self.user_stats_collection.add_success(
                     &sucess_result_msg.user_info.id,
                     &sucess_result_msg.endpoint_type_name,
+                    results_task_name,
                     sucess_result_msg.response_time,
+                    &sucess_result_msg.response_info,
                 );
 
                 self.prometheus_exporter_arc.add_success(
                     RequestLabel {
                         endpoint_type: sucess_result_msg.endpoint_type_name.r#type,
                         endpoint_name: sucess_result_msg.endpoint_type_name.name,
-                        user_id: sucess_result_msg.user_info.id,
+                        user_id: Some(sucess_result_msg.user_info.id),
                         user_name: sucess_result_msg.user_info.name,
+                        task_name: task_name.unwrap_or_default(),
                     },
                     sucess_result_msg.response_time,
+                    &sucess_result_msg.response_info,
                 );
             }
             ResultMessage::Failure(failure_result_msg) => {
-                all_results_gaurd.add_failure(&failure_result_msg.endpoint_type_name);
+                let task_name = failure_result_msg.task_info.map(|task_info| task_info.name);
+                let results_task_name = task_name.filter(|_| results_per_task);
+
+                all_results_gaurd.add_failure(
+                    &failure_result_msg.endpoint_type_name,
+                    results_task_name,
+                    failure_result_msg.response_time,
+                    &failure_result_msg.response_info,
+                );
 
                 self.user_stats_collection.add_failure(
                     &failure_result_msg.user_info.id,
                     &failure_result_msg.endpoint_type_name,
+                    results_task_name,
+                    failure_result_msg.response_time,
+                    &failure_result_msg.response_info,
                 );
 
-                self.prometheus_exporter_arc.add_failure(RequestLabel {
-                    endpoint_type: failure_result_msg.endpoint_type_name.r#type,
-                    endpoint_name: failure_result_msg.endpoint_type_name.name,
-                    user_id: failure_result_msg.user_info.id,
-                    user_name: failure_result_msg.user_info.name,
-                });
+                // failures without a reason are only counted
+                if let Some(reason) = &failure_result_msg.reason {
+                    self.all_failures_arc_rwlock
+                        .write()
+                        .await
+                        .add_error(&failure_result_msg.endpoint_type_name, reason);
+                }
+
+                self.prometheus_exporter_arc.add_failure(
+                    RequestLabel {
+                        endpoint_type: failure_result_msg.endpoint_type_name.r#type,
+                        endpoint_name: failure_result_msg.endpoint_type_name.name,
+                        user_id: Some(failure_result_msg.user_info.id),
+                        user_name: failure_result_msg.user_info.name,
+                        task_name: task_name.unwrap_or_default(),
+                    },
+                    &failure_result_msg.response_info,
+                );
             }
             ResultMessage::Error(error_result_msg) => {
+                let task_name = error_result_msg.task_info.map(|task_info| task_info.name);
+                let results_task_name = task_name.filter(|_| results_per_task);
+
                 all_results_gaurd.add_error(
+                    &error_result_msg.endpoint_type_name,
+                    results_task_name,
+                    &error_result_msg.error,
+                );
+
+                self.all_errors_arc_rwlock.write().await.add_error(
                     &error_result_msg.endpoint_type_name,
                     &error_result_msg.error,
                 );
@@ -291,14 +530,16 @@ impl Test {
                 self.user_stats_collection.add_error(
                     &error_result_msg.user_info.id,
                     &error_result_msg.endpoint_type_name,
+                    results_task_name,
                     &error_result_msg.error,
                 );
 
                 self.prometheus_exporter_arc.add_error(RequestLabel {
                     endpoint_type: error_result_msg.endpoint_type_name.r#type,
                     endpoint_name: error_result_msg.endpoint_type_name.name,
-                    user_id: error_result_msg.user_info.id,
+                    user_id: Some(error_result_msg.user_info.id),
                     user_name: error_result_msg.user_info.name,
+                    task_name: task_name.unwrap_or_default(),
                 });
             }
         }
@@ -318,6 +559,9 @@ impl Test {
         let mut total_users_spawned_gaurd = self.total_users_spawned_arc_rwlock.write().await;
         *total_users_spawned_gaurd += 1;
 
+        self.prometheus_exporter_arc
+            .set_spawned_users(*total_users_spawned_gaurd);
+
         self.user_stats_collection.insert_user(
             user_spawned_msg.user_info.id,
             user_spawned_msg.user_info.name,
@@ -328,6 +572,44 @@ impl Test {
         });
     }
 
+    #[inline]
+    fn on_user_failed_to_start_message(
+        &mut self,
+        user_failed_to_start_msg: crate::messages::UserFailedToStartMessage,
+    ) {
+        tracing::warn!(
+            user_name = &user_failed_to_start_msg.user_info.name,
+            user_id = &user_failed_to_start_msg.user_info.id,
+            error = %user_failed_to_start_msg.error,
+            "User failed to start",
+        );
+
+        self.user_stats_collection.set_user_status(
+            &user_failed_to_start_msg.user_info.id,
+            UserStatus::FailedToStart,
+        );
+
+        self.prometheus_exporter_arc.remove_user(UserCountLabel {
+            user_name: user_failed_to_start_msg.user_info.name,
+        });
+
+        self.prometheus_exporter_arc
+            .add_start_failure(UserCountLabel {
+                user_name: user_failed_to_start_msg.user_info.name,
+            });
+
+        self.total_users_failed_to_start += 1;
+        if let Some(abort_after) = self.test_config.start_failure_policy.abort_after {
+            if self.total_users_failed_to_start >= abort_after {
+                tracing::error!(
+                    total_users_failed_to_start = self.total_users_failed_to_start,
+                    "Too many users failed to start, stopping test"
+                );
+                self.token.cancel();
+            }
+        }
+    }
+
     #[inline]
     fn on_user_self_stopped_message(
         &mut self,
@@ -347,13 +629,13 @@ impl Test {
         });
 
         self.prometheus_exporter_arc.add_suicide(UserLabel {
-            user_id: user_self_stopped_msg.user_info.id,
+            user_id: Some(user_self_stopped_msg.user_info.id),
             user_name: user_self_stopped_msg.user_info.name,
         });
     }
 
     #[inline]
-    fn on_task_excuted_message(
+    async fn on_task_excuted_message(
         &mut self,
         user_fired_task_msg: crate::messages::TaskExecutedMessage,
     ) {
@@ -361,19 +643,155 @@ impl Test {
             user_name = &user_fired_task_msg.user_info.name,
             user_id = &user_fired_task_msg.user_info.id,
             task_name = &user_fired_task_msg.task_info.name,
+            duration = &user_fired_task_msg.duration,
             "User excuted a task"
         );
 
+        self.all_task_stats_arc_rwlock.write().await.add_execution(
+            UserTaskName {
+                user_name: user_fired_task_msg.user_info.name,
+                task_name: user_fired_task_msg.task_info.name,
+            },
+            user_fired_task_msg.duration,
+        );
+
+        self.prometheus_exporter_arc.observe_task_duration(
+            TaskDurationLabel {
+                user_name: user_fired_task_msg.user_info.name,
+                task_name: user_fired_task_msg.task_info.name,
+            },
+            user_fired_task_msg.duration,
+        );
+
         self.user_stats_collection
             .increment_total_tasks(&user_fired_task_msg.user_info.id);
 
         self.prometheus_exporter_arc.add_task(TaskLabel {
-            user_id: user_fired_task_msg.user_info.id,
+            user_id: Some(user_fired_task_msg.user_info.id),
             user_name: user_fired_task_msg.user_info.name,
             task_name: user_fired_task_msg.task_info.name,
         });
     }
 
+    #[inline]
+    async fn on_task_timed_out_message(
+        &mut self,
+        task_timed_out_msg: crate::messages::TaskTimedOutMessage,
+    ) {
+        tracing::warn!(
+            user_name = &task_timed_out_msg.user_info.name,
+            user_id = &task_timed_out_msg.user_info.id,
+            task_name = &task_timed_out_msg.task_info.name,
+            timeout_in_millis = &task_timed_out_msg.timeout_in_millis,
+            "Task timed out"
+        );
+
+        let endpoint_type_name = EndpointTypeName {
+            r#type: String::from(TASK_ENDPOINT_TYPE),
+            name: String::from(task_timed_out_msg.task_info.name),
+        };
+        let results_task_name =
+            Some(task_timed_out_msg.task_info.name).filter(|_| self.test_config.results_per_task);
+
+        self.all_results_arc_rwlock
+            .write()
+            .await
+            .add_timeout(&endpoint_type_name, results_task_name);
+
+        self.all_errors_arc_rwlock.write().await.add_error(
+            &endpoint_type_name,
+            &format!(
+                "Task timed out after {} ms",
+                task_timed_out_msg.timeout_in_millis
+            ),
+        );
+
+        self.user_stats_collection.add_timeout(
+            &task_timed_out_msg.user_info.id,
+            &endpoint_type_name,
+            results_task_name,
+        );
+
+        self.prometheus_exporter_arc.add_error(RequestLabel {
+            endpoint_type: endpoint_type_name.r#type,
+            endpoint_name: endpoint_type_name.name,
+            user_id: Some(task_timed_out_msg.user_info.id),
+            user_name: task_timed_out_msg.user_info.name,
+            task_name: task_timed_out_msg.task_info.name,
+        });
+
+        self.prometheus_exporter_arc.add_task_timeout(TaskLabel {
+            user_id: Some(task_timed_out_msg.user_info.id),
+            user_name: task_timed_out_msg.user_info.name,
+            task_name: task_timed_out_msg.task_info.name,
+        });
+    }
+
+    #[inline]
+    async fn on_check_message(&mut self, check_msg: crate::messages::CheckMessage) {
+        let task_name = check_msg
+            .task_info
+            .map(|task_info| task_info.name)
+            .unwrap_or_default();
+
+        let mut all_checks_gaurd = self.all_checks_arc_rwlock.write().await;
+
+        for (check_name, passed) in check_msg.checks {
+            tracing::trace!(
+                user_name = &check_msg.user_info.name,
+                user_id = &check_msg.user_info.id,
+                check_name = &check_name,
+                passed = passed,
+                "User executed a check"
+            );
+
+            all_checks_gaurd.add_check(&check_name, passed);
+
+            self.prometheus_exporter_arc.add_check(
+                CheckLabel {
+                    check_name,
+                    user_name: check_msg.user_info.name,
+                    task_name,
+                },
+                passed,
+            );
+        }
+    }
+
+    #[inline]
+    async fn on_metric_message(&mut self, metric_msg: crate::messages::MetricMessage) {
+        tracing::trace!(
+            user_name = &metric_msg.user_info.name,
+            user_id = &metric_msg.user_info.id,
+            metric_name = &metric_msg.name,
+            update = ?metric_msg.update,
+            "User updated a metric"
+        );
+
+        let mut all_metrics_gaurd = self.all_metrics_arc_rwlock.write().await;
+
+        match all_metrics_gaurd.update(&metric_msg.name, metric_msg.update) {
+            Ok(metric) => {
+                self.prometheus_exporter_arc.update_custom_metric(
+                    CustomMetricLabel {
+                        metric_name: metric_msg.name,
+                    },
+                    metric,
+                    metric_msg.update.value(),
+                );
+            }
+            Err(error) => {
+                tracing::warn!(
+                    user_name = &metric_msg.user_info.name,
+                    user_id = &metric_msg.user_info.id,
+                    metric_name = &metric_msg.name,
+                    %error,
+                    "Metric update rejected"
+                );
+            }
+        }
+    }
+
     #[inline]
     fn on_user_finished_message(
         &mut self,
@@ -384,29 +802,68 @@ impl Test {
     }
 
     #[inline]
-    fn on_user_panicked_message(
+    async fn on_user_panicked_message(
         &mut self,
         user_panicked_msg: crate::messages::UserPanickedMessage,
     ) {
         tracing::warn!(
             user_name = &user_panicked_msg.user_info.name,
             user_id = &user_panicked_msg.user_info.id,
+            task_name = user_panicked_msg.task_name,
+            message = %user_panicked_msg.message,
             "User panicked!"
         );
 
+        self.all_panics_arc_rwlock.write().await.add_panic(
+            &user_panicked_msg.message,
+            user_panicked_msg.user_info.name,
+            user_panicked_msg.task_name,
+            user_panicked_msg.backtrace.as_deref(),
+        );
+
         self.user_stats_collection
             .set_user_status(&user_panicked_msg.user_info.id, UserStatus::Panicked);
 
+        self.user_stats_collection
+            .add_panic(&user_panicked_msg.user_info.id, &user_panicked_msg.message);
+
         self.prometheus_exporter_arc.remove_user(UserCountLabel {
             user_name: user_panicked_msg.user_info.name,
         });
 
         self.prometheus_exporter_arc.add_panic(UserLabel {
-            user_id: user_panicked_msg.user_info.id,
+            user_id: Some(user_panicked_msg.user_info.id),
             user_name: user_panicked_msg.user_info.name,
         });
     }
 
+    #[inline]
+    fn on_user_restarted_message(
+        &mut self,
+        user_restarted_msg: crate::messages::UserRestartedMessage,
+    ) {
+        tracing::info!(
+            user_name = &user_restarted_msg.user_info.name,
+            user_id = &user_restarted_msg.user_info.id,
+            "User restarted",
+        );
+
+        self.user_stats_collection
+            .set_user_status(&user_restarted_msg.user_info.id, UserStatus::Spawned);
+
+        self.user_stats_collection
+            .increment_total_restarts(&user_restarted_msg.user_info.id);
+
+        self.prometheus_exporter_arc.add_user(UserCountLabel {
+            user_name: user_restarted_msg.user_info.name,
+        });
+
+        self.prometheus_exporter_arc.add_restart(UserLabel {
+            user_id: Some(user_restarted_msg.user_info.id),
+            user_name: user_restarted_msg.user_info.name,
+        });
+    }
+
     #[inline]
     fn on_user_unknown_status_message(
         &mut self,
@@ -435,6 +892,9 @@ impl Test {
     ) {
         *self.start_timestamp_arc_rwlock.write().await = Instant::now();
 
+        self.prometheus_exporter_arc
+            .set_target_users(total_spawnable_user_count);
+
         let spawn_coordinator_handle = spawn_coordinator.run();
         let server_handle = self.strat_server();
         let timer_handle = self.start_timer();
@@ -452,19 +912,58 @@ impl Test {
         )
         .await;
 
+        if let Some(statsd_sender) = self.statsd_sender.take() {
+            statsd_sender.close().await;
+        }
+        if let Some(raw_log_writer) = self.raw_log_writer.take() {
+            raw_log_writer.close().await;
+        }
+        #[cfg(feature = "otel")]
+        if let Some(otel_exporter) = self.otel_exporter.take() {
+            otel_exporter.shutdown().await;
+        }
+
         let elapsed_time =
             Test::calculate_elapsed_time(&*self.start_timestamp_arc_rwlock.read().await);
 
+        self.prometheus_exporter_arc.set_elapsed_time(&elapsed_time);
+
+        self.prometheus_exporter_arc
+            .set_test_state(TestState::Stopped);
+
         self.update_summary_and_write_to_file(&elapsed_time).await;
 
+        self.log_check_thresholds().await;
+
         let mut all_results_gaurd = self.all_results_arc_rwlock.write().await;
 
         all_results_gaurd.calculate_on_update_interval(&elapsed_time);
 
         self.writers
-            .write_on_update_interval(&*all_results_gaurd, &*self.prometheus_exporter_arc)
+            .write_on_update_interval(
+                &*all_results_gaurd,
+                &*self.all_errors_arc_rwlock.read().await,
+                &*self.all_failures_arc_rwlock.read().await,
+                &*self.prometheus_exporter_arc,
+                &elapsed_time,
+            )
             .await;
 
+        self.write_html_report(&elapsed_time, &all_results_gaurd)
+            .await;
+
+        let line_protocols = self.writers.line_protocols(&all_results_gaurd);
+
+        drop(all_results_gaurd);
+
+        self.log_endpoint_thresholds().await;
+
+        self.writers
+            .send_on_update_interval(&self.prometheus_exporter_arc, line_protocols)
+            .await;
+
+        self.write_junit_report(&elapsed_time).await;
+
         tracing::info!("Test terminated");
     }
 
@@ -530,22 +1029,152 @@ impl Test {
         }
     }
 
-    fn get_summary_string_from_extension(
+    /// Returns true if all check thresholds of the config are met.
+    /// Can be used after the test to set the exit code.
+    pub async fn check_thresholds_passed(&self) -> bool {
+        self.all_checks_arc_rwlock
+            .read()
+            .await
+            .evaluate_thresholds(&self.test_config.check_thresholds)
+            .iter()
+            .all(|result| result.passed)
+    }
+
+    async fn log_check_thresholds(&self) {
+        let results = self
+            .all_checks_arc_rwlock
+            .read()
+            .await
+            .evaluate_thresholds(&self.test_config.check_thresholds);
+        for result in results {
+            if result.passed {
+                tracing::info!(check_name = %result.check_name, min_pass_rate = result.min_pass_rate, pass_rate = ?result.pass_rate, "Check threshold passed");
+            } else {
+                tracing::error!(check_name = %result.check_name, min_pass_rate = result.min_pass_rate, pass_rate = ?result.pass_rate, "Check threshold failed");
+            }
+        }
+    }
+
+    // the first stop condition that is met
+    fn evaluate_stop_conditions(
+        stop_conditions: &[StopCondition],
+        all_results: &AllResults,
+    ) -> Option<StopConditionMet> {
+        stop_conditions.iter().find_map(|stop_condition| {
+            stop_condition
+                .evaluate(all_results)
+                .map(|observed| StopConditionMet {
+                    stop_condition: stop_condition.clone(),
+                    observed,
+                })
+        })
+    }
+
+    /// Returns true if all endpoint thresholds of the config are met.
+    /// Can be used after the test to set the exit code.
+    pub async fn endpoint_thresholds_passed(&self) -> bool {
+        thresholds::evaluate_endpoint_thresholds(
+            &*self.all_results_arc_rwlock.read().await,
+            &self.test_config.endpoint_thresholds,
+        )
+        .iter()
+        .all(|result| result.passed)
+    }
+
+    /// Returns the stop condition that stopped the test, if any.
+    pub async fn stop_condition_met(&self) -> Option<StopConditionMet> {
+        self.stop_condition_met_arc_rwlock.read().await.clone()
+    }
+
+    async fn log_endpoint_thresholds(&self) {
+        let results = thresholds::evaluate_endpoint_thresholds(
+            &*self.all_results_arc_rwlock.read().await,
+            &self.test_config.endpoint_thresholds,
+        );
+        for result in results {
+            if result.passed {
+                tracing::info!(endpoint_threshold = %result.endpoint_threshold, observed = ?result.observed, "Endpoint threshold passed");
+            } else {
+                tracing::error!(endpoint_threshold = %result.endpoint_threshold, observed = ?result.observed, "Endpoint threshold failed");
+            }
+        }
+    }
+
+    async fn get_summary_string_from_extension(
         &self,
         extension: SupportedExtension,
-    ) -> Result<String, user::UserStatsCollectionError> {
+    ) -> Result<String, SummaryError> {
+        let summary = Summary::new(
+            &self.user_stats_collection,
+            &*self.all_panics_arc_rwlock.read().await,
+            &*self.all_errors_arc_rwlock.read().await,
+            &*self.all_failures_arc_rwlock.read().await,
+            &*self.all_checks_arc_rwlock.read().await,
+            &*self.all_metrics_arc_rwlock.read().await,
+            &*self.all_task_stats_arc_rwlock.read().await,
+        )
+        .with_check_thresholds(
+            self.all_checks_arc_rwlock
+                .read()
+                .await
+                .evaluate_thresholds(&self.test_config.check_thresholds),
+        );
         match extension {
-            SupportedExtension::Yaml => self.user_stats_collection.yaml_string(),
-            SupportedExtension::Json => self.user_stats_collection.json_string(),
+            SupportedExtension::Yaml => summary.yaml_string(),
+            SupportedExtension::Json => summary.json_string(),
         }
     }
 
-    fn get_summary_string_from_path(
-        &self,
-        path: &Path,
-    ) -> Result<String, user::UserStatsCollectionError> {
+    async fn get_summary_string_from_path(&self, path: &Path) -> Result<String, SummaryError> {
         let extension = Test::get_supported_summary_extension(path);
-        self.get_summary_string_from_extension(extension)
+        self.get_summary_string_from_extension(extension).await
+    }
+
+    async fn write_html_report(&self, elapsed_time: &Duration, all_results: &AllResults) {
+        if let Some(html_report_writer) = self.writers.get_html_report_writer() {
+            let mut results_timeline_gaurd = self.results_timeline_arc_rwlock.write().await;
+            results_timeline_gaurd.record(
+                elapsed_time,
+                *self.total_users_spawned_arc_rwlock.read().await,
+                all_results,
+            );
+
+            let html = HtmlReport {
+                test_config: &self.test_config,
+                elapsed_time,
+                all_results,
+                all_errors: &*self.all_errors_arc_rwlock.read().await,
+                all_failures: &*self.all_failures_arc_rwlock.read().await,
+                user_stats_collection: &self.user_stats_collection,
+                results_timeline: &results_timeline_gaurd,
+            }
+            .html_string();
+
+            if let Err(error) = html_report_writer.write_all(html.as_bytes()).await {
+                tracing::error!(%error, "Error writing html report to file");
+            }
+        }
+    }
+
+    async fn write_junit_report(&self, elapsed_time: &Duration) {
+        if let Some(junit_report_writer) = self.writers.get_junit_report_writer() {
+            let endpoint_threshold_results = thresholds::evaluate_endpoint_thresholds(
+                &*self.all_results_arc_rwlock.read().await,
+                &self.test_config.endpoint_thresholds,
+            );
+            let xml = junit_report::junit_xml_string(
+                &*self.all_checks_arc_rwlock.read().await,
+                &self.test_config.check_thresholds,
+                &endpoint_threshold_results,
+                &self.test_config.stop_conditions,
+                self.stop_condition_met_arc_rwlock.read().await.as_ref(),
+                elapsed_time,
+                self.test_config.precision,
+            );
+            if let Err(error) = junit_report_writer.write_all(xml.as_bytes()).await {
+                tracing::error!(%error, "Error writing junit report to file");
+            }
+        }
     }
 
     async fn update_summary_and_write_to_file(&mut self, elapsed_time: &Duration) {
@@ -553,7 +1182,19 @@ impl Test {
             self.user_stats_collection
                 .calculate_on_update_interval(&elapsed_time);
 
-            let summary_string = self.get_summary_string_from_path(summary_writer.get_path());
+            self.all_task_stats_arc_rwlock
+                .write()
+                .await
+                .calculate_on_update_interval(elapsed_time);
+
+            self.all_metrics_arc_rwlock
+                .write()
+                .await
+                .calculate_on_update_interval(elapsed_time);
+
+            let summary_string = self
+                .get_summary_string_from_path(summary_writer.get_path())
+                .await;
 
             match summary_string {
                 Ok(summary_string) => {
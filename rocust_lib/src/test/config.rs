@@ -1,11 +1,518 @@
-use crate::fs::reader::{CreateError, ReadError, Reader};
+use crate::{
+    checks::{CheckThreshold, CheckThresholdParseError},
+    custom_metrics::DEFAULT_TREND_SCALE,
+    errors::DEFAULT_MAX_DISTINCT_ERRORS,
+    fs::reader::{CreateError, ReadError, Reader},
+    thresholds::{EndpointThreshold, StopCondition, ThresholdParseError},
+    utils,
+};
 use clap::Parser;
+use prometheus_client::metrics::histogram::exponential_buckets;
+use regex::{Error as RegexError, Regex};
 use serde::{Deserialize, Serialize};
 use serde_json::{self, Error as SerdeJsonError};
 use serde_yaml::{self, Error as SerdeYamlError};
-use std::net::{AddrParseError, SocketAddr};
+use std::{
+    net::{AddrParseError, SocketAddr},
+    num::{ParseFloatError, ParseIntError},
+    path::Path,
+    str::FromStr,
+    time::Duration,
+};
 use thiserror::Error as ThisError;
 
+/// What to do with a user whose `User::new` or `User::on_start` returned an error.
+///
+/// The user is recreated up to `retries` times. The backoff between attempts starts at `backoff_in_millis` and is doubled after every attempt.
+/// If all attempts fail, the user is skipped and the test continues without it, unless `abort_after` users have failed to start. Then the test is stopped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StartFailurePolicy {
+    pub retries: u32,
+    pub backoff_in_millis: u64,
+    pub abort_after: Option<u64>,
+}
+
+impl Default for StartFailurePolicy {
+    fn default() -> Self {
+        Self::skip()
+    }
+}
+
+impl StartFailurePolicy {
+    pub fn skip() -> Self {
+        Self {
+            retries: 0,
+            backoff_in_millis: 0,
+            abort_after: None,
+        }
+    }
+
+    pub fn retry(retries: u32, backoff_in_millis: u64) -> Self {
+        Self {
+            retries,
+            backoff_in_millis,
+            abort_after: None,
+        }
+    }
+
+    pub fn abort_after(self, abort_after: u64) -> Self {
+        let abort_after = Some(abort_after);
+        Self {
+            abort_after,
+            ..self
+        }
+    }
+
+    pub(crate) fn get_backoff(&self, attempt: u32) -> Duration {
+        let factor = 2u64.saturating_pow(attempt);
+        Duration::from_millis(self.backoff_in_millis.saturating_mul(factor))
+    }
+}
+
+/// Upper bounds in seconds of the buckets of the prometheus response time histogram.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ResponseTimeBuckets {
+    Explicit(Vec<f64>),
+    /// `count` buckets, the first one is `start` and every following one is `factor` times the previous one.
+    /// Given as `{start},{factor},{count}` on the command line.
+    Exponential {
+        start: f64,
+        factor: f64,
+        count: u16,
+    },
+}
+
+impl Default for ResponseTimeBuckets {
+    // 5ms to ~82s, same as the task durations
+    fn default() -> Self {
+        Self::Exponential {
+            start: 0.005,
+            factor: 2.0,
+            count: 15,
+        }
+    }
+}
+
+impl ResponseTimeBuckets {
+    // sorted and deduplicated, prometheus expects increasing bounds
+    pub(crate) fn get_bounds(&self) -> Vec<f64> {
+        let mut bounds: Vec<f64> = match self {
+            ResponseTimeBuckets::Explicit(bounds) => bounds.clone(),
+            ResponseTimeBuckets::Exponential {
+                start,
+                factor,
+                count,
+            } => exponential_buckets(*start, *factor, *count).collect(),
+        };
+        bounds.retain(|bound| bound.is_finite());
+        bounds.sort_by(|a, b| a.total_cmp(b));
+        bounds.dedup();
+        bounds
+    }
+}
+
+#[derive(Debug, ThisError)]
+pub enum ResponseTimeBucketsParseError {
+    #[error("Expected {{start}},{{factor}},{{count}}")]
+    WrongFormat,
+    #[error("Invalid start or factor: {0}")]
+    InvalidFloat(#[from] ParseFloatError),
+    #[error("Invalid count: {0}")]
+    InvalidCount(#[from] ParseIntError),
+}
+
+impl FromStr for ResponseTimeBuckets {
+    type Err = ResponseTimeBucketsParseError;
+
+    // exponential buckets, explicit buckets are given one by one
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.split(',').map(str::trim).collect();
+        match parts.as_slice() {
+            [start, factor, count] => Ok(Self::Exponential {
+                start: start.parse()?,
+                factor: factor.parse()?,
+                count: count.parse()?,
+            }),
+            _ => Err(ResponseTimeBucketsParseError::WrongFormat),
+        }
+    }
+}
+
+/// Rewrites endpoint names that match `pattern` to `replacement`, e.g. `^/items/\d+$` to `/items/{id}`.
+/// The replacement may contain capture groups like `$1`. Given as `{pattern}=>{replacement}` on the command line.
+#[derive(Debug, Clone)]
+pub struct EndpointNameRewrite {
+    pub pattern: Regex,
+    pub replacement: String,
+}
+
+impl EndpointNameRewrite {
+    pub fn new(pattern: &str, replacement: &str) -> Result<Self, RegexError> {
+        Ok(Self {
+            pattern: Regex::new(pattern)?,
+            replacement: String::from(replacement),
+        })
+    }
+}
+
+#[derive(Debug, ThisError)]
+pub enum EndpointNameRewriteParseError {
+    #[error("Expected {{pattern}}=>{{replacement}}")]
+    MissingSeparator,
+    #[error("Invalid pattern: {0}")]
+    InvalidPattern(#[from] RegexError),
+}
+
+impl FromStr for EndpointNameRewrite {
+    type Err = EndpointNameRewriteParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (pattern, replacement) = s
+            .rsplit_once("=>")
+            .ok_or(EndpointNameRewriteParseError::MissingSeparator)?;
+        Ok(Self::new(pattern.trim(), replacement.trim())?)
+    }
+}
+
+/// Controls the label cardinality of the prometheus metrics, applied before a series is created.
+///
+/// Every user id creates its own series, use `drop_user_id` for tests with many users.
+/// Endpoint names are rewritten by the first matching rule. Once `max_endpoint_names` distinct endpoint names are exported,
+/// new endpoint names are exported as `other`. The results, the summary and the files are not affected.
+#[derive(Debug, Clone, Default)]
+pub struct PrometheusLabelPolicy {
+    pub drop_user_id: bool,
+    pub endpoint_name_rewrites: Vec<EndpointNameRewrite>,
+    pub max_endpoint_names: Option<usize>,
+}
+
+impl PrometheusLabelPolicy {
+    pub fn drop_user_id(self) -> Self {
+        Self {
+            drop_user_id: true,
+            ..self
+        }
+    }
+
+    pub fn endpoint_name_rewrite(self, endpoint_name_rewrite: EndpointNameRewrite) -> Self {
+        let mut endpoint_name_rewrites = self.endpoint_name_rewrites;
+        endpoint_name_rewrites.push(endpoint_name_rewrite);
+        Self {
+            endpoint_name_rewrites,
+            ..self
+        }
+    }
+
+    pub fn max_endpoint_names(self, max_endpoint_names: usize) -> Self {
+        let max_endpoint_names = Some(max_endpoint_names);
+        Self {
+            max_endpoint_names,
+            ..self
+        }
+    }
+
+    // the first matching rule wins
+    pub(crate) fn rewrite_endpoint_name(&self, endpoint_name: String) -> String {
+        for rewrite in &self.endpoint_name_rewrites {
+            if rewrite.pattern.is_match(&endpoint_name) {
+                return rewrite
+                    .pattern
+                    .replace(&endpoint_name, rewrite.replacement.as_str())
+                    .into_owned();
+            }
+        }
+        endpoint_name
+    }
+}
+
+pub const DEFAULT_PUSHGATEWAY_JOB: &str = "rocust";
+
+/// Pushes the prometheus metrics to a Pushgateway every update interval and once at the end of the test,
+/// for tests that end before prometheus scrapes them.
+///
+/// The metrics are pushed with `PUT` to `{url}/metrics/job/{job}/{label_name}/{label_value}...`, replacing the previous push of the same group.
+/// Only `http` urls are supported. Prometheus remote-write is not supported.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrometheusPushgateway {
+    pub url: String,
+    pub job: String,
+    pub grouping_labels: Vec<(String, String)>,
+}
+
+impl PrometheusPushgateway {
+    pub fn new(url: String) -> Self {
+        Self {
+            url,
+            job: String::from(DEFAULT_PUSHGATEWAY_JOB),
+            grouping_labels: Vec::new(),
+        }
+    }
+
+    pub fn job(self, job: String) -> Self {
+        Self { job, ..self }
+    }
+
+    // e.g. the instance or the branch, pushes with different grouping labels do not replace each other
+    pub fn grouping_label(self, name: String, value: String) -> Self {
+        let mut grouping_labels = self.grouping_labels;
+        grouping_labels.push((name, value));
+        Self {
+            grouping_labels,
+            ..self
+        }
+    }
+}
+
+#[derive(Debug, ThisError)]
+#[error("Expected {{name}}={{value}}")]
+pub struct LabelParseError;
+
+// pushgateway grouping labels, influx and graphite tags
+fn parse_label(s: &str) -> Result<(String, String), LabelParseError> {
+    let (name, value) = s.split_once('=').ok_or(LabelParseError)?;
+    Ok((String::from(name), String::from(value)))
+}
+
+pub const DEFAULT_INFLUX_MEASUREMENT: &str = "rocust";
+
+/// Writes the results of every endpoint and the aggregated results as InfluxDB line protocol every update interval and once at the end of the test.
+///
+/// The lines are appended to `file` and/or sent with `POST` to `url`, e.g. `http://localhost:8086/write?db=rocust` or `http://localhost:8086/api/v2/write?org=my-org&bucket=rocust`.
+/// If `token` is set, it is sent as `Authorization: Token {token}`. Every line is tagged with the endpoint type and name and the given `tags`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InfluxOutput {
+    pub file: Option<String>,
+    pub url: Option<String>,
+    pub token: Option<String>,
+    pub measurement: String,
+    pub tags: Vec<(String, String)>,
+}
+
+impl Default for InfluxOutput {
+    fn default() -> Self {
+        Self {
+            file: None,
+            url: None,
+            token: None,
+            measurement: String::from(DEFAULT_INFLUX_MEASUREMENT),
+            tags: Vec::new(),
+        }
+    }
+}
+
+impl InfluxOutput {
+    pub fn file(self, file: String) -> Self {
+        let file = Some(file);
+        Self { file, ..self }
+    }
+
+    pub fn url(self, url: String) -> Self {
+        let url = Some(url);
+        Self { url, ..self }
+    }
+
+    pub fn token(self, token: String) -> Self {
+        let token = Some(token);
+        Self { token, ..self }
+    }
+
+    pub fn measurement(self, measurement: String) -> Self {
+        Self {
+            measurement,
+            ..self
+        }
+    }
+
+    // e.g. the test name or the run id
+    pub fn tag(self, name: String, value: String) -> Self {
+        let mut tags = self.tags;
+        tags.push((name, value));
+        Self { tags, ..self }
+    }
+}
+
+pub const DEFAULT_GRAPHITE_PREFIX: &str = "rocust";
+
+/// Sends the results of every endpoint and the aggregated results as Graphite plaintext to `address` over TCP every update interval and once at the end of the test.
+///
+/// Every result is sent as a tagged series `{prefix}.{field};type={type};name={name}` with the given `tags`, which needs Graphite 1.1 or later.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GraphiteOutput {
+    pub address: String,
+    pub prefix: String,
+    pub tags: Vec<(String, String)>,
+}
+
+impl GraphiteOutput {
+    // host:port, e.g. localhost:2003
+    pub fn new(address: String) -> Self {
+        Self {
+            address,
+            prefix: String::from(DEFAULT_GRAPHITE_PREFIX),
+            tags: Vec::new(),
+        }
+    }
+
+    pub fn prefix(self, prefix: String) -> Self {
+        Self { prefix, ..self }
+    }
+
+    pub fn tag(self, name: String, value: String) -> Self {
+        let mut tags = self.tags;
+        tags.push((name, value));
+        Self { tags, ..self }
+    }
+}
+
+pub const DEFAULT_STATSD_PREFIX: &str = "rocust";
+
+/// Streams every result as StatsD packets over UDP to `address`, batched into datagrams.
+///
+/// Successes are sent as `{prefix}.response_time` timings and `{prefix}.requests` counters, failures also as `{prefix}.failures` counters
+/// (and `{prefix}.failed_response_time` timings if reported with a response time) and errors as `{prefix}.errors` counters.
+/// Only `sample_rate` (between 0 and 1) of the results are sent, the rate is included in the packets so the counters can be scaled by the server.
+/// With `dogstatsd_tags` the packets are tagged with the user name, the endpoint type and name, the task name and the given `tags`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatsdOutput {
+    pub address: String,
+    pub prefix: String,
+    pub sample_rate: f64,
+    pub dogstatsd_tags: bool,
+    pub tags: Vec<(String, String)>,
+}
+
+impl StatsdOutput {
+    // host:port, e.g. localhost:8125
+    pub fn new(address: String) -> Self {
+        Self {
+            address,
+            prefix: String::from(DEFAULT_STATSD_PREFIX),
+            sample_rate: 1.0,
+            dogstatsd_tags: true,
+            tags: Vec::new(),
+        }
+    }
+
+    pub fn prefix(self, prefix: String) -> Self {
+        Self { prefix, ..self }
+    }
+
+    pub fn sample_rate(self, sample_rate: f64) -> Self {
+        Self {
+            sample_rate,
+            ..self
+        }
+    }
+
+    // for plain StatsD servers that do not support tags
+    pub fn dogstatsd_tags(self, dogstatsd_tags: bool) -> Self {
+        Self {
+            dogstatsd_tags,
+            ..self
+        }
+    }
+
+    pub fn tag(self, name: String, value: String) -> Self {
+        let mut tags = self.tags;
+        tags.push((name, value));
+        Self { tags, ..self }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RawLogFormat {
+    Jsonl,
+    Csv,
+}
+
+/// Writes every result as a line to `file`, with the timestamp, the user id and name, the task, the endpoint type and name,
+/// the outcome (success, failure or error), the response time and the error or failure reason.
+///
+/// The format is given by the extension of `file`, `.csv` for CSV and JSON Lines otherwise.
+/// If `max_file_size` (in bytes) is exceeded, the file is rotated to `{name}.1.{extension}`, `{name}.2.{extension}`, ... and a new one is started.
+/// Only `sample_rate` (between 0 and 1) of the results are written.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RawLog {
+    pub file: String,
+    pub format: RawLogFormat,
+    pub max_file_size: Option<u64>,
+    pub sample_rate: f64,
+}
+
+impl RawLog {
+    pub fn new(file: String) -> Self {
+        let format = match utils::get_extension_from_filename(Path::new(&file)) {
+            Some("csv") => RawLogFormat::Csv,
+            _ => RawLogFormat::Jsonl,
+        };
+        Self {
+            file,
+            format,
+            max_file_size: None,
+            sample_rate: 1.0,
+        }
+    }
+
+    pub fn format(self, format: RawLogFormat) -> Self {
+        Self { format, ..self }
+    }
+
+    pub fn max_file_size(self, max_file_size: u64) -> Self {
+        let max_file_size = Some(max_file_size);
+        Self {
+            max_file_size,
+            ..self
+        }
+    }
+
+    pub fn sample_rate(self, sample_rate: f64) -> Self {
+        Self {
+            sample_rate,
+            ..self
+        }
+    }
+}
+
+#[cfg(feature = "otel")]
+pub const DEFAULT_OTEL_SERVICE_NAME: &str = "rocust";
+
+/// Exports the results via OTLP over HTTP to `endpoint`, e.g. `http://localhost:4318`, every update interval and once at the end of the test.
+///
+/// Requests, failures and errors are exported as counters and the response times as a histogram.
+/// With `request_spans` a span is created for every reported request and transaction, see `Context::trace_context_headers` to link them to the spans of the server.
+#[cfg(feature = "otel")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OtelOutput {
+    pub endpoint: String,
+    pub service_name: String,
+    pub request_spans: bool,
+}
+
+#[cfg(feature = "otel")]
+impl OtelOutput {
+    pub fn new(endpoint: String) -> Self {
+        Self {
+            endpoint,
+            service_name: String::from(DEFAULT_OTEL_SERVICE_NAME),
+            request_spans: false,
+        }
+    }
+
+    pub fn service_name(self, service_name: String) -> Self {
+        Self {
+            service_name,
+            ..self
+        }
+    }
+
+    pub fn request_spans(self, request_spans: bool) -> Self {
+        Self {
+            request_spans,
+            ..self
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct TestConfig {
     pub user_count: u64,
@@ -17,9 +524,33 @@ pub struct TestConfig {
     pub current_results_file: Option<String>,
     pub results_history_file: Option<String>,
     pub summary_file: Option<String>,
+    pub html_report_file: Option<String>,
+    pub junit_report_file: Option<String>,
+    pub errors_file: Option<String>,
+    pub failures_file: Option<String>,
     pub prometheus_current_metrics_file: Option<String>,
     pub prometheus_metrics_history_folder: Option<String>,
     pub server_address: Option<SocketAddr>,
+    pub start_failure_policy: StartFailurePolicy,
+    pub capture_panic_backtraces: bool,
+    pub results_per_task: bool,
+    pub task_timeout_in_millis: Option<u64>,
+    pub base_url: Option<String>,
+    pub max_distinct_errors: usize,
+    pub check_thresholds: Vec<CheckThreshold>,
+    pub endpoint_thresholds: Vec<EndpointThreshold>,
+    pub stop_conditions: Vec<StopCondition>,
+    pub trend_scale: f64,
+    pub response_time_buckets: ResponseTimeBuckets,
+    pub response_time_gauge: bool,
+    pub prometheus_label_policy: PrometheusLabelPolicy,
+    pub prometheus_pushgateway: Option<PrometheusPushgateway>,
+    pub influx_output: Option<InfluxOutput>,
+    pub graphite_output: Option<GraphiteOutput>,
+    pub statsd_output: Option<StatsdOutput>,
+    pub raw_log: Option<RawLog>,
+    #[cfg(feature = "otel")]
+    pub otel_output: Option<OtelOutput>,
     pub additional_args: Vec<String>,
 }
 
@@ -35,9 +566,33 @@ impl Default for TestConfig {
             current_results_file: None,
             results_history_file: None,
             summary_file: None,
+            html_report_file: None,
+            junit_report_file: None,
+            errors_file: None,
+            failures_file: None,
             prometheus_current_metrics_file: None,
             prometheus_metrics_history_folder: None,
             server_address: None,
+            start_failure_policy: StartFailurePolicy::default(),
+            capture_panic_backtraces: false,
+            results_per_task: false,
+            task_timeout_in_millis: None,
+            base_url: None,
+            max_distinct_errors: DEFAULT_MAX_DISTINCT_ERRORS,
+            check_thresholds: Vec::new(),
+            endpoint_thresholds: Vec::new(),
+            stop_conditions: Vec::new(),
+            trend_scale: DEFAULT_TREND_SCALE,
+            response_time_buckets: ResponseTimeBuckets::default(),
+            response_time_gauge: false,
+            prometheus_label_policy: PrometheusLabelPolicy::default(),
+            prometheus_pushgateway: None,
+            influx_output: None,
+            graphite_output: None,
+            statsd_output: None,
+            raw_log: None,
+            #[cfg(feature = "otel")]
+            otel_output: None,
             additional_args: Vec::new(),
         }
     }
@@ -103,6 +658,22 @@ impl TestConfig {
         }
     }
 
+    pub fn html_report_file(self, html_report_file: String) -> Self {
+        let html_report_file = Some(html_report_file);
+        Self {
+            html_report_file,
+            ..self
+        }
+    }
+
+    pub fn junit_report_file(self, junit_report_file: String) -> Self {
+        let junit_report_file = Some(junit_report_file);
+        Self {
+            junit_report_file,
+            ..self
+        }
+    }
+
     pub fn prometheus_current_metrics_file(self, prometheus_current_metrics_file: String) -> Self {
         let prometheus_current_metrics_file = Some(prometheus_current_metrics_file);
         Self {
@@ -130,6 +701,170 @@ impl TestConfig {
         }
     }
 
+    pub fn start_failure_policy(self, start_failure_policy: StartFailurePolicy) -> Self {
+        Self {
+            start_failure_policy,
+            ..self
+        }
+    }
+
+    pub fn capture_panic_backtraces(self, capture_panic_backtraces: bool) -> Self {
+        Self {
+            capture_panic_backtraces,
+            ..self
+        }
+    }
+
+    pub fn results_per_task(self, results_per_task: bool) -> Self {
+        Self {
+            results_per_task,
+            ..self
+        }
+    }
+
+    pub fn task_timeout_in_millis(self, task_timeout_in_millis: u64) -> Self {
+        Self {
+            task_timeout_in_millis: Some(task_timeout_in_millis),
+            ..self
+        }
+    }
+
+    pub fn base_url(self, base_url: String) -> Self {
+        Self {
+            base_url: Some(base_url),
+            ..self
+        }
+    }
+
+    pub fn errors_file(self, errors_file: String) -> Self {
+        Self {
+            errors_file: Some(errors_file),
+            ..self
+        }
+    }
+
+    pub fn failures_file(self, failures_file: String) -> Self {
+        Self {
+            failures_file: Some(failures_file),
+            ..self
+        }
+    }
+
+    pub fn max_distinct_errors(self, max_distinct_errors: usize) -> Self {
+        Self {
+            max_distinct_errors,
+            ..self
+        }
+    }
+
+    pub fn check_threshold(self, check_name: String, min_pass_rate: f64) -> Self {
+        let mut check_thresholds = self.check_thresholds;
+        check_thresholds.push(CheckThreshold {
+            check_name,
+            min_pass_rate,
+        });
+        Self {
+            check_thresholds,
+            ..self
+        }
+    }
+
+    pub fn endpoint_threshold(self, endpoint_threshold: EndpointThreshold) -> Self {
+        let mut endpoint_thresholds = self.endpoint_thresholds;
+        endpoint_thresholds.push(endpoint_threshold);
+        Self {
+            endpoint_thresholds,
+            ..self
+        }
+    }
+
+    /// The test is stopped once any of the stop conditions is met.
+    pub fn stop_condition(self, stop_condition: StopCondition) -> Self {
+        let mut stop_conditions = self.stop_conditions;
+        stop_conditions.push(stop_condition);
+        Self {
+            stop_conditions,
+            ..self
+        }
+    }
+
+    /// Trend values are recorded in the percentiles as integers in units of 1 / trend_scale.
+    /// The default of 1000000 keeps 6 decimal places, use a bigger scale for smaller values.
+    pub fn trend_scale(self, trend_scale: f64) -> Self {
+        Self {
+            trend_scale,
+            ..self
+        }
+    }
+
+    pub fn response_time_buckets(self, response_time_buckets: ResponseTimeBuckets) -> Self {
+        Self {
+            response_time_buckets,
+            ..self
+        }
+    }
+
+    /// Also export the last response time as a gauge, like before the histogram was added.
+    pub fn response_time_gauge(self, response_time_gauge: bool) -> Self {
+        Self {
+            response_time_gauge,
+            ..self
+        }
+    }
+
+    pub fn prometheus_label_policy(self, prometheus_label_policy: PrometheusLabelPolicy) -> Self {
+        Self {
+            prometheus_label_policy,
+            ..self
+        }
+    }
+
+    pub fn prometheus_pushgateway(self, prometheus_pushgateway: PrometheusPushgateway) -> Self {
+        let prometheus_pushgateway = Some(prometheus_pushgateway);
+        Self {
+            prometheus_pushgateway,
+            ..self
+        }
+    }
+
+    pub fn influx_output(self, influx_output: InfluxOutput) -> Self {
+        let influx_output = Some(influx_output);
+        Self {
+            influx_output,
+            ..self
+        }
+    }
+
+    pub fn graphite_output(self, graphite_output: GraphiteOutput) -> Self {
+        let graphite_output = Some(graphite_output);
+        Self {
+            graphite_output,
+            ..self
+        }
+    }
+
+    pub fn statsd_output(self, statsd_output: StatsdOutput) -> Self {
+        let statsd_output = Some(statsd_output);
+        Self {
+            statsd_output,
+            ..self
+        }
+    }
+
+    pub fn raw_log(self, raw_log: RawLog) -> Self {
+        let raw_log = Some(raw_log);
+        Self { raw_log, ..self }
+    }
+
+    #[cfg(feature = "otel")]
+    pub fn otel_output(self, otel_output: OtelOutput) -> Self {
+        let otel_output = Some(otel_output);
+        Self {
+            otel_output,
+            ..self
+        }
+    }
+
     pub fn additional_args(self, additional_args: Vec<String>) -> Self {
         Self {
             additional_args,
@@ -200,6 +935,124 @@ impl TryFrom<ExternalTestConfig> for TestConfig {
             } else {
                 None
             };
+        let start_failure_policy = StartFailurePolicy {
+            retries: external_test_config.start_retries.unwrap_or(0),
+            backoff_in_millis: external_test_config
+                .start_retry_backoff_in_millis
+                .unwrap_or(0),
+            abort_after: external_test_config.abort_after_start_failures,
+        };
+        let check_thresholds = external_test_config
+            .check_threshold
+            .iter()
+            .map(|check_threshold| check_threshold.parse())
+            .collect::<Result<Vec<CheckThreshold>, CheckThresholdParseError>>()?;
+        let endpoint_thresholds = external_test_config
+            .endpoint_threshold
+            .iter()
+            .map(|endpoint_threshold| endpoint_threshold.parse())
+            .collect::<Result<Vec<EndpointThreshold>, ThresholdParseError>>()?;
+        let stop_conditions = external_test_config
+            .stop_condition
+            .iter()
+            .map(|stop_condition| stop_condition.parse())
+            .collect::<Result<Vec<StopCondition>, ThresholdParseError>>()?;
+        let response_time_buckets = if !external_test_config.response_time_buckets.is_empty() {
+            ResponseTimeBuckets::Explicit(external_test_config.response_time_buckets)
+        } else if let Some(exponential_response_time_buckets) =
+            external_test_config.exponential_response_time_buckets
+        {
+            exponential_response_time_buckets.parse()?
+        } else {
+            ResponseTimeBuckets::default()
+        };
+        let prometheus_label_policy = PrometheusLabelPolicy {
+            drop_user_id: external_test_config.prometheus_drop_user_id,
+            endpoint_name_rewrites: external_test_config
+                .prometheus_endpoint_name_rewrite
+                .iter()
+                .map(|endpoint_name_rewrite| endpoint_name_rewrite.parse())
+                .collect::<Result<Vec<EndpointNameRewrite>, EndpointNameRewriteParseError>>()?,
+            max_endpoint_names: external_test_config.prometheus_max_endpoint_names,
+        };
+        let prometheus_pushgateway = match external_test_config.prometheus_pushgateway_url {
+            Some(url) => {
+                let mut prometheus_pushgateway = PrometheusPushgateway::new(url);
+                if let Some(job) = external_test_config.prometheus_pushgateway_job {
+                    prometheus_pushgateway = prometheus_pushgateway.job(job);
+                }
+                for grouping_label in &external_test_config.prometheus_pushgateway_grouping_label {
+                    let (name, value) = parse_label(grouping_label)?;
+                    prometheus_pushgateway = prometheus_pushgateway.grouping_label(name, value);
+                }
+                Some(prometheus_pushgateway)
+            }
+            None => None,
+        };
+        let influx_output = if external_test_config.influx_file.is_some()
+            || external_test_config.influx_url.is_some()
+        {
+            Some(InfluxOutput {
+                file: external_test_config.influx_file,
+                url: external_test_config.influx_url,
+                token: external_test_config.influx_token,
+                measurement: external_test_config
+                    .influx_measurement
+                    .unwrap_or_else(|| String::from(DEFAULT_INFLUX_MEASUREMENT)),
+                tags: external_test_config
+                    .influx_tag
+                    .iter()
+                    .map(|tag| parse_label(tag))
+                    .collect::<Result<Vec<(String, String)>, LabelParseError>>()?,
+            })
+        } else {
+            None
+        };
+        let graphite_output = match external_test_config.graphite_address {
+            Some(address) => Some(GraphiteOutput {
+                address,
+                prefix: external_test_config
+                    .graphite_prefix
+                    .unwrap_or_else(|| String::from(DEFAULT_GRAPHITE_PREFIX)),
+                tags: external_test_config
+                    .graphite_tag
+                    .iter()
+                    .map(|tag| parse_label(tag))
+                    .collect::<Result<Vec<(String, String)>, LabelParseError>>()?,
+            }),
+            None => None,
+        };
+        let statsd_output = match external_test_config.statsd_address {
+            Some(address) => Some(StatsdOutput {
+                address,
+                prefix: external_test_config
+                    .statsd_prefix
+                    .unwrap_or_else(|| String::from(DEFAULT_STATSD_PREFIX)),
+                sample_rate: external_test_config.statsd_sample_rate.unwrap_or(1.0),
+                dogstatsd_tags: !external_test_config.statsd_no_tags,
+                tags: external_test_config
+                    .statsd_tag
+                    .iter()
+                    .map(|tag| parse_label(tag))
+                    .collect::<Result<Vec<(String, String)>, LabelParseError>>()?,
+            }),
+            None => None,
+        };
+        let raw_log = external_test_config.raw_log_file.map(|file| RawLog {
+            max_file_size: external_test_config.raw_log_max_file_size,
+            sample_rate: external_test_config.raw_log_sample_rate.unwrap_or(1.0),
+            ..RawLog::new(file)
+        });
+        #[cfg(feature = "otel")]
+        let otel_output = external_test_config
+            .otel_endpoint
+            .map(|endpoint| OtelOutput {
+                endpoint,
+                service_name: external_test_config
+                    .otel_service_name
+                    .unwrap_or_else(|| String::from(DEFAULT_OTEL_SERVICE_NAME)),
+                request_spans: external_test_config.otel_request_spans,
+            });
         Ok(Self {
             user_count: external_test_config.user_count,
             users_per_sec: external_test_config.users_per_sec,
@@ -210,10 +1063,38 @@ impl TryFrom<ExternalTestConfig> for TestConfig {
             current_results_file: external_test_config.current_results_file,
             results_history_file: external_test_config.results_history_file,
             summary_file: external_test_config.summary_file,
+            html_report_file: external_test_config.html_report_file,
+            junit_report_file: external_test_config.junit_report_file,
+            errors_file: external_test_config.errors_file,
+            failures_file: external_test_config.failures_file,
             prometheus_current_metrics_file: external_test_config.prometheus_current_metrics_file,
             prometheus_metrics_history_folder: external_test_config
                 .prometheus_metrics_history_folder,
             server_address,
+            start_failure_policy,
+            capture_panic_backtraces: external_test_config.capture_panic_backtraces,
+            results_per_task: external_test_config.results_per_task,
+            task_timeout_in_millis: external_test_config.task_timeout_in_millis,
+            base_url: external_test_config.base_url,
+            max_distinct_errors: external_test_config
+                .max_distinct_errors
+                .unwrap_or(DEFAULT_MAX_DISTINCT_ERRORS),
+            check_thresholds,
+            endpoint_thresholds,
+            stop_conditions,
+            trend_scale: external_test_config
+                .trend_scale
+                .unwrap_or(DEFAULT_TREND_SCALE),
+            response_time_buckets,
+            response_time_gauge: external_test_config.response_time_gauge,
+            prometheus_label_policy,
+            prometheus_pushgateway,
+            influx_output,
+            graphite_output,
+            statsd_output,
+            raw_log,
+            #[cfg(feature = "otel")]
+            otel_output,
             additional_args: external_test_config.additional_arg,
         })
     }
@@ -282,14 +1163,31 @@ struct ExternalTestConfig {
     #[arg(long, default_value = None)]
     current_results_file: Option<String>,
 
-    /// Path to the file where the results history should be written to. If not set, the results will not be written to a file. Extension .csv
+    /// Path to the file where the results history should be written to. If not set, the results will not be written to a file. Every update interval a row per endpoint and one for the aggregated results is appended, with the stats of the interval and of the whole test. Extension .csv or .jsonl
     #[arg(long, default_value = None)]
     results_history_file: Option<String>,
 
     /// Path to the file where the summary should be written to. If not set, the summary will not be written to a file. Extension .json or .yaml/.yml
+    /// The summary is an object with the sections user_stats, panics, errors, failures, checks, check_thresholds, metrics and task_stats.
     #[arg(long, default_value = None)]
     summary_file: Option<String>,
 
+    /// Path to the file where a static HTML report should be written to at the end of the test. If not set, no report will be written.
+    #[arg(long, default_value = None)]
+    html_report_file: Option<String>,
+
+    /// Path to the file where a JUnit XML report of the endpoint thresholds, check thresholds, checks and stop conditions should be written to at the end of the test. If not set, no report will be written.
+    #[arg(long, default_value = None)]
+    junit_report_file: Option<String>,
+
+    /// Path to the file where the errors, grouped by endpoint and message, should be written to. If not set, the errors will not be written to a file. Extension .csv
+    #[arg(long, default_value = None)]
+    errors_file: Option<String>,
+
+    /// Path to the file where the failures with a reason, grouped by endpoint and reason, should be written to. If not set, the failures will not be written to a file. Extension .csv
+    #[arg(long, default_value = None)]
+    failures_file: Option<String>,
+
     /// Path to the file where the current prometheus metrics should be written to. If not set, the metrics will not be written to a file.
     #[arg(long, default_value = None)]
     prometheus_current_metrics_file: Option<String>,
@@ -302,19 +1200,206 @@ struct ExternalTestConfig {
     #[arg(long, default_value = None)]
     server_address: Option<String>,
 
+    /// How many times a user that failed to start (User::new or User::on_start returned an error) should be recreated. If not set, the user is skipped.
+    #[arg(long, default_value = None)]
+    start_retries: Option<u32>,
+
+    /// Backoff in milliseconds before recreating a user that failed to start. Doubled after every attempt.
+    #[arg(long, default_value = None)]
+    start_retry_backoff_in_millis: Option<u64>,
+
+    /// Stop the test when this many users have failed to start. If not set, the test continues without them.
+    #[arg(long, default_value = None)]
+    abort_after_start_failures: Option<u64>,
+
+    /// Capture a backtrace when a user panics. The backtrace of the first occurrence of every panic message will be included in the summary.
+    #[arg(long)]
+    #[serde(default)]
+    capture_panic_backtraces: bool,
+
+    /// Break down the results by the task that produced them. The breakdown will be included in the summary and the results endpoint.
+    #[arg(long)]
+    #[serde(default)]
+    results_per_task: bool,
+
+    /// Timeout in milliseconds for a single task execution. Timed out tasks are cancelled and recorded as errors. Can be overridden per user type and per task. If not set, tasks will not time out.
+    #[arg(long, default_value = None)]
+    task_timeout_in_millis: Option<u64>,
+
+    /// Base url for relative urls of the RocustHttpClient (http feature), e.g. https://example.com. If not set, urls have to be absolute.
+    #[arg(long, default_value = None)]
+    base_url: Option<String>,
+
+    /// Maximum number of distinct error messages and failure reasons to keep. Once reached, new messages are counted as other errors or failures. Default 1000.
+    #[arg(long, default_value = None)]
+    max_distinct_errors: Option<usize>,
+
+    /// Minimum pass rate of a check, given as {check_name}={min_pass_rate}, e.g. "status is 200=0.99". Can be given multiple times. The results are included in the summary.
+    #[arg(long, action = clap::ArgAction::Append)]
+    #[serde(default)]
+    check_threshold: Vec<String>,
+
+    /// Maximum of a metric of the results of an endpoint, given as [{type} {name} ]{metric}<={max}, e.g. "GET /items p95_response_time<=0.5". Without an endpoint the aggregated results are used.
+    /// Metrics are avg_response_time, median_response_time, p90_response_time, p95_response_time, p99_response_time, max_response_time (in seconds) and failure_rate (between 0 and 1).
+    /// Can be given multiple times. The threshold fails if the endpoint has no results. The results are included in the JUnit report.
+    #[arg(long, action = clap::ArgAction::Append)]
+    #[serde(default)]
+    endpoint_threshold: Vec<String>,
+
+    /// Trend values are recorded in the percentiles as integers in units of 1 / trend scale. Default 1000000, use a bigger scale for smaller values.
+    #[arg(long, default_value = None)]
+    trend_scale: Option<f64>,
+
+    /// Upper bound in seconds of a bucket of the prometheus response time histogram. Can be given multiple times. If not set, exponential buckets are used.
+    #[arg(long, action = clap::ArgAction::Append)]
+    #[serde(default)]
+    response_time_buckets: Vec<f64>,
+
+    /// Exponential buckets of the prometheus response time histogram, given as {start},{factor},{count}. Ignored if response time buckets are given. Default 0.005,2,15.
+    #[arg(long, default_value = None)]
+    exponential_response_time_buckets: Option<String>,
+
+    /// Also export the last response time as a gauge (rocust_response_time). The histogram (rocust_response_time_seconds) is always exported.
+    #[arg(long)]
+    #[serde(default)]
+    response_time_gauge: bool,
+
+    /// Do not use the user id as a prometheus label. Recommended for tests with many users, every user id creates its own series.
+    #[arg(long)]
+    #[serde(default)]
+    prometheus_drop_user_id: bool,
+
+    /// Rewrite prometheus endpoint names matching a regex, given as {pattern}=>{replacement}, e.g. "^/items/\d+$=>/items/{id}". Can be given multiple times, the first matching rule wins.
+    #[arg(long, action = clap::ArgAction::Append)]
+    #[serde(default)]
+    prometheus_endpoint_name_rewrite: Vec<String>,
+
+    /// Maximum number of distinct prometheus endpoint names. Once reached, new endpoint names are exported as "other". If not set, endpoint names are not limited.
+    #[arg(long, default_value = None)]
+    prometheus_max_endpoint_names: Option<usize>,
+
+    /// Url of a Pushgateway, e.g. "http://localhost:9091". If set, the prometheus metrics are pushed every {update_interval} seconds and once at the end of the test.
+    #[arg(long, default_value = None)]
+    prometheus_pushgateway_url: Option<String>,
+
+    /// Job of the pushed prometheus metrics. Default "rocust".
+    #[arg(long, default_value = None)]
+    prometheus_pushgateway_job: Option<String>,
+
+    /// Grouping label of the pushed prometheus metrics, given as {name}={value}, e.g. "instance=ci". Can be given multiple times.
+    #[arg(long, action = clap::ArgAction::Append)]
+    #[serde(default)]
+    prometheus_pushgateway_grouping_label: Vec<String>,
+
+    /// Path to the file where the results should be appended to as InfluxDB line protocol every {update_interval} seconds. If not set, the results will not be written to a file.
+    #[arg(long, default_value = None)]
+    influx_file: Option<String>,
+
+    /// InfluxDB write url, e.g. "http://localhost:8086/write?db=rocust". If set, the results are sent as line protocol every {update_interval} seconds.
+    #[arg(long, default_value = None)]
+    influx_url: Option<String>,
+
+    /// InfluxDB token, sent as "Authorization: Token {token}".
+    #[arg(long, default_value = None)]
+    influx_token: Option<String>,
+
+    /// InfluxDB measurement of the results. Default "rocust".
+    #[arg(long, default_value = None)]
+    influx_measurement: Option<String>,
+
+    /// InfluxDB tag of the results, given as {name}={value}, e.g. "run_id=42". Can be given multiple times.
+    #[arg(long, action = clap::ArgAction::Append)]
+    #[serde(default)]
+    influx_tag: Vec<String>,
+
+    /// Graphite plaintext address, e.g. "localhost:2003". If set, the results are sent every {update_interval} seconds.
+    #[arg(long, default_value = None)]
+    graphite_address: Option<String>,
+
+    /// Graphite prefix of the results. Default "rocust".
+    #[arg(long, default_value = None)]
+    graphite_prefix: Option<String>,
+
+    /// Graphite tag of the results, given as {name}={value}, e.g. "run_id=42". Can be given multiple times.
+    #[arg(long, action = clap::ArgAction::Append)]
+    #[serde(default)]
+    graphite_tag: Vec<String>,
+
+    /// StatsD address, e.g. "localhost:8125". If set, every result is sent as StatsD packets over UDP.
+    #[arg(long, default_value = None)]
+    statsd_address: Option<String>,
+
+    /// StatsD prefix of the packets. Default "rocust".
+    #[arg(long, default_value = None)]
+    statsd_prefix: Option<String>,
+
+    /// Rate of the results sent to StatsD, between 0 and 1. Default 1.
+    #[arg(long, default_value = None)]
+    statsd_sample_rate: Option<f64>,
+
+    /// Do not tag the StatsD packets. Required for StatsD servers without DogStatsD tag support.
+    #[arg(long)]
+    #[serde(default)]
+    statsd_no_tags: bool,
+
+    /// DogStatsD tag of the packets, given as {name}={value}, e.g. "run_id=42". Can be given multiple times.
+    #[arg(long, action = clap::ArgAction::Append)]
+    #[serde(default)]
+    statsd_tag: Vec<String>,
+
+    /// Raw log file path. If set, every result is written as a line to this file. CSV if the extension is ".csv", JSON Lines otherwise.
+    #[arg(long, default_value = None)]
+    raw_log_file: Option<String>,
+
+    /// Size in bytes at which the raw log file is rotated. If not set, the file is never rotated.
+    #[arg(long, default_value = None)]
+    raw_log_max_file_size: Option<u64>,
+
+    /// Rate of the results written to the raw log file, between 0 and 1. Default 1.
+    #[arg(long, default_value = None)]
+    raw_log_sample_rate: Option<f64>,
+
+    /// OTLP HTTP endpoint, e.g. "http://localhost:4318". If set, the results are exported every {update_interval} seconds.
+    #[cfg(feature = "otel")]
+    #[arg(long, default_value = None)]
+    otel_endpoint: Option<String>,
+
+    /// Service name of the exported results. Default "rocust".
+    #[cfg(feature = "otel")]
+    #[arg(long, default_value = None)]
+    otel_service_name: Option<String>,
+
+    /// Create a span for every reported request and transaction.
+    #[cfg(feature = "otel")]
+    #[arg(long)]
+    #[serde(default)]
+    otel_request_spans: bool,
+
     /// Additional args, will be passed to the users.
     #[arg(long, action = clap::ArgAction::Append)]
     additional_arg: Vec<String>,
 
-    /// [Not supported yet] Stop the test when the stop condition is met. The stop condition will be checked at the end of each update phase (every {update_interval} seconds).
-    #[arg(long)]
-    stop_condition: Option<String>,
+    /// Stop the test when the stop condition is met, given as [{type} {name} ]{metric}>{max}, e.g. "failure_rate>0.1", with the metrics of the endpoint thresholds.
+    /// The stop condition will be checked at the end of each update phase (every {update_interval} seconds). Can be given multiple times, the test is stopped by the first one met.
+    #[arg(long, action = clap::ArgAction::Append)]
+    #[serde(default)]
+    stop_condition: Vec<String>,
 }
 
 #[derive(Debug, ThisError)]
 pub enum FromExternalTestConfigError {
     #[error("Error while parsing server address: {0}")]
     ServerAddressParseError(#[from] AddrParseError),
+    #[error("Error while parsing check threshold: {0}")]
+    CheckThresholdParseError(#[from] CheckThresholdParseError),
+    #[error("Error while parsing endpoint threshold or stop condition: {0}")]
+    ThresholdParseError(#[from] ThresholdParseError),
+    #[error("Error while parsing response time buckets: {0}")]
+    ResponseTimeBucketsParseError(#[from] ResponseTimeBucketsParseError),
+    #[error("Error while parsing endpoint name rewrite: {0}")]
+    EndpointNameRewriteParseError(#[from] EndpointNameRewriteParseError),
+    #[error("Error while parsing label: {0}")]
+    LabelParseError(#[from] LabelParseError),
 }
 
 #[derive(Debug, ThisError)]
@@ -362,3 +1447,62 @@ impl std::fmt::Display for TestConfig {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn start_failure_backoff_doubles_per_attempt() {
+        let start_failure_policy = StartFailurePolicy::retry(3, 100);
+
+        assert_eq!(
+            start_failure_policy.get_backoff(0),
+            Duration::from_millis(100)
+        );
+        assert_eq!(
+            start_failure_policy.get_backoff(1),
+            Duration::from_millis(200)
+        );
+        assert_eq!(
+            start_failure_policy.get_backoff(2),
+            Duration::from_millis(400)
+        );
+        // saturates instead of overflowing
+        assert_eq!(
+            start_failure_policy.get_backoff(100),
+            Duration::from_millis(u64::MAX)
+        );
+    }
+
+    #[test]
+    fn exponential_response_time_buckets_are_parsed() {
+        let response_time_buckets: ResponseTimeBuckets = "0.01, 2, 3".parse().unwrap();
+
+        assert_eq!(
+            response_time_buckets,
+            ResponseTimeBuckets::Exponential {
+                start: 0.01,
+                factor: 2.0,
+                count: 3
+            }
+        );
+        assert_eq!(response_time_buckets.get_bounds(), vec![0.01, 0.02, 0.04]);
+        assert!(matches!(
+            "0.01,2".parse::<ResponseTimeBuckets>(),
+            Err(ResponseTimeBucketsParseError::WrongFormat)
+        ));
+        assert!(matches!(
+            "0.01,2,many".parse::<ResponseTimeBuckets>(),
+            Err(ResponseTimeBucketsParseError::InvalidCount(_))
+        ));
+    }
+
+    #[test]
+    fn explicit_response_time_buckets_are_sorted_and_deduplicated() {
+        let response_time_buckets =
+            ResponseTimeBuckets::Explicit(vec![1.0, 0.1, f64::INFINITY, 0.5, 0.1, f64::NAN]);
+
+        assert_eq!(response_time_buckets.get_bounds(), vec![0.1, 0.5, 1.0]);
+    }
+}
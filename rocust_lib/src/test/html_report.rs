@@ -0,0 +1,512 @@
+use crate::{
+    errors::AllErrors,
+    results::{AllResults, Results, Totals, FILE_AGR_TYPE_NAME},
+    test::user::{UserStatsCollection, UserStatsInfo},
+    utils, TestConfig,
+};
+use std::{collections::BTreeMap, time::Duration};
+
+const CHART_WIDTH: f64 = 860.0;
+const CHART_HEIGHT: f64 = 220.0;
+const CHART_MARGIN: f64 = 40.0;
+
+const STYLE: &str = "body{font-family:sans-serif;margin:2em;color:#222}\
+table{border-collapse:collapse;margin-bottom:2em}\
+th,td{border:1px solid #ccc;padding:4px 8px;text-align:right}\
+th{background:#f3f3f3}td.text{text-align:left}\
+svg{display:block;margin-bottom:2em}\
+.legend{font-size:12px}";
+
+// the results are cumulative, the timeline keeps the rates of every update interval
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub(crate) struct TimelinePoint {
+    pub(crate) elapsed_secs: f64,
+    pub(crate) users: u64,
+    pub(crate) requests_per_second: f64,
+    pub(crate) failed_requests_per_second: f64,
+    pub(crate) errors_per_second: f64,
+    // of the successful requests of the interval
+    pub(crate) average_response_time: f64,
+    // of all successful requests until then
+    pub(crate) percentile_95_response_time: f64,
+}
+
+#[derive(Debug, Default, Clone)]
+pub(crate) struct ResultsTimeline {
+    points: Vec<TimelinePoint>,
+    previous_totals: Totals,
+}
+
+impl ResultsTimeline {
+    pub(crate) fn record(&mut self, elapsed_time: &Duration, users: u64, all_results: &AllResults) {
+        let results = all_results.get_aggrigated_results();
+        let totals = Totals::new(elapsed_time, results);
+        let previous = self.previous_totals;
+        let interval = totals.elapsed_secs - previous.elapsed_secs;
+        if interval <= 0.0 {
+            return;
+        }
+        let requests = totals.requests - previous.requests;
+        let failed_requests = totals.failed_requests - previous.failed_requests;
+        let successful_requests = requests - failed_requests;
+        let average_response_time = if successful_requests > 0 {
+            (totals.response_time - previous.response_time) / successful_requests as f64
+        } else {
+            0.0
+        };
+        self.points.push(TimelinePoint {
+            elapsed_secs: totals.elapsed_secs,
+            users,
+            requests_per_second: requests as f64 / interval,
+            failed_requests_per_second: failed_requests as f64 / interval,
+            errors_per_second: (totals.errors - previous.errors) as f64 / interval,
+            average_response_time,
+            percentile_95_response_time: results.get_percentile_95_response_time(),
+        });
+        self.previous_totals = totals;
+    }
+
+    pub(crate) fn get_points(&self) -> &[TimelinePoint] {
+        &self.points
+    }
+}
+
+// per user name
+#[derive(Debug, Default)]
+struct UserRow {
+    statuses: BTreeMap<String, u64>,
+    total_tasks: u64,
+    total_restarts: u64,
+    total_panics: u64,
+}
+
+struct Series {
+    name: &'static str,
+    color: &'static str,
+    // elapsed seconds and value
+    points: Vec<(f64, f64)>,
+}
+
+pub(crate) struct HtmlReport<'a> {
+    pub(crate) test_config: &'a TestConfig,
+    pub(crate) elapsed_time: &'a Duration,
+    pub(crate) all_results: &'a AllResults,
+    pub(crate) all_errors: &'a AllErrors,
+    pub(crate) all_failures: &'a AllErrors,
+    pub(crate) user_stats_collection: &'a UserStatsCollection,
+    pub(crate) results_timeline: &'a ResultsTimeline,
+}
+
+impl<'a> HtmlReport<'a> {
+    pub(crate) fn html_string(&self) -> String {
+        let mut html = String::from("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+        html.push_str("<title>Rocust report</title>\n");
+        html.push_str(&format!("<style>{}</style>\n", STYLE));
+        html.push_str("</head>\n<body>\n<h1>Rocust report</h1>\n");
+        html.push_str(&self.config_section());
+        html.push_str(&self.results_section());
+        html.push_str(&self.charts_section());
+        html.push_str(&self.errors_section());
+        html.push_str(&self.users_section());
+        html.push_str("</body>\n</html>\n");
+        html
+    }
+
+    // averages without requests are not a number
+    fn number(&self, value: f64) -> String {
+        if !value.is_finite() {
+            return String::from("-");
+        }
+        format!("{:.1$}", value, self.test_config.precision)
+    }
+
+    fn config_section(&self) -> String {
+        let test_config = self.test_config;
+        let runtime = match test_config.runtime {
+            Some(runtime) => format!("{}s", runtime),
+            None => String::from("until stopped"),
+        };
+        let mut rows = vec![
+            ("Users", test_config.user_count.to_string()),
+            ("Users per second", test_config.users_per_sec.to_string()),
+            ("Runtime", runtime),
+            (
+                "Elapsed time",
+                format!("{}s", self.number(self.elapsed_time.as_secs_f64())),
+            ),
+            (
+                "Update interval",
+                format!("{}s", test_config.update_interval_in_secs),
+            ),
+        ];
+        if let Some(base_url) = &test_config.base_url {
+            rows.push(("Base url", base_url.clone()));
+        }
+        if !test_config.additional_args.is_empty() {
+            rows.push(("Additional args", test_config.additional_args.join(" ")));
+        }
+
+        let mut section = String::from("<h2>Test</h2>\n<table>\n");
+        for (name, value) in rows {
+            section.push_str(&format!(
+                "<tr><th class=\"text\">{}</th><td class=\"text\">{}</td></tr>\n",
+                name,
+                utils::escape_xml(&value)
+            ));
+        }
+        section.push_str("</table>\n");
+        section
+    }
+
+    fn results_row(&self, r#type: &str, name: &str, results: &Results) -> String {
+        let mut row = format!(
+            "<tr><td class=\"text\">{}</td><td class=\"text\">{}</td>",
+            utils::escape_xml(r#type),
+            utils::escape_xml(name)
+        );
+        for count in [
+            results.get_total_requests(),
+            results.get_total_failed_requests(),
+            results.get_total_errors(),
+        ] {
+            row.push_str(&format!("<td>{}</td>", count));
+        }
+        for value in [
+            results.get_requests_per_second(),
+            results.get_average_response_time(),
+            results.get_min_response_time(),
+            results.get_median_response_time(),
+            results.get_percentile_90_response_time(),
+            results.get_percentile_95_response_time(),
+            results.get_percentile_99_response_time(),
+            results.get_max_response_time(),
+        ] {
+            row.push_str(&format!("<td>{}</td>", self.number(value)));
+        }
+        let status_codes = results
+            .get_status_codes()
+            .iter()
+            .map(|(status_code, count)| format!("{}:{}", status_code, count))
+            .collect::<Vec<_>>()
+            .join(" ");
+        row.push_str(&format!("<td class=\"text\">{}</td></tr>\n", status_codes));
+        row
+    }
+
+    fn results_section(&self) -> String {
+        let mut section =
+            String::from("<h2>Endpoints</h2>\n<p>Response times in seconds.</p>\n<table>\n<tr>");
+        for header in [
+            "Type",
+            "Name",
+            "Requests",
+            "Failed",
+            "Errors",
+            "Req/s",
+            "Avg",
+            "Min",
+            "Median",
+            "90%",
+            "95%",
+            "99%",
+            "Max",
+            "Status codes",
+        ] {
+            section.push_str(&format!("<th>{}</th>", header));
+        }
+        section.push_str("</tr>\n");
+
+        let mut endpoint_results: Vec<_> = self.all_results.get_endpoint_results().iter().collect();
+        endpoint_results.sort_by(|(a, _), (b, _)| (&a.r#type, &a.name).cmp(&(&b.r#type, &b.name)));
+        for (endpoint_type_name, results) in endpoint_results {
+            section.push_str(&self.results_row(
+                &endpoint_type_name.r#type,
+                &endpoint_type_name.name,
+                results,
+            ));
+        }
+        section.push_str(&self.results_row(
+            FILE_AGR_TYPE_NAME[0],
+            FILE_AGR_TYPE_NAME[1],
+            self.all_results.get_aggrigated_results(),
+        ));
+        section.push_str("</table>\n");
+        section
+    }
+
+    fn charts_section(&self) -> String {
+        let points = self.results_timeline.get_points();
+        let mut section = String::from("<h2>Timeline</h2>\n");
+        if points.is_empty() {
+            section.push_str("<p>No update interval has passed.</p>\n");
+            return section;
+        }
+        let series = |name, color, value: fn(&TimelinePoint) -> f64| Series {
+            name,
+            color,
+            points: points
+                .iter()
+                .map(|point| (point.elapsed_secs, value(point)))
+                .collect(),
+        };
+        section.push_str(&svg_chart(
+            "Requests per second",
+            &[
+                series("requests", "#1f77b4", |p| p.requests_per_second),
+                series("failed", "#ff7f0e", |p| p.failed_requests_per_second),
+                series("errors", "#d62728", |p| p.errors_per_second),
+            ],
+        ));
+        section.push_str(&svg_chart(
+            "Response time (s)",
+            &[
+                series("average", "#1f77b4", |p| p.average_response_time),
+                series("95%", "#9467bd", |p| p.percentile_95_response_time),
+            ],
+        ));
+        section.push_str(&svg_chart(
+            "Spawned users",
+            &[series("users", "#2ca02c", |p| p.users as f64)],
+        ));
+        section
+    }
+
+    fn errors_section(&self) -> String {
+        let mut section = String::from("<h2>Errors and failures</h2>\n");
+        if self.all_errors.is_empty() && self.all_failures.is_empty() {
+            section.push_str("<p>None.</p>\n");
+            return section;
+        }
+        // the timestamps are absolute, shown relative to the start of the test
+        let start_timestamp = utils::get_timestamp_as_millis()
+            .map(|now| now as u64)
+            .unwrap_or_default()
+            .saturating_sub(self.elapsed_time.as_millis() as u64);
+        section.push_str("<table>\n<tr><th>Kind</th><th>Type</th><th>Name</th><th>Message</th><th>Occurrences</th><th>First seen (s)</th><th>Last seen (s)</th></tr>\n");
+        for (kind, all_errors) in [("error", self.all_errors), ("failure", self.all_failures)] {
+            for (endpoint_type_name_error, error_stats) in all_errors.get_errors() {
+                let seen = |timestamp: u64| {
+                    self.number(timestamp.saturating_sub(start_timestamp) as f64 / 1000.0)
+                };
+                section.push_str(&format!(
+                    "<tr><td class=\"text\">{}</td><td class=\"text\">{}</td><td class=\"text\">{}</td><td class=\"text\">{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                    kind,
+                    utils::escape_xml(&endpoint_type_name_error.endpoint_type_name.r#type),
+                    utils::escape_xml(&endpoint_type_name_error.endpoint_type_name.name),
+                    utils::escape_xml(&endpoint_type_name_error.error),
+                    error_stats.get_occurrences(),
+                    seen(error_stats.get_first_seen()),
+                    seen(error_stats.get_last_seen()),
+                ));
+            }
+        }
+        section.push_str("</table>\n");
+        section
+    }
+
+    fn users_section(&self) -> String {
+        let mut users: BTreeMap<&str, UserRow> = BTreeMap::new();
+        for user_info in self.user_stats_collection.get_user_stats_infos() {
+            let UserStatsInfo {
+                name,
+                status,
+                total_tasks,
+                total_restarts,
+                panics,
+                ..
+            } = user_info;
+            let user_row = users.entry(name).or_default();
+            *user_row
+                .statuses
+                .entry(format!("{:?}", status))
+                .or_default() += 1;
+            user_row.total_tasks += total_tasks;
+            user_row.total_restarts += total_restarts;
+            user_row.total_panics += panics.values().sum::<u64>();
+        }
+
+        let mut section = String::from("<h2>Users</h2>\n<table>\n<tr><th>User</th><th>Count</th><th>Status</th><th>Tasks</th><th>Restarts</th><th>Panics</th></tr>\n");
+        for (name, user_row) in users {
+            let count: u64 = user_row.statuses.values().sum();
+            let statuses = user_row
+                .statuses
+                .iter()
+                .map(|(status, count)| format!("{}: {}", status, count))
+                .collect::<Vec<_>>()
+                .join(", ");
+            section.push_str(&format!(
+                "<tr><td class=\"text\">{}</td><td>{}</td><td class=\"text\">{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                utils::escape_xml(name),
+                count,
+                statuses,
+                user_row.total_tasks,
+                user_row.total_restarts,
+                user_row.total_panics
+            ));
+        }
+        section.push_str("</table>\n");
+        section
+    }
+}
+
+// a line chart without scripts, so the report can be viewed offline
+fn svg_chart(title: &str, series: &[Series]) -> String {
+    let values = || series.iter().flat_map(|series| series.points.iter());
+    let max_x = values().map(|(x, _)| *x).fold(0.0, f64::max);
+    let max_y = values().map(|(_, y)| *y).fold(0.0, f64::max);
+    // a flat line at the bottom instead of a division by zero
+    let (max_x, max_y) = (
+        if max_x > 0.0 { max_x } else { 1.0 },
+        if max_y > 0.0 { max_y } else { 1.0 },
+    );
+    let plot_width = CHART_WIDTH - 2.0 * CHART_MARGIN;
+    let plot_height = CHART_HEIGHT - 2.0 * CHART_MARGIN;
+    let bottom = CHART_HEIGHT - CHART_MARGIN;
+
+    let mut svg = format!(
+        "<h3>{}</h3>\n<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\">\n",
+        utils::escape_xml(title),
+        CHART_WIDTH,
+        CHART_HEIGHT,
+        CHART_WIDTH,
+        CHART_HEIGHT
+    );
+    svg.push_str(&format!(
+        "<polyline fill=\"none\" stroke=\"#888\" points=\"{m},{m} {m},{b} {r},{b}\"/>\n",
+        m = CHART_MARGIN,
+        b = bottom,
+        r = CHART_WIDTH - CHART_MARGIN
+    ));
+    svg.push_str(&format!(
+        "<text x=\"{}\" y=\"{}\" font-size=\"11\" text-anchor=\"end\">{}</text>\n",
+        CHART_MARGIN - 4.0,
+        CHART_MARGIN + 4.0,
+        format_axis(max_y)
+    ));
+    svg.push_str(&format!(
+        "<text x=\"{}\" y=\"{}\" font-size=\"11\" text-anchor=\"end\">0</text>\n",
+        CHART_MARGIN - 4.0,
+        bottom + 4.0
+    ));
+    svg.push_str(&format!(
+        "<text x=\"{}\" y=\"{}\" font-size=\"11\" text-anchor=\"end\">{}s</text>\n",
+        CHART_WIDTH - CHART_MARGIN,
+        bottom + 16.0,
+        format_axis(max_x)
+    ));
+    for (index, series) in series.iter().enumerate() {
+        let points = series
+            .points
+            .iter()
+            .map(|(x, y)| {
+                format!(
+                    "{:.1},{:.1}",
+                    CHART_MARGIN + x / max_x * plot_width,
+                    bottom - y / max_y * plot_height
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+        svg.push_str(&format!(
+            "<polyline fill=\"none\" stroke=\"{}\" stroke-width=\"2\" points=\"{}\"/>\n",
+            series.color, points
+        ));
+        svg.push_str(&format!(
+            "<text class=\"legend\" x=\"{}\" y=\"{}\" fill=\"{}\">{}</text>\n",
+            CHART_MARGIN + 10.0 + index as f64 * 110.0,
+            CHART_MARGIN - 12.0,
+            series.color,
+            utils::escape_xml(series.name)
+        ));
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+fn format_axis(value: f64) -> String {
+    if value >= 100.0 {
+        format!("{:.0}", value)
+    } else {
+        format!("{:.3}", value)
+            .trim_end_matches('0')
+            .trim_end_matches('.')
+            .to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        errors::ErrorKind,
+        results::{EndpointTypeName, ResponseInfo},
+    };
+
+    fn endpoint_type_name(name: &str) -> EndpointTypeName {
+        EndpointTypeName {
+            r#type: String::from("GET"),
+            name: String::from(name),
+        }
+    }
+
+    #[test]
+    fn timeline_keeps_the_rates_of_every_interval() {
+        let mut all_results = AllResults::with_percentiles();
+        let mut results_timeline = ResultsTimeline::default();
+        for _ in 0..4 {
+            all_results.add_success(&endpoint_type_name("/"), None, 0.1, &ResponseInfo::new());
+        }
+        all_results.calculate_on_update_interval(&Duration::from_secs(2));
+        results_timeline.record(&Duration::from_secs(2), 1, &all_results);
+        all_results.add_success(&endpoint_type_name("/"), None, 0.4, &ResponseInfo::new());
+        all_results.add_failure(&endpoint_type_name("/"), None, None, &ResponseInfo::new());
+        all_results.calculate_on_update_interval(&Duration::from_secs(4));
+        results_timeline.record(&Duration::from_secs(4), 2, &all_results);
+
+        let points = results_timeline.get_points();
+        assert_eq!(points.len(), 2);
+        assert_eq!(points[0].requests_per_second, 2.0);
+        assert!((points[0].average_response_time - 0.1).abs() < 1e-9);
+        assert_eq!(points[1].requests_per_second, 1.0);
+        assert_eq!(points[1].failed_requests_per_second, 0.5);
+        assert!((points[1].average_response_time - 0.4).abs() < 1e-9);
+        assert_eq!(points[1].users, 2);
+    }
+
+    #[test]
+    fn report_contains_every_section() {
+        let mut all_results = AllResults::with_percentiles();
+        all_results.add_success(
+            &endpoint_type_name("/<items>"),
+            None,
+            0.2,
+            &ResponseInfo::new(),
+        );
+        all_results.calculate_on_update_interval(&Duration::from_secs(1));
+        let mut results_timeline = ResultsTimeline::default();
+        results_timeline.record(&Duration::from_secs(1), 1, &all_results);
+        let mut all_errors = AllErrors::new(ErrorKind::Error, 10);
+        all_errors.add_error(&endpoint_type_name("/"), "connection refused");
+        let mut user_stats_collection = UserStatsCollection::new();
+        user_stats_collection.insert_user(0, "ShopUser");
+
+        let html = HtmlReport {
+            test_config: &TestConfig::default(),
+            elapsed_time: &Duration::from_secs(1),
+            all_results: &all_results,
+            all_errors: &all_errors,
+            all_failures: &AllErrors::new(ErrorKind::Failure, 10),
+            user_stats_collection: &user_stats_collection,
+            results_timeline: &results_timeline,
+        }
+        .html_string();
+
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<td class=\"text\">/&lt;items&gt;</td>"));
+        assert!(html.contains("<td class=\"text\">aggregated</td>"));
+        assert!(html.contains("<h3>Requests per second</h3>"));
+        assert!(html.contains("connection refused"));
+        assert!(html.contains("<td class=\"text\">ShopUser</td><td>1</td>"));
+        assert!(!html.contains("<script"));
+    }
+}
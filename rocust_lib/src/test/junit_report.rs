@@ -0,0 +1,406 @@
+use crate::{
+    checks::{AllChecks, CheckThreshold},
+    thresholds::{EndpointThresholdResult, StopCondition, StopConditionMet},
+    utils::escape_xml,
+};
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq)]
+struct TestCase {
+    name: String,
+    classname: &'static str,
+    // message and details (expected vs observed) of a failed test case
+    failure: Option<(String, String)>,
+    system_out: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct TestSuite {
+    name: &'static str,
+    test_cases: Vec<TestCase>,
+}
+
+impl TestSuite {
+    fn failures(&self) -> usize {
+        self.test_cases
+            .iter()
+            .filter(|test_case| test_case.failure.is_some())
+            .count()
+    }
+}
+
+// every check threshold is a test case
+fn check_thresholds_suite(
+    all_checks: &AllChecks,
+    check_thresholds: &[CheckThreshold],
+    precision: usize,
+) -> TestSuite {
+    let test_cases = all_checks
+        .evaluate_thresholds(check_thresholds)
+        .into_iter()
+        .map(|result| {
+            let failure = if result.passed {
+                None
+            } else {
+                let expected = format!("pass rate >= {:.1$}", result.min_pass_rate, precision);
+                let (message, observed) =
+                    match (result.pass_rate, all_checks.get_by_name(&result.check_name)) {
+                        (Some(pass_rate), Some(check_stats)) => (
+                            format!(
+                                "pass rate {:.2$} is below {:.2$}",
+                                pass_rate, result.min_pass_rate, precision
+                            ),
+                            format!(
+                                "pass rate {:.3$} ({} passed, {} failed)",
+                                pass_rate,
+                                check_stats.get_passes(),
+                                check_stats.get_fails(),
+                                precision
+                            ),
+                        ),
+                        _ => (
+                            String::from("check was never executed"),
+                            String::from("no checks"),
+                        ),
+                    };
+                Some((
+                    message,
+                    format!("expected: {}\nobserved: {}", expected, observed),
+                ))
+            };
+            TestCase {
+                name: result.check_name,
+                classname: "rocust.check_thresholds",
+                failure,
+                system_out: None,
+            }
+        })
+        .collect();
+    TestSuite {
+        name: "check thresholds",
+        test_cases,
+    }
+}
+
+// checks without a threshold only report their counts and always pass
+fn checks_suite(all_checks: &AllChecks, check_thresholds: &[CheckThreshold]) -> TestSuite {
+    let mut checks: Vec<_> = all_checks
+        .get_checks()
+        .iter()
+        .filter(|(name, _)| {
+            !check_thresholds
+                .iter()
+                .any(|check_threshold| &check_threshold.check_name == *name)
+        })
+        .collect();
+    checks.sort_by_key(|(name, _)| *name);
+    let test_cases = checks
+        .into_iter()
+        .map(|(name, check_stats)| {
+            let system_out = format!(
+                "{} passed, {} failed",
+                check_stats.get_passes(),
+                check_stats.get_fails()
+            );
+            TestCase {
+                name: name.clone(),
+                classname: "rocust.checks",
+                failure: None,
+                system_out: Some(system_out),
+            }
+        })
+        .collect();
+    TestSuite {
+        name: "checks",
+        test_cases,
+    }
+}
+
+// every endpoint threshold is a test case
+fn endpoint_thresholds_suite(
+    endpoint_threshold_results: &[EndpointThresholdResult],
+    precision: usize,
+) -> TestSuite {
+    let test_cases = endpoint_threshold_results
+        .iter()
+        .map(|result| {
+            let endpoint_threshold = &result.endpoint_threshold;
+            let failure = if result.passed {
+                None
+            } else {
+                let expected = format!(
+                    "{} <= {:.2$}",
+                    endpoint_threshold.metric, endpoint_threshold.max, precision
+                );
+                let (message, observed) = match result.observed {
+                    Some(observed) => (
+                        format!(
+                            "{} {:.3$} is above {:.3$}",
+                            endpoint_threshold.metric, observed, endpoint_threshold.max, precision
+                        ),
+                        format!("{} {:.2$}", endpoint_threshold.metric, observed, precision),
+                    ),
+                    None => (
+                        String::from("endpoint has no results"),
+                        String::from("no results"),
+                    ),
+                };
+                Some((
+                    message,
+                    format!("expected: {}\nobserved: {}", expected, observed),
+                ))
+            };
+            TestCase {
+                name: endpoint_threshold.to_string(),
+                classname: "rocust.endpoint_thresholds",
+                failure,
+                system_out: None,
+            }
+        })
+        .collect();
+    TestSuite {
+        name: "endpoint thresholds",
+        test_cases,
+    }
+}
+
+// every stop condition is a test case that fails if it stopped the test
+fn stop_conditions_suite(
+    stop_conditions: &[StopCondition],
+    stop_condition_met: Option<&StopConditionMet>,
+    precision: usize,
+) -> TestSuite {
+    let test_cases = stop_conditions
+        .iter()
+        .map(|stop_condition| {
+            let failure = stop_condition_met
+                .filter(|stop_condition_met| &stop_condition_met.stop_condition == stop_condition)
+                .map(|stop_condition_met| {
+                    (
+                        format!(
+                            "test was stopped, {} {:.3$} is above {:.3$}",
+                            stop_condition.metric,
+                            stop_condition_met.observed,
+                            stop_condition.max,
+                            precision
+                        ),
+                        format!(
+                            "expected: {} <= {:.4$}\nobserved: {} {:.4$}",
+                            stop_condition.metric,
+                            stop_condition.max,
+                            stop_condition.metric,
+                            stop_condition_met.observed,
+                            precision
+                        ),
+                    )
+                });
+            TestCase {
+                name: stop_condition.to_string(),
+                classname: "rocust.stop_conditions",
+                failure,
+                system_out: None,
+            }
+        })
+        .collect();
+    TestSuite {
+        name: "stop conditions",
+        test_cases,
+    }
+}
+
+pub(crate) fn junit_xml_string(
+    all_checks: &AllChecks,
+    check_thresholds: &[CheckThreshold],
+    endpoint_threshold_results: &[EndpointThresholdResult],
+    stop_conditions: &[StopCondition],
+    stop_condition_met: Option<&StopConditionMet>,
+    elapsed_time: &Duration,
+    precision: usize,
+) -> String {
+    let test_suites = [
+        check_thresholds_suite(all_checks, check_thresholds, precision),
+        checks_suite(all_checks, check_thresholds),
+        endpoint_thresholds_suite(endpoint_threshold_results, precision),
+        stop_conditions_suite(stop_conditions, stop_condition_met, precision),
+    ];
+    let tests: usize = test_suites
+        .iter()
+        .map(|test_suite| test_suite.test_cases.len())
+        .sum();
+    let failures: usize = test_suites.iter().map(TestSuite::failures).sum();
+    let time = format!("{:.3}", elapsed_time.as_secs_f64());
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str(&format!(
+        "<testsuites name=\"rocust\" tests=\"{}\" failures=\"{}\" errors=\"0\" time=\"{}\">\n",
+        tests, failures, time
+    ));
+    for test_suite in &test_suites {
+        xml.push_str(&format!(
+            "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"0\" skipped=\"0\" time=\"{}\">\n",
+            test_suite.name,
+            test_suite.test_cases.len(),
+            test_suite.failures(),
+            time
+        ));
+        for test_case in &test_suite.test_cases {
+            let attributes = format!(
+                "name=\"{}\" classname=\"{}\"",
+                escape_xml(&test_case.name),
+                test_case.classname
+            );
+            if test_case.failure.is_none() && test_case.system_out.is_none() {
+                xml.push_str(&format!("    <testcase {}/>\n", attributes));
+                continue;
+            }
+            xml.push_str(&format!("    <testcase {}>\n", attributes));
+            if let Some((message, details)) = &test_case.failure {
+                xml.push_str(&format!(
+                    "      <failure message=\"{}\" type=\"{}\">{}</failure>\n",
+                    escape_xml(message),
+                    test_suite.name,
+                    escape_xml(details)
+                ));
+            }
+            if let Some(system_out) = &test_case.system_out {
+                xml.push_str(&format!(
+                    "      <system-out>{}</system-out>\n",
+                    escape_xml(system_out)
+                ));
+            }
+            xml.push_str("    </testcase>\n");
+        }
+        xml.push_str("  </testsuite>\n");
+    }
+    xml.push_str("</testsuites>\n");
+    xml
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::thresholds::EndpointThreshold;
+
+    fn all_checks() -> AllChecks {
+        let mut all_checks = AllChecks::default();
+        for passed in [true, true, true, false] {
+            all_checks.add_check("status is 200", passed);
+        }
+        all_checks.add_check("body has <items>", true);
+        all_checks.add_check("cart is empty", false);
+        all_checks
+    }
+
+    fn endpoint_threshold_result(
+        endpoint_threshold: &str,
+        observed: Option<f64>,
+        passed: bool,
+    ) -> EndpointThresholdResult {
+        EndpointThresholdResult {
+            endpoint_threshold: endpoint_threshold.parse::<EndpointThreshold>().unwrap(),
+            observed,
+            passed,
+        }
+    }
+
+    #[test]
+    fn failed_thresholds_are_failures_and_checks_pass_with_their_counts() {
+        let check_thresholds = vec![
+            "status is 200=0.9".parse().unwrap(),
+            "never=0.5".parse().unwrap(),
+        ];
+
+        let xml = junit_xml_string(
+            &all_checks(),
+            &check_thresholds,
+            &[],
+            &[],
+            None,
+            &Duration::from_millis(1500),
+            3,
+        );
+
+        assert!(xml.contains(
+            "<testsuites name=\"rocust\" tests=\"4\" failures=\"2\" errors=\"0\" time=\"1.500\">"
+        ));
+        assert!(xml.contains("<testcase name=\"status is 200\" classname=\"rocust.check_thresholds\">\n      <failure message=\"pass rate 0.750 is below 0.900\" type=\"check thresholds\">expected: pass rate &gt;= 0.900\nobserved: pass rate 0.750 (3 passed, 1 failed)</failure>"));
+        assert!(xml.contains("<failure message=\"check was never executed\""));
+        assert!(xml.contains("<testcase name=\"body has &lt;items&gt;\" classname=\"rocust.checks\">\n      <system-out>1 passed, 0 failed</system-out>\n    </testcase>"));
+        assert!(xml.contains("<testcase name=\"cart is empty\" classname=\"rocust.checks\">\n      <system-out>0 passed, 1 failed</system-out>\n    </testcase>"));
+        // only in the check thresholds suite
+        assert_eq!(xml.matches("name=\"status is 200\"").count(), 1);
+    }
+
+    #[test]
+    fn passed_threshold_is_not_a_failure() {
+        let check_thresholds = vec!["status is 200=0.75".parse().unwrap()];
+
+        let xml = junit_xml_string(
+            &all_checks(),
+            &check_thresholds,
+            &[],
+            &[],
+            None,
+            &Duration::ZERO,
+            3,
+        );
+
+        assert!(xml
+            .contains("<testcase name=\"status is 200\" classname=\"rocust.check_thresholds\"/>"));
+        assert!(xml.contains("tests=\"3\" failures=\"0\""));
+    }
+
+    #[test]
+    fn failed_endpoint_thresholds_are_failures() {
+        let endpoint_threshold_results = vec![
+            endpoint_threshold_result("GET /items p95_response_time<=0.5", Some(0.75), false),
+            endpoint_threshold_result("GET /missing failure_rate<=0.1", None, false),
+            endpoint_threshold_result("failure_rate<=0.1", Some(0.05), true),
+        ];
+
+        let xml = junit_xml_string(
+            &AllChecks::default(),
+            &[],
+            &endpoint_threshold_results,
+            &[],
+            None,
+            &Duration::ZERO,
+            3,
+        );
+
+        assert!(xml.contains("<testsuite name=\"endpoint thresholds\" tests=\"3\" failures=\"2\""));
+        assert!(xml.contains("<testcase name=\"GET /items p95_response_time&lt;=0.5\" classname=\"rocust.endpoint_thresholds\">\n      <failure message=\"p95_response_time 0.750 is above 0.500\" type=\"endpoint thresholds\">expected: p95_response_time &lt;= 0.500\nobserved: p95_response_time 0.750</failure>"));
+        assert!(xml.contains("<failure message=\"endpoint has no results\" type=\"endpoint thresholds\">expected: failure_rate &lt;= 0.100\nobserved: no results</failure>"));
+        assert!(xml.contains(
+            "<testcase name=\"failure_rate&lt;=0.1\" classname=\"rocust.endpoint_thresholds\"/>"
+        ));
+    }
+
+    #[test]
+    fn only_the_met_stop_condition_is_a_failure() {
+        let stop_conditions: Vec<StopCondition> = vec![
+            "failure_rate>0.1".parse().unwrap(),
+            "GET /items max_response_time>2".parse().unwrap(),
+        ];
+        let stop_condition_met = StopConditionMet {
+            stop_condition: stop_conditions[0].clone(),
+            observed: 0.25,
+        };
+
+        let xml = junit_xml_string(
+            &AllChecks::default(),
+            &[],
+            &[],
+            &stop_conditions,
+            Some(&stop_condition_met),
+            &Duration::ZERO,
+            3,
+        );
+
+        assert!(xml.contains("<testsuite name=\"stop conditions\" tests=\"2\" failures=\"1\""));
+        assert!(xml.contains("<testcase name=\"failure_rate&gt;0.1\" classname=\"rocust.stop_conditions\">\n      <failure message=\"test was stopped, failure_rate 0.250 is above 0.100\" type=\"stop conditions\">expected: failure_rate &lt;= 0.100\nobserved: failure_rate 0.250</failure>"));
+        assert!(xml.contains(
+            "<testcase name=\"GET /items max_response_time&gt;2\" classname=\"rocust.stop_conditions\"/>"
+        ));
+    }
+}
@@ -0,0 +1,418 @@
+use crate::{
+    fs::writer::{CreateError, WriteError, Writer},
+    results::{AllResults, Results, FILE_AGR_TYPE_NAME},
+    test::config::{GraphiteOutput, InfluxOutput},
+};
+use hyper::{
+    client::HttpConnector,
+    header::AUTHORIZATION,
+    http::{uri::InvalidUri, Error as HttpError},
+    Body, Client, Error as HyperError, Method, Request, StatusCode, Uri,
+};
+use std::{io::Error as IoError, time::Duration};
+use thiserror::Error as ThisError;
+use tokio::{io::AsyncWriteExt, net::TcpStream};
+
+const SEND_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, ThisError)]
+pub enum LineWriterError {
+    #[error("Invalid url: {0}")]
+    InvalidUri(#[from] InvalidUri),
+    #[error("Error creating file: {0}")]
+    CreateError(#[from] CreateError),
+    #[error("Error writing file: {0}")]
+    WriteError(#[from] WriteError),
+    #[error("Error building request: {0}")]
+    RequestError(#[from] HttpError),
+    #[error("Error sending results: {0}")]
+    HyperError(#[from] HyperError),
+    #[error("Error sending results: {0}")]
+    IoError(#[from] IoError),
+    #[error("No response within {0:?}")]
+    Timeout(Duration),
+    #[error("Unexpected status {0}")]
+    UnexpectedStatus(StatusCode),
+}
+
+enum FieldValue {
+    Integer(u64),
+    Float(f64),
+}
+
+// non finite values (e.g. the average response time without successful requests) are skipped
+fn fields(results: &Results) -> Vec<(&'static str, FieldValue)> {
+    let fields = [
+        (
+            "total_requests",
+            FieldValue::Integer(results.get_total_requests() as u64),
+        ),
+        (
+            "total_failed_requests",
+            FieldValue::Integer(results.get_total_failed_requests() as u64),
+        ),
+        (
+            "total_errors",
+            FieldValue::Integer(results.get_total_errors() as u64),
+        ),
+        (
+            "total_timeouts",
+            FieldValue::Integer(results.get_total_timeouts() as u64),
+        ),
+        (
+            "requests_per_second",
+            FieldValue::Float(results.get_requests_per_second()),
+        ),
+        (
+            "failed_requests_per_second",
+            FieldValue::Float(results.get_failed_requests_per_second()),
+        ),
+        (
+            "average_response_time",
+            FieldValue::Float(results.get_average_response_time()),
+        ),
+        (
+            "min_response_time",
+            FieldValue::Float(results.get_min_response_time()),
+        ),
+        (
+            "max_response_time",
+            FieldValue::Float(results.get_max_response_time()),
+        ),
+        (
+            "average_failed_response_time",
+            FieldValue::Float(results.get_average_failed_response_time()),
+        ),
+        (
+            "total_bytes_received",
+            FieldValue::Integer(results.get_total_bytes_received()),
+        ),
+        (
+            "total_bytes_sent",
+            FieldValue::Integer(results.get_total_bytes_sent()),
+        ),
+        (
+            "average_bytes_received",
+            FieldValue::Float(results.get_average_bytes_received()),
+        ),
+        (
+            "average_bytes_sent",
+            FieldValue::Float(results.get_average_bytes_sent()),
+        ),
+    ];
+    fields
+        .into_iter()
+        .filter(|(_, value)| match value {
+            FieldValue::Integer(_) => true,
+            FieldValue::Float(value) => value.is_finite(),
+        })
+        .collect()
+}
+
+// sorted by type and name, the aggregated results last
+fn sorted_results(all_results: &AllResults) -> Vec<(&str, &str, &Results)> {
+    let mut results: Vec<(&str, &str, &Results)> = all_results
+        .get_endpoint_results()
+        .iter()
+        .map(|(endpoint_type_name, results)| {
+            (
+                endpoint_type_name.r#type.as_str(),
+                endpoint_type_name.name.as_str(),
+                results,
+            )
+        })
+        .collect();
+    results.sort_by_key(|(r#type, name, _)| (*r#type, *name));
+    results.push((
+        FILE_AGR_TYPE_NAME[0],
+        FILE_AGR_TYPE_NAME[1],
+        all_results.get_aggrigated_results(),
+    ));
+    results
+}
+
+// commas, equal signs and spaces are escaped in tags and field keys, the measurement does not escape equal signs
+fn escape_influx(value: &str, escape_equal_sign: bool) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if c == ',' || c == ' ' || (escape_equal_sign && c == '=') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+// one line per endpoint, tags with empty values are not allowed and skipped
+pub(crate) fn influx_line_protocol_string(
+    all_results: &AllResults,
+    influx_output: &InfluxOutput,
+    timestamp_in_millis: u128,
+) -> String {
+    let timestamp_in_nanos = timestamp_in_millis * 1_000_000;
+    let mut lines = String::new();
+    for (r#type, name, results) in sorted_results(all_results) {
+        lines.push_str(&escape_influx(&influx_output.measurement, false));
+        let endpoint_tags = [("type", r#type), ("name", name)];
+        let tags = endpoint_tags.into_iter().chain(
+            influx_output
+                .tags
+                .iter()
+                .map(|(name, value)| (name.as_str(), value.as_str())),
+        );
+        for (tag_name, tag_value) in tags {
+            if tag_value.is_empty() {
+                continue;
+            }
+            lines.push_str(&format!(
+                ",{}={}",
+                escape_influx(tag_name, true),
+                escape_influx(tag_value, true)
+            ));
+        }
+        let fields = fields(results)
+            .into_iter()
+            .map(|(field_name, value)| match value {
+                FieldValue::Integer(value) => format!("{}={}i", field_name, value),
+                FieldValue::Float(value) => format!("{}={}", field_name, value),
+            })
+            .collect::<Vec<_>>()
+            .join(",");
+        lines.push_str(&format!(" {} {}\n", fields, timestamp_in_nanos));
+    }
+    lines
+}
+
+// semicolons and whitespace would end the path or the tag
+fn sanitize_graphite(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| {
+            if c == ';' || c.is_whitespace() {
+                '_'
+            } else {
+                c
+            }
+        })
+        .collect()
+}
+
+// one line per endpoint and field, tags with empty values are not allowed and skipped
+pub(crate) fn graphite_plaintext_string(
+    all_results: &AllResults,
+    graphite_output: &GraphiteOutput,
+    timestamp_in_millis: u128,
+) -> String {
+    let timestamp_in_secs = timestamp_in_millis / 1000;
+    let mut lines = String::new();
+    for (r#type, name, results) in sorted_results(all_results) {
+        let endpoint_tags = [("type", r#type), ("name", name)];
+        let tags = endpoint_tags
+            .into_iter()
+            .chain(
+                graphite_output
+                    .tags
+                    .iter()
+                    .map(|(name, value)| (name.as_str(), value.as_str())),
+            )
+            .filter(|(_, tag_value)| !tag_value.is_empty())
+            .map(|(tag_name, tag_value)| {
+                format!(
+                    ";{}={}",
+                    sanitize_graphite(tag_name),
+                    sanitize_graphite(tag_value).trim_start_matches('~')
+                )
+            })
+            .collect::<String>();
+        for (field_name, value) in fields(results) {
+            let value = match value {
+                FieldValue::Integer(value) => value.to_string(),
+                FieldValue::Float(value) => value.to_string(),
+            };
+            lines.push_str(&format!(
+                "{}.{}{} {} {}\n",
+                sanitize_graphite(&graphite_output.prefix),
+                field_name,
+                tags,
+                value,
+                timestamp_in_secs
+            ));
+        }
+    }
+    lines
+}
+
+#[derive(Clone)]
+pub(crate) struct InfluxWriter {
+    influx_output: InfluxOutput,
+    file_writer: Option<Writer>,
+    client: Client<HttpConnector>,
+    uri: Option<Uri>,
+}
+
+impl InfluxWriter {
+    // the file is truncated, the lines of every update interval are appended
+    pub(crate) async fn new(influx_output: &InfluxOutput) -> Result<Self, LineWriterError> {
+        let file_writer = match &influx_output.file {
+            Some(file) => {
+                let writer = Writer::from_str(file).await?;
+                writer.write_all(b"").await?;
+                Some(writer)
+            }
+            None => None,
+        };
+        let uri = match &influx_output.url {
+            Some(url) => Some(url.parse()?),
+            None => None,
+        };
+        Ok(Self {
+            influx_output: influx_output.clone(),
+            file_writer,
+            client: Client::new(),
+            uri,
+        })
+    }
+
+    pub(crate) fn lines(&self, all_results: &AllResults, timestamp_in_millis: u128) -> String {
+        influx_line_protocol_string(all_results, &self.influx_output, timestamp_in_millis)
+    }
+
+    pub(crate) async fn write_file(&self, lines: &str) -> Result<(), LineWriterError> {
+        if let Some(file_writer) = &self.file_writer {
+            file_writer.append_all(lines.as_bytes()).await?;
+        }
+        Ok(())
+    }
+
+    pub(crate) async fn send(&self, lines: String) -> Result<(), LineWriterError> {
+        let uri = match &self.uri {
+            Some(uri) => uri.clone(),
+            None => return Ok(()),
+        };
+        let mut request = Request::builder().method(Method::POST).uri(uri);
+        if let Some(token) = &self.influx_output.token {
+            request = request.header(AUTHORIZATION, format!("Token {}", token));
+        }
+        let request = request.body(Body::from(lines))?;
+        let response = tokio::time::timeout(SEND_TIMEOUT, self.client.request(request))
+            .await
+            .map_err(|_| LineWriterError::Timeout(SEND_TIMEOUT))??;
+        if !response.status().is_success() {
+            return Err(LineWriterError::UnexpectedStatus(response.status()));
+        }
+        Ok(())
+    }
+}
+
+// connects for every update interval, so a restarted graphite server does not break the test
+#[derive(Clone)]
+pub(crate) struct GraphiteWriter {
+    graphite_output: GraphiteOutput,
+}
+
+impl GraphiteWriter {
+    pub(crate) fn new(graphite_output: &GraphiteOutput) -> Self {
+        Self {
+            graphite_output: graphite_output.clone(),
+        }
+    }
+
+    pub(crate) fn lines(&self, all_results: &AllResults, timestamp_in_millis: u128) -> String {
+        graphite_plaintext_string(all_results, &self.graphite_output, timestamp_in_millis)
+    }
+
+    pub(crate) async fn send(&self, lines: &str) -> Result<(), LineWriterError> {
+        tokio::time::timeout(SEND_TIMEOUT, async {
+            let mut stream = TcpStream::connect(&self.graphite_output.address).await?;
+            stream.write_all(lines.as_bytes()).await?;
+            stream.shutdown().await
+        })
+        .await
+        .map_err(|_| LineWriterError::Timeout(SEND_TIMEOUT))??;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::results::{EndpointTypeName, ResponseInfo};
+    use tokio::{io::AsyncReadExt, net::TcpListener};
+
+    fn all_results() -> AllResults {
+        let mut all_results = AllResults::default();
+        let endpoint_type_name = EndpointTypeName {
+            r#type: String::from("GET"),
+            name: String::from("/items list"),
+        };
+        all_results.add_success(&endpoint_type_name, None, 0.5, &ResponseInfo::new());
+        all_results.add_success(&endpoint_type_name, None, 1.5, &ResponseInfo::new());
+        all_results.add_failure(&endpoint_type_name, None, None, &ResponseInfo::new());
+        all_results.calculate_on_update_interval(&Duration::from_secs(2));
+        all_results
+    }
+
+    #[test]
+    fn influx_lines_are_escaped_and_tagged() {
+        let influx_output = InfluxOutput::default()
+            .tag(String::from("test name"), String::from("checkout,v2"))
+            .tag(String::from("run_id"), String::from(""));
+
+        let lines = influx_line_protocol_string(&all_results(), &influx_output, 1_000);
+        let lines: Vec<&str> = lines.lines().collect();
+
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with(
+            r"rocust,type=GET,name=/items\ list,test\ name=checkout\,v2 total_requests=3i,total_failed_requests=1i,"
+        ));
+        assert!(lines[0].contains(",average_response_time=1,"));
+        assert!(lines[0].ends_with(" 1000000000"));
+        // the aggregated type is empty and skipped like the empty run id
+        assert!(lines[1].starts_with(r"rocust,name=aggregated,test\ name=checkout\,v2 "));
+    }
+
+    #[test]
+    fn non_finite_fields_are_skipped() {
+        let mut all_results = AllResults::default();
+        let endpoint_type_name = EndpointTypeName {
+            r#type: String::from("GET"),
+            name: String::from("/"),
+        };
+        all_results.add_failure(&endpoint_type_name, None, None, &ResponseInfo::new());
+        all_results.calculate_on_update_interval(&Duration::from_secs(1));
+
+        let lines = influx_line_protocol_string(&all_results, &InfluxOutput::default(), 0);
+
+        assert!(!lines.contains("average_response_time"));
+        assert!(!lines.contains("NaN"));
+    }
+
+    #[test]
+    fn graphite_lines_are_tagged_series() {
+        let graphite_output = GraphiteOutput::new(String::from("localhost:2003"))
+            .tag(String::from("run_id"), String::from("42"));
+
+        let lines = graphite_plaintext_string(&all_results(), &graphite_output, 1_500);
+
+        assert!(lines.contains("rocust.total_requests;type=GET;name=/items_list;run_id=42 3 1\n"));
+        assert!(lines.contains("rocust.total_requests;name=aggregated;run_id=42 3 1\n"));
+    }
+
+    #[tokio::test]
+    async fn graphite_lines_are_sent_over_tcp() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let received = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut received = String::new();
+            stream.read_to_string(&mut received).await.unwrap();
+            received
+        });
+        let graphite_writer = GraphiteWriter::new(&GraphiteOutput::new(address));
+        let lines = graphite_writer.lines(&all_results(), 0);
+
+        graphite_writer.send(&lines).await.unwrap();
+
+        assert_eq!(received.await.unwrap(), lines);
+    }
+}
@@ -0,0 +1,383 @@
+use crate::{
+    messages::ResultMessage,
+    results::CSVError,
+    test::config::{RawLog, RawLogFormat},
+    utils,
+};
+use csv::WriterBuilder as CsvWriterBuilder;
+use rand::Rng;
+use serde::Serialize;
+use serde_json::Error as SerdeJsonError;
+use std::{
+    io::Error as IoError,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use thiserror::Error as ThisError;
+use tokio::{
+    fs::{self, File},
+    io::{AsyncWriteExt, BufWriter},
+    sync::mpsc,
+    task::JoinHandle,
+};
+
+const CSV_HEADERS: [&str; 9] = [
+    "timestamp",
+    "user_id",
+    "user_name",
+    "task_name",
+    "type",
+    "name",
+    "outcome",
+    "response_time",
+    "error",
+];
+
+// the lines are visible in the file while the test is running
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+const CHANNEL_CAPACITY: usize = 10_000;
+
+#[derive(Debug, ThisError)]
+pub enum RawLogError {
+    #[error("Error serializing json: {0}")]
+    Json(#[from] SerdeJsonError),
+    #[error("Error serializing csv: {0}")]
+    Csv(#[from] CSVError),
+}
+
+#[derive(Debug, Serialize)]
+struct RawLogRecord<'a> {
+    timestamp: u64,
+    user_id: u64,
+    user_name: &'a str,
+    task_name: Option<&'a str>,
+    r#type: &'a str,
+    name: &'a str,
+    outcome: &'static str,
+    response_time: Option<f64>,
+    error: Option<&'a str>,
+}
+
+impl<'a> RawLogRecord<'a> {
+    fn new(result_msg: &'a ResultMessage) -> Self {
+        let (user_info, task_info, endpoint_type_name, timestamp) = match result_msg {
+            ResultMessage::Success(msg) => (
+                &msg.user_info,
+                &msg.task_info,
+                &msg.endpoint_type_name,
+                msg.timestamp,
+            ),
+            ResultMessage::Failure(msg) => (
+                &msg.user_info,
+                &msg.task_info,
+                &msg.endpoint_type_name,
+                msg.timestamp,
+            ),
+            ResultMessage::Error(msg) => (
+                &msg.user_info,
+                &msg.task_info,
+                &msg.endpoint_type_name,
+                msg.timestamp,
+            ),
+        };
+        let (outcome, response_time, error) = match result_msg {
+            ResultMessage::Success(msg) => ("success", Some(msg.response_time), None),
+            ResultMessage::Failure(msg) => ("failure", msg.response_time, msg.reason.as_deref()),
+            ResultMessage::Error(msg) => ("error", None, Some(msg.error.as_str())),
+        };
+        Self {
+            timestamp,
+            user_id: user_info.id,
+            user_name: user_info.name,
+            task_name: task_info.as_ref().map(|task_info| task_info.name),
+            r#type: &endpoint_type_name.r#type,
+            name: &endpoint_type_name.name,
+            outcome,
+            response_time,
+            error,
+        }
+    }
+
+    fn line(&self, format: RawLogFormat) -> Result<String, RawLogError> {
+        match format {
+            RawLogFormat::Jsonl => {
+                let mut line = serde_json::to_string(self)?;
+                line.push('\n');
+                Ok(line)
+            }
+            RawLogFormat::Csv => Ok(self.csv_line()?),
+        }
+    }
+
+    fn csv_line(&self) -> Result<String, CSVError> {
+        let mut wtr = CsvWriterBuilder::new()
+            .has_headers(false)
+            .from_writer(vec![]);
+        wtr.serialize(self)?;
+        Ok(String::from_utf8(wtr.into_inner()?)?)
+    }
+}
+
+fn header(format: RawLogFormat) -> Result<String, CSVError> {
+    match format {
+        RawLogFormat::Jsonl => Ok(String::new()),
+        RawLogFormat::Csv => {
+            let mut wtr = CsvWriterBuilder::new().from_writer(vec![]);
+            wtr.write_record(CSV_HEADERS)?;
+            Ok(String::from_utf8(wtr.into_inner()?)?)
+        }
+    }
+}
+
+pub(crate) struct RawLogWriter {
+    format: RawLogFormat,
+    sample_rate: f64,
+    lines_tx: mpsc::Sender<String>,
+    writer_handle: JoinHandle<()>,
+}
+
+impl RawLogWriter {
+    pub(crate) async fn new(raw_log: &RawLog) -> Result<Self, IoError> {
+        let header = header(raw_log.format)
+            .map_err(|error| IoError::new(std::io::ErrorKind::InvalidData, error))?;
+        let rotating_file =
+            RotatingFile::create(PathBuf::from(&raw_log.file), header, raw_log.max_file_size)
+                .await?;
+
+        let (lines_tx, lines_rx) = mpsc::channel(CHANNEL_CAPACITY);
+        let writer_handle = tokio::spawn(run_writer(rotating_file, lines_rx));
+
+        Ok(Self {
+            format: raw_log.format,
+            sample_rate: raw_log.sample_rate.clamp(0.0, 1.0),
+            lines_tx,
+            writer_handle,
+        })
+    }
+
+    // waits if the file can not keep up, a raw log with gaps is of no use
+    pub(crate) async fn log_result(&self, result_msg: &ResultMessage) {
+        if self.sample_rate < 1.0 && !rand::thread_rng().gen_bool(self.sample_rate) {
+            return;
+        }
+        match RawLogRecord::new(result_msg).line(self.format) {
+            Ok(line) => {
+                if let Err(error) = self.lines_tx.send(line).await {
+                    tracing::error!(%error, "Raw log writer stopped");
+                }
+            }
+            Err(error) => {
+                tracing::error!(%error, "Failed to serialize raw log line");
+            }
+        }
+    }
+
+    // writes the remaining lines
+    pub(crate) async fn close(self) {
+        drop(self.lines_tx);
+        if let Err(error) = self.writer_handle.await {
+            tracing::error!(%error, "Error joining raw log writer");
+        }
+    }
+}
+
+struct RotatingFile {
+    path: PathBuf,
+    // written at the start of every file
+    header: String,
+    max_file_size: Option<u64>,
+    file: BufWriter<File>,
+    size: u64,
+    rotations: u64,
+}
+
+impl RotatingFile {
+    async fn create(
+        path: PathBuf,
+        header: String,
+        max_file_size: Option<u64>,
+    ) -> Result<Self, IoError> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        let file = Self::create_file(&path, &header).await?;
+        Ok(Self {
+            size: header.len() as u64,
+            path,
+            header,
+            max_file_size,
+            file,
+            rotations: 0,
+        })
+    }
+
+    async fn create_file(path: &Path, header: &str) -> Result<BufWriter<File>, IoError> {
+        let mut file = BufWriter::new(File::create(path).await?);
+        file.write_all(header.as_bytes()).await?;
+        Ok(file)
+    }
+
+    async fn write_line(&mut self, line: &str) -> Result<(), IoError> {
+        if let Some(max_file_size) = self.max_file_size {
+            // a file holds at least one line, even if it is larger than max_file_size
+            let has_lines = self.size > self.header.len() as u64;
+            if has_lines && self.size + line.len() as u64 > max_file_size {
+                self.rotate().await?;
+            }
+        }
+        self.file.write_all(line.as_bytes()).await?;
+        self.size += line.len() as u64;
+        Ok(())
+    }
+
+    async fn rotate(&mut self) -> Result<(), IoError> {
+        self.file.flush().await?;
+        self.rotations += 1;
+        fs::rename(&self.path, rotated_path(&self.path, self.rotations)).await?;
+        self.file = Self::create_file(&self.path, &self.header).await?;
+        self.size = self.header.len() as u64;
+        Ok(())
+    }
+}
+
+// raw.jsonl -> raw.1.jsonl
+fn rotated_path(path: &Path, rotation: u64) -> PathBuf {
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let file_name = match utils::get_extension_from_filename(path) {
+        Some(extension) => format!("{}.{}.{}", stem, rotation, extension),
+        None => format!("{}.{}", stem, rotation),
+    };
+    path.with_file_name(file_name)
+}
+
+async fn run_writer(mut rotating_file: RotatingFile, mut lines_rx: mpsc::Receiver<String>) {
+    let mut interval = tokio::time::interval(FLUSH_INTERVAL);
+    loop {
+        tokio::select! {
+            biased;
+            line = lines_rx.recv() => match line {
+                Some(line) => {
+                    if let Err(error) = rotating_file.write_line(&line).await {
+                        tracing::error!(%error, "Failed to write raw log line");
+                    }
+                }
+                None => break,
+            },
+            _ = interval.tick() => {
+                if let Err(error) = rotating_file.file.flush().await {
+                    tracing::error!(%error, "Failed to flush raw log file");
+                }
+            }
+        }
+    }
+    if let Err(error) = rotating_file.file.flush().await {
+        tracing::error!(%error, "Failed to flush raw log file");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messages::test_results;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("rocust_raw_log_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn lines_are_serialized() {
+        let results = test_results("/items");
+
+        assert_eq!(
+            RawLogRecord::new(&results[0])
+                .line(RawLogFormat::Jsonl)
+                .unwrap(),
+            "{\"timestamp\":1000,\"user_id\":3,\"user_name\":\"ShopUser\",\"task_name\":null,\"type\":\"GET\",\"name\":\"/items\",\"outcome\":\"success\",\"response_time\":0.25,\"error\":null}\n"
+        );
+        assert_eq!(
+            RawLogRecord::new(&results[1])
+                .line(RawLogFormat::Csv)
+                .unwrap(),
+            "1000,3,ShopUser,,GET,/items,failure,0.5,\"status 500, retry\"\n"
+        );
+        assert_eq!(
+            header(RawLogFormat::Csv).unwrap(),
+            "timestamp,user_id,user_name,task_name,type,name,outcome,response_time,error\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn every_result_is_written() {
+        let dir = temp_dir("all");
+        let file = dir.join("raw.csv");
+        let raw_log_writer = RawLogWriter::new(&RawLog::new(file.to_string_lossy().into_owned()))
+            .await
+            .unwrap();
+
+        for result_msg in test_results("/items") {
+            raw_log_writer.log_result(&result_msg).await;
+        }
+        raw_log_writer.close().await;
+
+        let content = std::fs::read_to_string(&file).unwrap();
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].starts_with("timestamp,"));
+        assert!(lines[3].ends_with(",error,,connection refused"));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn files_are_rotated_by_size() {
+        let dir = temp_dir("rotated");
+        let file = dir.join("raw.jsonl");
+        let raw_log = RawLog::new(file.to_string_lossy().into_owned()).max_file_size(300);
+        let raw_log_writer = RawLogWriter::new(&raw_log).await.unwrap();
+
+        for _ in 0..2 {
+            for result_msg in test_results("/items") {
+                raw_log_writer.log_result(&result_msg).await;
+            }
+        }
+        raw_log_writer.close().await;
+
+        // every line is larger than half of max_file_size
+        let mut line_count = 0;
+        for rotation in 1..=5 {
+            let content =
+                std::fs::read_to_string(dir.join(format!("raw.{}.jsonl", rotation))).unwrap();
+            assert_eq!(content.lines().count(), 1);
+            line_count += 1;
+        }
+        let content = std::fs::read_to_string(dir.join("raw.jsonl")).unwrap();
+        assert!(content.starts_with("{\"timestamp\":"));
+        assert!(content.contains("\"outcome\":\"error\""));
+        line_count += content.lines().count();
+        assert_eq!(line_count, 6);
+        assert!(!dir.join("raw.6.jsonl").exists());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn nothing_is_written_with_sample_rate_zero() {
+        let dir = temp_dir("sampled");
+        let file = dir.join("raw.jsonl");
+        let raw_log = RawLog::new(file.to_string_lossy().into_owned()).sample_rate(0.0);
+        let raw_log_writer = RawLogWriter::new(&raw_log).await.unwrap();
+
+        for result_msg in test_results("/items") {
+            raw_log_writer.log_result(&result_msg).await;
+        }
+        raw_log_writer.close().await;
+
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "");
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,343 @@
+use crate::{
+    fs::writer::{CreateError, WriteError, Writer},
+    results::{
+        status_codes_string, AllResults, CSVError, EndpointTypeName, Totals, FILE_AGR_TYPE_NAME,
+    },
+    utils,
+};
+use csv::WriterBuilder as CsvWriterBuilder;
+use serde::Serialize;
+use serde_json::Error as SerdeJsonError;
+use std::{
+    collections::HashMap,
+    path::Path,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use thiserror::Error as ThisError;
+
+const CSV_HEADERS: [&str; 26] = [
+    "timestamp",
+    "type",
+    "name",
+    "interval",
+    "interval_requests",
+    "interval_failed_requests",
+    "interval_errors",
+    "interval_requests_per_second",
+    "interval_failed_requests_per_second",
+    "interval_average_response_time",
+    "total_requests",
+    "total_failed_requests",
+    "total_errors",
+    "requests_per_second",
+    "failed_requests_per_second",
+    "average_response_time",
+    "min_response_time",
+    "median_response_time",
+    "percentile_90_response_time",
+    "percentile_95_response_time",
+    "percentile_99_response_time",
+    "max_response_time",
+    "average_failed_response_time",
+    "total_bytes_received",
+    "total_bytes_sent",
+    "status_codes",
+];
+
+#[derive(Debug, ThisError)]
+pub enum ResultsHistoryError {
+    #[error("Error serializing json: {0}")]
+    Json(#[from] SerdeJsonError),
+    #[error("Error serializing csv: {0}")]
+    Csv(#[from] CSVError),
+    #[error("Error creating results history file: {0}")]
+    Create(#[from] CreateError),
+    #[error("Error writing results history: {0}")]
+    Write(#[from] WriteError),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ResultsHistoryFormat {
+    Jsonl,
+    Csv,
+}
+
+// the interval stats are the difference to the previous update, the other stats are cumulative
+#[derive(Debug, Serialize)]
+struct ResultsHistoryRecord<'a> {
+    timestamp: u128,
+    r#type: &'a str,
+    name: &'a str,
+    interval: f64,
+    interval_requests: u32,
+    interval_failed_requests: u32,
+    interval_errors: u32,
+    interval_requests_per_second: f64,
+    interval_failed_requests_per_second: f64,
+    // of the successful requests of the interval
+    interval_average_response_time: f64,
+    total_requests: u32,
+    total_failed_requests: u32,
+    total_errors: u32,
+    requests_per_second: f64,
+    failed_requests_per_second: f64,
+    average_response_time: f64,
+    min_response_time: f64,
+    median_response_time: f64,
+    percentile_90_response_time: f64,
+    percentile_95_response_time: f64,
+    percentile_99_response_time: f64,
+    max_response_time: f64,
+    average_failed_response_time: f64,
+    total_bytes_received: u64,
+    total_bytes_sent: u64,
+    status_codes: String,
+}
+
+#[derive(Debug)]
+struct ResultsHistory {
+    format: ResultsHistoryFormat,
+    previous_elapsed_secs: f64,
+    previous_totals: HashMap<EndpointTypeName, Totals>,
+}
+
+#[derive(Clone)]
+pub(crate) struct ResultsHistoryWriter {
+    writer: Writer,
+    results_history: Arc<Mutex<ResultsHistory>>,
+}
+
+impl ResultsHistoryWriter {
+    // the file is truncated, the rows of every update interval are appended
+    pub(crate) async fn new(results_history_file: &str) -> Result<Self, ResultsHistoryError> {
+        let format = match utils::get_extension_from_filename(Path::new(results_history_file)) {
+            Some("jsonl") => ResultsHistoryFormat::Jsonl,
+            _ => ResultsHistoryFormat::Csv,
+        };
+        let writer = Writer::from_str(results_history_file).await?;
+        writer.write_all(header(format)?.as_bytes()).await?;
+        Ok(Self {
+            writer,
+            results_history: Arc::new(Mutex::new(ResultsHistory::new(format))),
+        })
+    }
+
+    pub(crate) async fn write(
+        &self,
+        all_results: &AllResults,
+        elapsed_time: &Duration,
+        timestamp: u128,
+    ) -> Result<(), ResultsHistoryError> {
+        let rows = self
+            .results_history
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .rows(all_results, elapsed_time, timestamp)?;
+        self.writer.append_all(rows.as_bytes()).await?;
+        Ok(())
+    }
+}
+
+impl ResultsHistory {
+    fn new(format: ResultsHistoryFormat) -> Self {
+        Self {
+            format,
+            previous_elapsed_secs: 0.0,
+            previous_totals: HashMap::new(),
+        }
+    }
+
+    // a row per endpoint and one for the aggregated results
+    fn rows(
+        &mut self,
+        all_results: &AllResults,
+        elapsed_time: &Duration,
+        timestamp: u128,
+    ) -> Result<String, ResultsHistoryError> {
+        let elapsed_secs = elapsed_time.as_secs_f64();
+        let interval = elapsed_secs - self.previous_elapsed_secs;
+        // nothing happened since the last update
+        if interval <= 0.0 {
+            return Ok(String::new());
+        }
+
+        let mut endpoint_results: Vec<_> = all_results.get_endpoint_results().iter().collect();
+        endpoint_results.sort_by(|(a, _), (b, _)| (&a.r#type, &a.name).cmp(&(&b.r#type, &b.name)));
+        let aggrigated_type_name = EndpointTypeName {
+            r#type: String::from(FILE_AGR_TYPE_NAME[0]),
+            name: String::from(FILE_AGR_TYPE_NAME[1]),
+        };
+        endpoint_results.push((&aggrigated_type_name, all_results.get_aggrigated_results()));
+
+        let mut rows = String::new();
+        for (endpoint_type_name, results) in endpoint_results {
+            let totals = Totals::new(elapsed_time, results);
+            // endpoints that are new since the last update had no results before
+            let previous = self
+                .previous_totals
+                .insert(endpoint_type_name.clone(), totals)
+                .unwrap_or_default();
+            let interval_requests = totals.requests - previous.requests;
+            let interval_failed_requests = totals.failed_requests - previous.failed_requests;
+            let interval_successful_requests = interval_requests - interval_failed_requests;
+            let interval_average_response_time = if interval_successful_requests > 0 {
+                (totals.response_time - previous.response_time)
+                    / interval_successful_requests as f64
+            } else {
+                0.0
+            };
+            let record = ResultsHistoryRecord {
+                timestamp,
+                r#type: &endpoint_type_name.r#type,
+                name: &endpoint_type_name.name,
+                interval,
+                interval_requests,
+                interval_failed_requests,
+                interval_errors: totals.errors - previous.errors,
+                interval_requests_per_second: interval_requests as f64 / interval,
+                interval_failed_requests_per_second: interval_failed_requests as f64 / interval,
+                interval_average_response_time,
+                total_requests: results.get_total_requests(),
+                total_failed_requests: results.get_total_failed_requests(),
+                total_errors: results.get_total_errors(),
+                requests_per_second: results.get_requests_per_second(),
+                failed_requests_per_second: results.get_failed_requests_per_second(),
+                average_response_time: results.get_average_response_time(),
+                min_response_time: results.get_min_response_time(),
+                median_response_time: results.get_median_response_time(),
+                percentile_90_response_time: results.get_percentile_90_response_time(),
+                percentile_95_response_time: results.get_percentile_95_response_time(),
+                percentile_99_response_time: results.get_percentile_99_response_time(),
+                max_response_time: results.get_max_response_time(),
+                average_failed_response_time: results.get_average_failed_response_time(),
+                total_bytes_received: results.get_total_bytes_received(),
+                total_bytes_sent: results.get_total_bytes_sent(),
+                status_codes: status_codes_string(results.get_status_codes(), " "),
+            };
+            rows.push_str(&record.line(self.format)?);
+        }
+        self.previous_elapsed_secs = elapsed_secs;
+        Ok(rows)
+    }
+}
+
+impl<'a> ResultsHistoryRecord<'a> {
+    fn line(&self, format: ResultsHistoryFormat) -> Result<String, ResultsHistoryError> {
+        match format {
+            ResultsHistoryFormat::Jsonl => {
+                let mut line = serde_json::to_string(self)?;
+                line.push('\n');
+                Ok(line)
+            }
+            ResultsHistoryFormat::Csv => Ok(self.csv_line()?),
+        }
+    }
+
+    fn csv_line(&self) -> Result<String, CSVError> {
+        let mut wtr = CsvWriterBuilder::new()
+            .has_headers(false)
+            .from_writer(vec![]);
+        wtr.serialize(self)?;
+        Ok(String::from_utf8(wtr.into_inner()?)?)
+    }
+}
+
+fn header(format: ResultsHistoryFormat) -> Result<String, CSVError> {
+    match format {
+        ResultsHistoryFormat::Jsonl => Ok(String::new()),
+        ResultsHistoryFormat::Csv => {
+            let mut wtr = CsvWriterBuilder::new().from_writer(vec![]);
+            wtr.write_record(CSV_HEADERS)?;
+            Ok(String::from_utf8(wtr.into_inner()?)?)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::results::ResponseInfo;
+
+    fn endpoint_type_name(name: &str) -> EndpointTypeName {
+        EndpointTypeName {
+            r#type: String::from("GET"),
+            name: String::from(name),
+        }
+    }
+
+    #[test]
+    fn csv_header_matches_rows() {
+        let mut all_results = AllResults::default();
+        all_results.add_success(
+            &endpoint_type_name("/items"),
+            None,
+            0.5,
+            &ResponseInfo::new().status_code(200),
+        );
+        all_results.calculate_on_update_interval(&Duration::from_secs(1));
+
+        let header = header(ResultsHistoryFormat::Csv).unwrap();
+        let rows = ResultsHistory::new(ResultsHistoryFormat::Csv)
+            .rows(&all_results, &Duration::from_secs(1), 1000)
+            .unwrap();
+
+        let columns = header.trim_end().split(',').count();
+        assert_eq!(columns, CSV_HEADERS.len());
+        for row in rows.lines() {
+            assert_eq!(row.split(',').count(), columns);
+        }
+        assert!(rows.starts_with("1000,GET,/items,1.0,1,0,0,1.0,0.0,0.5,1,0,0,1.0,"));
+        assert!(rows
+            .lines()
+            .last()
+            .unwrap()
+            .starts_with("1000,,aggregated,"));
+    }
+
+    #[test]
+    fn interval_stats_are_the_difference_to_the_previous_update() {
+        let mut results_history = ResultsHistory::new(ResultsHistoryFormat::Jsonl);
+        let mut all_results = AllResults::default();
+        let info = ResponseInfo::new();
+        all_results.add_success(&endpoint_type_name("/items"), None, 1.0, &info);
+        all_results.calculate_on_update_interval(&Duration::from_secs(2));
+        results_history
+            .rows(&all_results, &Duration::from_secs(2), 2000)
+            .unwrap();
+
+        all_results.add_success(&endpoint_type_name("/items"), None, 3.0, &info);
+        all_results.add_failure(&endpoint_type_name("/items"), None, None, &info);
+        all_results.add_error(&endpoint_type_name("/cart"), None, "refused");
+        all_results.calculate_on_update_interval(&Duration::from_secs(4));
+        let rows = results_history
+            .rows(&all_results, &Duration::from_secs(4), 4000)
+            .unwrap();
+
+        let rows: Vec<serde_json::Value> = rows
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(rows.len(), 3);
+        // sorted by type and name, aggregated last
+        assert_eq!(rows[0]["name"], "/cart");
+        assert_eq!(rows[0]["interval_errors"], 1);
+        let items = &rows[1];
+        assert_eq!(items["name"], "/items");
+        assert_eq!(items["interval"], 2.0);
+        assert_eq!(items["interval_requests"], 2);
+        assert_eq!(items["interval_failed_requests"], 1);
+        assert_eq!(items["interval_requests_per_second"], 1.0);
+        assert_eq!(items["interval_average_response_time"], 3.0);
+        assert_eq!(items["total_requests"], 3);
+        assert_eq!(items["average_response_time"], 2.0);
+        assert_eq!(rows[2]["name"], "aggregated");
+        assert_eq!(rows[2]["interval_requests"], 2);
+
+        // no rows without a new update
+        let rows = results_history
+            .rows(&all_results, &Duration::from_secs(4), 4000)
+            .unwrap();
+        assert!(rows.is_empty());
+    }
+}
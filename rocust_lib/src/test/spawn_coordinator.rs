@@ -1,13 +1,18 @@
 use super::controller::TestController;
 use crate::{
     events::EventsHandler,
+    futures::TimedExt,
     messages::MainMessage,
-    tasks::{AsyncTask, EventsTaskInfo},
-    test::user::{EventsUserInfo, UserController, UserStatus},
+    panics::{self, PanicSlot},
+    tasks::AsyncTask,
+    test::user::{EventsUserInfo, RestartPolicy, UserController, UserStatus},
     traits::{HasTask, PrioritisedRandom},
-    Context, Shared, Test, TestConfig, User,
+    Context, Shared, StartError, Test, TestConfig, User,
+};
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
 };
-use std::sync::Arc;
 use tokio::{
     sync::mpsc::{self, Receiver, Sender},
     task::JoinHandle,
@@ -147,6 +152,9 @@ where
     user_name: &'static str,
     tasks: Arc<Vec<AsyncTask<T>>>,
     between: (u64, u64),
+    restart_policy: RestartPolicy,
+    // the timeout of the user type or the test config, tasks can override it
+    task_timeout: Option<Duration>,
     user_count: u64,
     token: CancellationToken,
     test_config: TestConfig,
@@ -177,6 +185,10 @@ where
             user_name: T::get_name(),
             tasks: Arc::new(T::get_async_tasks()),
             between: T::get_between(),
+            restart_policy: T::get_restart_policy(),
+            task_timeout: T::get_task_timeout_in_millis()
+                .or(test_config.task_timeout_in_millis)
+                .map(Duration::from_millis),
             user_count,
             token,
             test_config,
@@ -216,28 +228,59 @@ where
 
                     // these are the tokens for the test
                     let test_token_for_user = token.clone();
+                    let test_token_for_supervisor = token.clone();
 
-                    // create a user token for the UserController
-                    let user_token = CancellationToken::new();
-                    let user_controller = UserController::new(user_token.clone());
                     let user_info = EventsUserInfo::new(id, self.user_name);
                     let events_handler = EventsHandler::new(user_info, results_tx.clone());
                     let supervisor_events_handler = events_handler.clone();
 
-                    // create the data for the user
-                    let user_context = Context::new(
-                        test_controller.clone(),
-                        events_handler.clone(),
-                        user_controller,
-                    );
-
+                    let test_controller = test_controller.clone();
                     let tasks = tasks.clone();
                     let shared = self.shared.clone();
-                    let supervisor = tokio::spawn(async move {
-                        let handle = tokio::spawn(async move {
-                            events_handler.add_user_spawned().await;
-                            let mut user = T::new(&test_config, &user_context, shared).await;
-                            user.on_start(&user_context).await;
+                    let between = self.between;
+                    let restart_policy = self.restart_policy.clone();
+                    let task_timeout = self.task_timeout;
+                    let panic_slot = Arc::new(Mutex::new(PanicSlot::default()));
+                    let supervisor_panic_slot = panic_slot.clone();
+
+                    // creates a fresh user with the same id, called again on every restart
+                    let spawn_user = move || {
+                        let test_config = test_config.clone();
+                        let test_token_for_user = test_token_for_user.clone();
+
+                        // create a user token for the UserController
+                        let user_token = CancellationToken::new();
+                        let user_controller = UserController::new(user_token.clone());
+                        let events_handler = events_handler.clone();
+
+                        // create the data for the user
+                        let user_context = Context::new(
+                            test_controller.clone(),
+                            events_handler.clone(),
+                            user_controller,
+                        )
+                        .base_url(test_config.base_url.clone());
+
+                        let tasks = tasks.clone();
+                        let shared = shared.clone();
+                        let panic_slot = panic_slot.clone();
+                        tokio::spawn(PanicSlot::scope(panic_slot.clone(), async move {
+                            let mut user = match start_user::<T>(
+                                &test_config,
+                                &user_context,
+                                shared,
+                                &test_token_for_user,
+                            )
+                            .await
+                            {
+                                Ok(user) => user,
+                                Err(error) => {
+                                    events_handler
+                                        .add_user_failed_to_start(error.to_string())
+                                        .await;
+                                    return UserStatus::FailedToStart;
+                                }
+                            };
 
                             if tasks.is_empty() {
                                 user.on_stop(&user_context).await;
@@ -247,50 +290,111 @@ where
                             loop {
                                 if let Some(task) = tasks.get_prioritised_random() {
                                     let task_call_and_sleep = async {
-                                        Test::sleep_between(self.between).await;
-                                        task.call(&mut user, &user_context).await;
+                                        Test::sleep_between(between).await;
+                                        PanicSlot::set_task_name(&panic_slot, Some(task.name));
+                                        user_context
+                                            .set_current_task(Some(task.get_events_task_info()));
+                                        let task_call = task.call(&mut user, &user_context).timed();
+                                        // the timed out task is cancelled by dropping its future
+                                        let result = match task.timeout.or(task_timeout) {
+                                            Some(timeout) => {
+                                                tokio::time::timeout(timeout, task_call)
+                                                    .await
+                                                    .map(|(_, duration)| duration)
+                                                    .map_err(|_| timeout)
+                                            }
+                                            None => Ok(task_call.await.1),
+                                        };
+                                        user_context.set_current_task(None);
+                                        PanicSlot::set_task_name(&panic_slot, None);
+                                        result
                                     };
 
                                     tokio::select! {
                                         _ = user_token.cancelled() => {
+                                            user_context.set_current_task(None);
                                             user.on_stop(&user_context).await;
                                             return UserStatus::Cancelled;
                                         }
                                         _ = test_token_for_user.cancelled() => {
+                                            user_context.set_current_task(None);
                                             user.on_stop(&user_context).await;
                                             return UserStatus::Finished;
                                         }
-                                        _ = task_call_and_sleep => {
-                                            events_handler
-                                            .add_task_executed(EventsTaskInfo { name: task.name }).await;
+                                        result = task_call_and_sleep => {
+                                            match result {
+                                                Ok(duration) => {
+                                                    events_handler
+                                                    .add_task_executed(task.get_events_task_info(), duration.as_secs_f64()).await;
+                                                }
+                                                Err(timeout) => {
+                                                    events_handler
+                                                    .add_task_timed_out(task.get_events_task_info(), timeout.as_millis() as u64).await;
+                                                }
+                                            }
                                         }
                                     }
                                 }
                             }
-                        });
+                        }))
+                    };
 
-                        match handle.await {
-                            Ok(status) => match status {
-                                UserStatus::Finished => {
-                                    supervisor_events_handler.add_user_finished().await;
-                                }
-                                UserStatus::Cancelled => {
-                                    supervisor_events_handler.add_user_self_stopped().await;
+                    let supervisor = tokio::spawn(async move {
+                        supervisor_events_handler.add_user_spawned().await;
+                        let mut restarts = 0;
+                        loop {
+                            match spawn_user().await {
+                                Ok(status) => {
+                                    match status {
+                                        UserStatus::Finished => {
+                                            supervisor_events_handler.add_user_finished().await;
+                                        }
+                                        UserStatus::Cancelled => {
+                                            supervisor_events_handler.add_user_self_stopped().await;
+                                        }
+                                        UserStatus::FailedToStart => {
+                                            // already reported by the user
+                                        }
+                                        _ => {
+                                            // well obviously unreachable
+                                        }
+                                    }
+                                    break;
                                 }
-                                _ => {
-                                    // well obviously unreachable
+                                Err(e) => {
+                                    if e.is_panic() {
+                                        let slot = PanicSlot::take(&supervisor_panic_slot);
+                                        supervisor_events_handler
+                                            .add_user_panicked(
+                                                panics::get_panic_message(e.into_panic()),
+                                                slot.task_name,
+                                                slot.backtrace,
+                                            )
+                                            .await;
+                                    } else {
+                                        // very unlikely
+                                        supervisor_events_handler.add_user_unknown_status().await;
+                                        break;
+                                    }
                                 }
-                            },
-                            Err(e) => {
-                                if e.is_panic() {
-                                    supervisor_events_handler
-                                        .add_user_panicked(e.to_string())
-                                        .await;
-                                } else {
-                                    // very unlikely
-                                    supervisor_events_handler.add_user_unknown_status().await;
+                            }
+
+                            // the user panicked, replace it with a fresh one if the policy allows it
+                            if !restart_policy.should_restart(restarts)
+                                || test_token_for_supervisor.is_cancelled()
+                            {
+                                break;
+                            }
+
+                            tokio::select! {
+                                _ = test_token_for_supervisor.cancelled() => {
+                                    break;
                                 }
-                            } // at this point we can decide what to do with the user, maybe restart it?
+                                _ = tokio::time::sleep(restart_policy.get_backoff()) => {}
+                            }
+
+                            restarts += 1;
+                            supervisor_events_handler.add_user_restarted().await;
                         }
                     });
                     supervisors.push((supervisor, id));
@@ -301,3 +405,470 @@ where
         })
     }
 }
+
+// creates the user and calls on_start, retrying according to the start failure policy
+async fn start_user<T>(
+    test_config: &TestConfig,
+    context: &Context,
+    shared: T::Shared,
+    token: &CancellationToken,
+) -> Result<T, StartError>
+where
+    T: HasTask + User,
+{
+    let policy = &test_config.start_failure_policy;
+    let mut attempt = 0;
+    loop {
+        let result = match T::new(test_config, context, shared.clone()).await {
+            Ok(mut user) => user.on_start(context).await.map(|_| user),
+            Err(error) => Err(error),
+        };
+
+        let error = match result {
+            Ok(user) => return Ok(user),
+            Err(error) => error,
+        };
+
+        if attempt >= policy.retries {
+            return Err(error);
+        }
+
+        let backoff = policy.get_backoff(attempt);
+        attempt += 1;
+        tracing::debug!(
+            user_name = T::get_name(),
+            user_id = context.get_id(),
+            %error,
+            attempt,
+            backoff = ?backoff,
+            "User failed to start, retrying"
+        );
+
+        tokio::select! {
+            _ = token.cancelled() => {
+                return Err(error);
+            }
+            _ = tokio::time::sleep(backoff) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::config::StartFailurePolicy;
+    use async_trait::async_trait;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    // counts the attempts to create the user, the first `failures` attempts fail
+    #[derive(Clone, Default)]
+    struct Attempts {
+        count: Arc<AtomicU32>,
+        failures: u32,
+    }
+
+    #[async_trait]
+    impl Shared for Attempts {
+        async fn new() -> Self {
+            Self::default()
+        }
+    }
+
+    struct FlakyUser;
+
+    impl HasTask for FlakyUser {
+        fn get_name() -> &'static str {
+            "FlakyUser"
+        }
+    }
+
+    #[async_trait]
+    impl User for FlakyUser {
+        type Shared = Attempts;
+
+        async fn new(
+            _test_config: &TestConfig,
+            _context: &Context,
+            shared: Self::Shared,
+        ) -> Result<Self, StartError> {
+            let attempt = shared.count.fetch_add(1, Ordering::SeqCst) + 1;
+            if attempt <= shared.failures {
+                return Err(format!("attempt {} failed", attempt).into());
+            }
+            Ok(FlakyUser)
+        }
+    }
+
+    // panics in User::new, so it never runs a task
+    struct PanickingUser;
+
+    impl HasTask for PanickingUser {
+        fn get_name() -> &'static str {
+            "PanickingUser"
+        }
+    }
+
+    #[async_trait]
+    impl User for PanickingUser {
+        type Shared = Attempts;
+
+        async fn new(
+            _test_config: &TestConfig,
+            _context: &Context,
+            shared: Self::Shared,
+        ) -> Result<Self, StartError> {
+            shared.count.fetch_add(1, Ordering::SeqCst);
+            panic!("could not start");
+        }
+
+        fn get_restart_policy() -> RestartPolicy {
+            RestartPolicy::UpTo {
+                max_restarts: 2,
+                backoff_in_millis: 1,
+            }
+        }
+    }
+
+    struct NeverRestartedUser;
+
+    impl HasTask for NeverRestartedUser {
+        fn get_name() -> &'static str {
+            "NeverRestartedUser"
+        }
+    }
+
+    #[async_trait]
+    impl User for NeverRestartedUser {
+        type Shared = Attempts;
+
+        async fn new(
+            _test_config: &TestConfig,
+            _context: &Context,
+            shared: Self::Shared,
+        ) -> Result<Self, StartError> {
+            shared.count.fetch_add(1, Ordering::SeqCst);
+            panic!("could not start");
+        }
+    }
+
+    // panics in its only task
+    struct ExplodingUser;
+
+    fn explode<'a>(
+        _user: &'a mut ExplodingUser,
+        _context: &'a Context,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = ()> + Send + 'a>> {
+        Box::pin(async { panic!("boom") })
+    }
+
+    impl HasTask for ExplodingUser {
+        fn get_async_tasks() -> Vec<AsyncTask<Self>> {
+            vec![AsyncTask::new(1, "explode", explode)]
+        }
+
+        fn get_name() -> &'static str {
+            "ExplodingUser"
+        }
+    }
+
+    #[async_trait]
+    impl User for ExplodingUser {
+        type Shared = Attempts;
+
+        async fn new(
+            _test_config: &TestConfig,
+            _context: &Context,
+            _shared: Self::Shared,
+        ) -> Result<Self, StartError> {
+            Ok(ExplodingUser)
+        }
+    }
+
+    // the first call of its task hangs and times out, the second one stops the user
+    struct SleepyUser {
+        calls: Arc<AtomicU32>,
+    }
+
+    fn sleep<'a>(
+        user: &'a mut SleepyUser,
+        context: &'a Context,
+    ) -> std::pin::Pin<Box<dyn std::future::Future<Output = ()> + Send + 'a>> {
+        Box::pin(async move {
+            if user.calls.fetch_add(1, Ordering::SeqCst) == 0 {
+                tokio::time::sleep(Duration::from_secs(60)).await;
+            } else {
+                context.stop().await;
+                std::future::pending::<()>().await;
+            }
+        })
+    }
+
+    impl HasTask for SleepyUser {
+        fn get_async_tasks() -> Vec<AsyncTask<Self>> {
+            vec![AsyncTask::new(1, "sleep", sleep).with_timeout(Duration::from_millis(10))]
+        }
+
+        fn get_name() -> &'static str {
+            "SleepyUser"
+        }
+
+        fn get_task_timeout_in_millis() -> Option<u64> {
+            Some(60_000)
+        }
+    }
+
+    #[async_trait]
+    impl User for SleepyUser {
+        type Shared = Attempts;
+
+        async fn new(
+            _test_config: &TestConfig,
+            _context: &Context,
+            shared: Self::Shared,
+        ) -> Result<Self, StartError> {
+            Ok(SleepyUser {
+                calls: shared.count,
+            })
+        }
+    }
+
+    fn kind(message: &MainMessage) -> &'static str {
+        match message {
+            MainMessage::ResultMessage(_) => "result",
+            MainMessage::UserSpawned(_) => "spawned",
+            MainMessage::UserFailedToStart(_) => "failed_to_start",
+            MainMessage::UserSelfStopped(_) => "self_stopped",
+            MainMessage::UserFinished(_) => "finished",
+            MainMessage::UserPanicked(_) => "panicked",
+            MainMessage::UserRestarted(_) => "restarted",
+            MainMessage::UserUnknownStatus(_) => "unknown_status",
+            MainMessage::TaskExecuted(_) => "task_executed",
+            MainMessage::TaskTimedOut(_) => "task_timed_out",
+            MainMessage::Check(_) => "check",
+            MainMessage::Metric(_) => "metric",
+        }
+    }
+
+    // spawns a single user and waits until its supervisor is done
+    async fn run_user<T>(shared: Attempts) -> Vec<MainMessage>
+    where
+        T: HasTask + User<Shared = Attempts>,
+    {
+        let (results_tx, mut results_rx) = mpsc::channel(100);
+        let (spawn_tx, spawn_rx) = mpsc::channel(1);
+        let token = CancellationToken::new();
+        let spawner = Spawner::<T, Attempts>::new(
+            1,
+            token.clone(),
+            TestConfig::default(),
+            Arc::new(TestController::new(token)),
+            results_tx,
+            0,
+            shared,
+            spawn_rx,
+        );
+        let spawner_handle = spawner.run();
+        spawn_tx.send(1).await.unwrap();
+        drop(spawn_tx);
+        for (supervisor, _) in spawner_handle.await.unwrap() {
+            supervisor.await.unwrap();
+        }
+
+        let mut messages = Vec::new();
+        while let Ok(message) = results_rx.try_recv() {
+            messages.push(message);
+        }
+        messages
+    }
+
+    fn task_timeout<T>(test_config: TestConfig) -> Option<Duration>
+    where
+        T: HasTask + User<Shared = Attempts>,
+    {
+        let (results_tx, _) = mpsc::channel(1);
+        let (_, spawn_rx) = mpsc::channel(1);
+        let token = CancellationToken::new();
+        Spawner::<T, Attempts>::new(
+            1,
+            token.clone(),
+            test_config,
+            Arc::new(TestController::new(token)),
+            results_tx,
+            0,
+            attempts(0),
+            spawn_rx,
+        )
+        .task_timeout
+    }
+
+    fn context() -> Context {
+        let (results_tx, _) = mpsc::channel(1);
+        Context::new(
+            Arc::new(TestController::new(CancellationToken::new())),
+            EventsHandler::new(EventsUserInfo::new(0, FlakyUser::get_name()), results_tx),
+            UserController::new(CancellationToken::new()),
+        )
+    }
+
+    fn attempts(failures: u32) -> Attempts {
+        Attempts {
+            count: Arc::new(AtomicU32::new(0)),
+            failures,
+        }
+    }
+
+    async fn start(
+        start_failure_policy: StartFailurePolicy,
+        shared: &Attempts,
+        token: &CancellationToken,
+    ) -> Result<FlakyUser, StartError> {
+        let test_config = TestConfig::default().start_failure_policy(start_failure_policy);
+        start_user::<FlakyUser>(&test_config, &context(), shared.clone(), token).await
+    }
+
+    #[tokio::test]
+    async fn skip_does_not_retry() {
+        let shared = attempts(1);
+
+        let result = start(
+            StartFailurePolicy::skip(),
+            &shared,
+            &CancellationToken::new(),
+        )
+        .await;
+
+        assert_eq!(result.err().unwrap().to_string(), "attempt 1 failed");
+        assert_eq!(shared.count.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn retries_until_the_user_starts() {
+        let shared = attempts(2);
+
+        let result = start(
+            StartFailurePolicy::retry(3, 1),
+            &shared,
+            &CancellationToken::new(),
+        )
+        .await;
+
+        assert!(result.is_ok());
+        assert_eq!(shared.count.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_the_retries() {
+        let shared = attempts(10);
+
+        let result = start(
+            StartFailurePolicy::retry(2, 1),
+            &shared,
+            &CancellationToken::new(),
+        )
+        .await;
+
+        // the error of the last attempt is reported
+        assert_eq!(result.err().unwrap().to_string(), "attempt 3 failed");
+        assert_eq!(shared.count.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn stopping_the_test_stops_the_backoff() {
+        let shared = attempts(10);
+        let token = CancellationToken::new();
+        token.cancel();
+
+        let result = tokio::time::timeout(
+            Duration::from_secs(5),
+            start(StartFailurePolicy::retry(5, 60_000), &shared, &token),
+        )
+        .await
+        .expect("the backoff was not cancelled");
+
+        assert!(result.is_err());
+        assert_eq!(shared.count.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn panicked_user_is_restarted_up_to_the_limit() {
+        let shared = attempts(0);
+
+        let messages = run_user::<PanickingUser>(shared.clone()).await;
+
+        let kinds: Vec<_> = messages.iter().map(kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                "spawned",
+                "panicked",
+                "restarted",
+                "panicked",
+                "restarted",
+                "panicked"
+            ]
+        );
+        // a fresh user is created on every restart
+        assert_eq!(shared.count.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn panicked_user_is_not_restarted_by_default() {
+        let shared = attempts(0);
+
+        let messages = run_user::<NeverRestartedUser>(shared.clone()).await;
+
+        let kinds: Vec<_> = messages.iter().map(kind).collect();
+        assert_eq!(kinds, vec!["spawned", "panicked"]);
+        assert_eq!(shared.count.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn panic_is_reported_with_the_task_name() {
+        let messages = run_user::<ExplodingUser>(attempts(0)).await;
+
+        let kinds: Vec<_> = messages.iter().map(kind).collect();
+        assert_eq!(kinds, vec!["spawned", "panicked"]);
+        if let MainMessage::UserPanicked(message) = &messages[1] {
+            assert_eq!(message.message, "boom");
+            assert_eq!(message.task_name, Some("explode"));
+            assert_eq!(message.user_info.name, "ExplodingUser");
+        }
+    }
+
+    #[tokio::test]
+    async fn timed_out_task_is_cancelled_and_reported() {
+        let shared = attempts(0);
+
+        let messages = tokio::time::timeout(
+            Duration::from_secs(5),
+            run_user::<SleepyUser>(shared.clone()),
+        )
+        .await
+        .expect("the task did not time out");
+
+        let kinds: Vec<_> = messages.iter().map(kind).collect();
+        assert_eq!(kinds, vec!["spawned", "task_timed_out", "self_stopped"]);
+        if let MainMessage::TaskTimedOut(message) = &messages[1] {
+            // the task timeout overrides the timeout of the user type
+            assert_eq!(message.timeout_in_millis, 10);
+            assert_eq!(message.task_info.name, "sleep");
+        }
+        // the user keeps running tasks after a timeout
+        assert_eq!(shared.count.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn user_type_timeout_overrides_the_test_config() {
+        let test_config = TestConfig::default().task_timeout_in_millis(1000);
+
+        assert_eq!(
+            task_timeout::<FlakyUser>(test_config.clone()),
+            Some(Duration::from_millis(1000))
+        );
+        assert_eq!(
+            task_timeout::<SleepyUser>(test_config),
+            Some(Duration::from_secs(60))
+        );
+        assert_eq!(task_timeout::<FlakyUser>(TestConfig::default()), None);
+    }
+}
@@ -0,0 +1,294 @@
+use crate::{messages::ResultMessage, test::config::StatsdOutput};
+use rand::Rng;
+use std::{io::Error as IoError, net::SocketAddr, time::Duration};
+use tokio::{net::UdpSocket, sync::mpsc, task::JoinHandle};
+
+// fits into a single ethernet frame
+const MAX_DATAGRAM_SIZE: usize = 1432;
+
+const FLUSH_INTERVAL: Duration = Duration::from_millis(100);
+
+// the main loop never waits for the socket, packets are dropped if it can not keep up
+const CHANNEL_CAPACITY: usize = 10_000;
+
+pub(crate) struct StatsdSender {
+    prefix: String,
+    sample_rate: f64,
+    dogstatsd_tags: bool,
+    // rendered once, appended to the tags of every result
+    constant_tags: String,
+    packets_tx: mpsc::Sender<String>,
+    batcher_handle: JoinHandle<()>,
+}
+
+impl StatsdSender {
+    pub(crate) async fn new(statsd_output: &StatsdOutput) -> Result<Self, IoError> {
+        let addr = tokio::net::lookup_host(&statsd_output.address)
+            .await?
+            .next()
+            .ok_or_else(|| {
+                IoError::new(
+                    std::io::ErrorKind::NotFound,
+                    format!("Could not resolve {}", statsd_output.address),
+                )
+            })?;
+        let local_addr: SocketAddr = if addr.is_ipv4() {
+            ([0, 0, 0, 0], 0).into()
+        } else {
+            ([0u16; 8], 0).into()
+        };
+        let socket = UdpSocket::bind(local_addr).await?;
+        socket.connect(addr).await?;
+
+        let (packets_tx, packets_rx) = mpsc::channel(CHANNEL_CAPACITY);
+        let batcher_handle = tokio::spawn(run_batcher(socket, packets_rx));
+
+        let constant_tags = statsd_output
+            .tags
+            .iter()
+            .map(|(name, value)| {
+                format!(
+                    ",{}:{}",
+                    sanitize_tag(name, true),
+                    sanitize_tag(value, false)
+                )
+            })
+            .collect();
+
+        Ok(Self {
+            prefix: sanitize_metric_name(&statsd_output.prefix),
+            sample_rate: statsd_output.sample_rate.clamp(0.0, 1.0),
+            dogstatsd_tags: statsd_output.dogstatsd_tags,
+            constant_tags,
+            packets_tx,
+            batcher_handle,
+        })
+    }
+
+    pub(crate) fn send_result(&self, result_msg: &ResultMessage) {
+        // decided before formatting, so unsampled results cost nothing
+        if self.sample_rate < 1.0 && !rand::thread_rng().gen_bool(self.sample_rate) {
+            return;
+        }
+        if let Err(error) = self.packets_tx.try_send(self.packets(result_msg)) {
+            tracing::debug!(%error, "Dropping statsd packets");
+        }
+    }
+
+    // sends the remaining packets
+    pub(crate) async fn close(self) {
+        drop(self.packets_tx);
+        if let Err(error) = self.batcher_handle.await {
+            tracing::error!(%error, "Error joining statsd sender");
+        }
+    }
+
+    fn packets(&self, result_msg: &ResultMessage) -> String {
+        let (user_info, task_info, endpoint_type_name) = match result_msg {
+            ResultMessage::Success(msg) => {
+                (&msg.user_info, &msg.task_info, &msg.endpoint_type_name)
+            }
+            ResultMessage::Failure(msg) => {
+                (&msg.user_info, &msg.task_info, &msg.endpoint_type_name)
+            }
+            ResultMessage::Error(msg) => (&msg.user_info, &msg.task_info, &msg.endpoint_type_name),
+        };
+
+        let mut suffix = String::new();
+        if self.sample_rate < 1.0 {
+            suffix.push_str(&format!("|@{}", self.sample_rate));
+        }
+        if self.dogstatsd_tags {
+            suffix.push_str(&format!(
+                "|#user_name:{},endpoint_type:{},endpoint_name:{}",
+                sanitize_tag(user_info.name, false),
+                sanitize_tag(&endpoint_type_name.r#type, false),
+                sanitize_tag(&endpoint_type_name.name, false),
+            ));
+            if let Some(task_info) = task_info {
+                suffix.push_str(&format!(
+                    ",task_name:{}",
+                    sanitize_tag(task_info.name, false)
+                ));
+            }
+            suffix.push_str(&self.constant_tags);
+        }
+
+        let packet = |name: &str, value: String, metric_type: &str| {
+            format!(
+                "{}.{}:{}|{}{}",
+                self.prefix, name, value, metric_type, suffix
+            )
+        };
+        let mut packets = vec![];
+        match result_msg {
+            ResultMessage::Success(msg) => {
+                packets.push(packet("response_time", millis(msg.response_time), "ms"));
+                packets.push(packet("requests", String::from("1"), "c"));
+            }
+            ResultMessage::Failure(msg) => {
+                if let Some(response_time) = msg.response_time {
+                    packets.push(packet("failed_response_time", millis(response_time), "ms"));
+                }
+                packets.push(packet("requests", String::from("1"), "c"));
+                packets.push(packet("failures", String::from("1"), "c"));
+            }
+            ResultMessage::Error(_) => {
+                packets.push(packet("errors", String::from("1"), "c"));
+            }
+        }
+        packets.join("\n")
+    }
+}
+
+// response times are given in seconds, statsd timings in milliseconds
+fn millis(seconds: f64) -> String {
+    format!("{}", seconds * 1000.0)
+}
+
+fn sanitize_metric_name(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| match c {
+            ':' | '|' | '@' | '#' | ',' => '_',
+            c if c.is_whitespace() => '_',
+            c => c,
+        })
+        .collect()
+}
+
+// tag values may contain ':', tag names may not
+fn sanitize_tag(value: &str, is_name: bool) -> String {
+    value
+        .chars()
+        .map(|c| match c {
+            '|' | '#' | ',' | '@' => '_',
+            ':' if is_name => '_',
+            c if c.is_whitespace() => '_',
+            c => c,
+        })
+        .collect()
+}
+
+// packets of multiple results are sent in one datagram, flushed when full or every FLUSH_INTERVAL
+async fn run_batcher(socket: UdpSocket, mut packets_rx: mpsc::Receiver<String>) {
+    let mut datagram = String::with_capacity(MAX_DATAGRAM_SIZE);
+    let mut interval = tokio::time::interval(FLUSH_INTERVAL);
+    loop {
+        tokio::select! {
+            // queued packets first, a full datagram is sent anyway
+            biased;
+            packets = packets_rx.recv() => match packets {
+                Some(packets) => {
+                    if !datagram.is_empty() && datagram.len() + 1 + packets.len() > MAX_DATAGRAM_SIZE {
+                        send_datagram(&socket, &mut datagram).await;
+                    }
+                    if !datagram.is_empty() {
+                        datagram.push('\n');
+                    }
+                    datagram.push_str(&packets);
+                }
+                None => {
+                    send_datagram(&socket, &mut datagram).await;
+                    break;
+                }
+            },
+            _ = interval.tick() => {
+                send_datagram(&socket, &mut datagram).await;
+            }
+        }
+    }
+}
+
+async fn send_datagram(socket: &UdpSocket, datagram: &mut String) {
+    if datagram.is_empty() {
+        return;
+    }
+    // nobody listening is not an error worth spamming the logs every flush
+    if let Err(error) = socket.send(datagram.as_bytes()).await {
+        tracing::debug!(%error, "Error sending statsd datagram");
+    }
+    datagram.clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messages::test_results;
+
+    async fn stand_in() -> (UdpSocket, String) {
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let address = socket.local_addr().unwrap().to_string();
+        (socket, address)
+    }
+
+    #[tokio::test]
+    async fn packets_are_tagged_and_sampled() {
+        let (_socket, address) = stand_in().await;
+        let statsd_output = StatsdOutput::new(address)
+            .sample_rate(0.5)
+            .tag(String::from("run_id"), String::from("42"));
+        let statsd_sender = StatsdSender::new(&statsd_output).await.unwrap();
+
+        let packets = statsd_sender.packets(&test_results("/items, all")[0]);
+
+        assert_eq!(
+            packets,
+            "rocust.response_time:250|ms|@0.5|#user_name:ShopUser,endpoint_type:GET,endpoint_name:/items__all,run_id:42\n\
+             rocust.requests:1|c|@0.5|#user_name:ShopUser,endpoint_type:GET,endpoint_name:/items__all,run_id:42"
+        );
+    }
+
+    #[tokio::test]
+    async fn packets_without_tags() {
+        let (_socket, address) = stand_in().await;
+        let statsd_output = StatsdOutput::new(address).dogstatsd_tags(false);
+        let statsd_sender = StatsdSender::new(&statsd_output).await.unwrap();
+
+        assert_eq!(
+            statsd_sender.packets(&test_results("/items, all")[1]),
+            "rocust.failed_response_time:500|ms\nrocust.requests:1|c\nrocust.failures:1|c"
+        );
+        assert_eq!(
+            statsd_sender.packets(&test_results("/items, all")[2]),
+            "rocust.errors:1|c"
+        );
+    }
+
+    #[tokio::test]
+    async fn results_are_batched_into_one_datagram() {
+        let (socket, address) = stand_in().await;
+        let statsd_sender = StatsdSender::new(&StatsdOutput::new(address))
+            .await
+            .unwrap();
+
+        for result_msg in test_results("/items, all") {
+            statsd_sender.send_result(&result_msg);
+        }
+        statsd_sender.close().await;
+
+        let mut buffer = [0; MAX_DATAGRAM_SIZE];
+        let size = socket.recv(&mut buffer).await.unwrap();
+        let datagram = std::str::from_utf8(&buffer[..size]).unwrap();
+        assert_eq!(datagram.lines().count(), 6);
+        assert!(datagram.starts_with("rocust.response_time:250|ms|#"));
+    }
+
+    #[tokio::test]
+    async fn nothing_is_sent_with_sample_rate_zero() {
+        let (socket, address) = stand_in().await;
+        let statsd_sender = StatsdSender::new(&StatsdOutput::new(address).sample_rate(0.0))
+            .await
+            .unwrap();
+
+        for result_msg in test_results("/items, all") {
+            statsd_sender.send_result(&result_msg);
+        }
+        statsd_sender.close().await;
+
+        let mut buffer = [0; MAX_DATAGRAM_SIZE];
+        let received =
+            tokio::time::timeout(Duration::from_millis(200), socket.recv(&mut buffer)).await;
+        assert!(received.is_err());
+    }
+}
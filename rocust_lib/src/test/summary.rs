@@ -0,0 +1,113 @@
+use crate::{
+    checks::{AllChecks, CheckThresholdResult, SerCheckStats},
+    custom_metrics::{AllMetrics, SerMetric},
+    errors::{AllErrors, SerErrorStats},
+    panics::{AllPanics, PanicStats},
+    task_stats::{AllTaskStats, SerTaskStats},
+    test::user::{SerUserStats, UserStatsCollection},
+};
+use serde::Serialize;
+use serde_json::Error as SerdeJsonError;
+use serde_yaml::Error as SerdeYamlError;
+use thiserror::Error as ThisError;
+
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct Summary {
+    user_stats: Vec<SerUserStats>,
+    panics: Vec<PanicStats>,
+    errors: Vec<SerErrorStats>,
+    failures: Vec<SerErrorStats>,
+    checks: Vec<SerCheckStats>,
+    check_thresholds: Vec<CheckThresholdResult>,
+    metrics: Vec<SerMetric>,
+    task_stats: Vec<SerTaskStats>,
+}
+
+#[derive(Debug, ThisError)]
+pub enum SummaryError {
+    #[error("Error converting to json: {0}")]
+    SerdeJsonError(#[from] SerdeJsonError),
+
+    #[error("Error converting to yaml: {0}")]
+    SerdeYamlError(#[from] SerdeYamlError),
+}
+
+impl Summary {
+    pub(crate) fn new(
+        user_stats_collection: &UserStatsCollection,
+        all_panics: &AllPanics,
+        all_errors: &AllErrors,
+        all_failures: &AllErrors,
+        all_checks: &AllChecks,
+        all_metrics: &AllMetrics,
+        all_task_stats: &AllTaskStats,
+    ) -> Self {
+        Self {
+            user_stats: user_stats_collection.ser_user_stats(),
+            panics: all_panics.ser_panics(),
+            errors: all_errors.ser_errors(),
+            failures: all_failures.ser_errors(),
+            checks: all_checks.ser_checks(),
+            check_thresholds: Vec::new(),
+            metrics: all_metrics.ser_metrics(),
+            task_stats: all_task_stats.ser_task_stats(),
+        }
+    }
+
+    pub(crate) fn with_check_thresholds(self, check_thresholds: Vec<CheckThresholdResult>) -> Self {
+        Self {
+            check_thresholds,
+            ..self
+        }
+    }
+
+    pub(crate) fn json_string(&self) -> Result<String, SummaryError> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    pub(crate) fn yaml_string(&self) -> Result<String, SummaryError> {
+        Ok(serde_yaml::to_string(self)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summary_has_a_section_per_kind() {
+        let mut all_panics = AllPanics::default();
+        all_panics.add_panic("boom", "ShopUser", Some("checkout"), None);
+
+        let summary = Summary::new(
+            &UserStatsCollection::default(),
+            &all_panics,
+            &AllErrors::default(),
+            &AllErrors::default(),
+            &AllChecks::default(),
+            &AllMetrics::default(),
+            &AllTaskStats::default(),
+        );
+        let json: serde_json::Value =
+            serde_json::from_str(&summary.json_string().unwrap()).unwrap();
+
+        let mut sections: Vec<_> = json.as_object().unwrap().keys().cloned().collect();
+        sections.sort();
+        assert_eq!(
+            sections,
+            vec![
+                "check_thresholds",
+                "checks",
+                "errors",
+                "failures",
+                "metrics",
+                "panics",
+                "task_stats",
+                "user_stats"
+            ]
+        );
+        assert_eq!(json["panics"][0]["message"], "boom");
+        assert_eq!(json["panics"][0]["task_names"][0], "checkout");
+        assert!(json["user_stats"].as_array().unwrap().is_empty());
+    }
+}
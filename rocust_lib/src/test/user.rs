@@ -2,13 +2,10 @@ pub mod context;
 
 use crate::{
     futures::BreakPoint,
-    results::{AllResults, EndpointTypeName, SerAllResults},
+    results::{AllResults, EndpointTypeName, ResponseInfo, SerAllResults},
 };
 use serde::Serialize;
-use serde_json::Error as SerdeJsonError;
-use serde_yaml::Error as SerdeYamlError;
 use std::{collections::HashMap, time::Duration};
-use thiserror::Error as ThisError;
 use tokio_util::sync::CancellationToken;
 
 #[derive(Debug, Clone, Serialize)]
@@ -21,15 +18,6 @@ pub struct UserStatsCollection {
     user_stats_map: HashMap<u64, UserStats>,
 }
 
-#[derive(Debug, ThisError)]
-pub enum UserStatsCollectionError {
-    #[error("Error converting to json: {0}")]
-    SerdeJsonError(#[from] SerdeJsonError),
-
-    #[error("Error converting to yaml: {0}")]
-    SerdeYamlError(#[from] SerdeYamlError),
-}
-
 impl UserStatsCollection {
     pub fn new() -> Self {
         Self {
@@ -41,12 +29,17 @@ impl UserStatsCollection {
         &mut self,
         user_id: &u64,
         endpoint_type_name: &EndpointTypeName,
+        task_name: Option<&'static str>,
         response_time: f64,
+        response_info: &ResponseInfo,
     ) {
         if let Some(user_stats) = self.user_stats_map.get_mut(user_id) {
-            user_stats
-                .all_results
-                .add_success(endpoint_type_name, response_time);
+            user_stats.all_results.add_success(
+                endpoint_type_name,
+                task_name,
+                response_time,
+                response_info,
+            );
         }
     }
 
@@ -60,9 +53,21 @@ impl UserStatsCollection {
         );
     }
 
-    pub(crate) fn add_failure(&mut self, user_id: &u64, endpoint_type_name: &EndpointTypeName) {
+    pub(crate) fn add_failure(
+        &mut self,
+        user_id: &u64,
+        endpoint_type_name: &EndpointTypeName,
+        task_name: Option<&'static str>,
+        response_time: Option<f64>,
+        response_info: &ResponseInfo,
+    ) {
         if let Some(user_stats) = self.user_stats_map.get_mut(user_id) {
-            user_stats.all_results.add_failure(endpoint_type_name);
+            user_stats.all_results.add_failure(
+                endpoint_type_name,
+                task_name,
+                response_time,
+                response_info,
+            );
         }
     }
 
@@ -70,10 +75,26 @@ impl UserStatsCollection {
         &mut self,
         user_id: &u64,
         endpoint_type_name: &EndpointTypeName,
+        task_name: Option<&'static str>,
         error: &str,
     ) {
         if let Some(user_stats) = self.user_stats_map.get_mut(user_id) {
-            user_stats.all_results.add_error(endpoint_type_name, error);
+            user_stats
+                .all_results
+                .add_error(endpoint_type_name, task_name, error);
+        }
+    }
+
+    pub(crate) fn add_timeout(
+        &mut self,
+        user_id: &u64,
+        endpoint_type_name: &EndpointTypeName,
+        task_name: Option<&'static str>,
+    ) {
+        if let Some(user_stats) = self.user_stats_map.get_mut(user_id) {
+            user_stats
+                .all_results
+                .add_timeout(endpoint_type_name, task_name);
         }
     }
 
@@ -89,24 +110,42 @@ impl UserStatsCollection {
         }
     }
 
+    pub(crate) fn add_panic(&mut self, user_id: &u64, message: &str) {
+        if let Some(user_stats) = self.user_stats_map.get_mut(user_id) {
+            *user_stats
+                .user_info
+                .panics
+                .entry(message.to_string())
+                .or_insert(0) += 1;
+        }
+    }
+
+    pub(crate) fn increment_total_restarts(&mut self, user_id: &u64) {
+        if let Some(user_stats) = self.user_stats_map.get_mut(user_id) {
+            user_stats.user_info.total_restarts += 1;
+        }
+    }
+
     pub(crate) fn increment_total_tasks(&mut self, user_id: &u64) {
         if let Some(user_stats) = self.user_stats_map.get_mut(user_id) {
             user_stats.user_info.total_tasks += 1;
         }
     }
 
-    pub(crate) fn json_string(&self) -> Result<String, UserStatsCollectionError> {
-        let ser_user_stats_collection: SerUserStatsCollection = self.clone().into();
-        Ok(serde_json::to_string(
-            &ser_user_stats_collection.user_stats_vec,
-        )?)
+    // ordered by id
+    pub(crate) fn get_user_stats_infos(&self) -> Vec<&UserStatsInfo> {
+        let mut user_stats_infos: Vec<&UserStatsInfo> = self
+            .user_stats_map
+            .values()
+            .map(|user_stats| &user_stats.user_info)
+            .collect();
+        user_stats_infos.sort_by_key(|user_info| user_info.id);
+        user_stats_infos
     }
 
-    pub(crate) fn yaml_string(&self) -> Result<String, UserStatsCollectionError> {
+    pub(crate) fn ser_user_stats(&self) -> Vec<SerUserStats> {
         let ser_user_stats_collection: SerUserStatsCollection = self.clone().into();
-        Ok(serde_yaml::to_string(
-            &ser_user_stats_collection.user_stats_vec,
-        )?)
+        ser_user_stats_collection.user_stats_vec
     }
 }
 
@@ -128,7 +167,7 @@ impl From<UserStatsCollection> for SerUserStatsCollection {
 }
 
 #[derive(Debug, Clone, Serialize)]
-struct SerUserStats {
+pub(crate) struct SerUserStats {
     user_info: UserStatsInfo,
     all_results: SerAllResults,
 }
@@ -161,6 +200,7 @@ impl From<UserStats> for SerUserStats {
 pub enum UserStatus {
     Finished,
     Spawned,
+    FailedToStart,
     Panicked,
     Cancelled, // cancelled by himself
     Unknown,
@@ -172,6 +212,9 @@ pub struct UserStatsInfo {
     pub name: &'static str,
     pub status: UserStatus,
     pub total_tasks: u64,
+    pub total_restarts: u64,
+    // panic messages with occurrences
+    pub panics: HashMap<String, u64>,
 }
 
 impl UserStatsInfo {
@@ -181,6 +224,45 @@ impl UserStatsInfo {
             name,
             status: UserStatus::Spawned,
             total_tasks: 0,
+            total_restarts: 0,
+            panics: HashMap::new(),
+        }
+    }
+}
+
+/// What to do with a user that panicked.
+///
+/// A restarted user is a fresh instance created with `User::new` and the same id as the panicked one.
+/// The restart happens after waiting `backoff_in_millis`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum RestartPolicy {
+    #[default]
+    Never,
+    Always {
+        backoff_in_millis: u64,
+    },
+    UpTo {
+        max_restarts: u64,
+        backoff_in_millis: u64,
+    },
+}
+
+impl RestartPolicy {
+    pub(crate) fn should_restart(&self, restarts: u64) -> bool {
+        match self {
+            RestartPolicy::Never => false,
+            RestartPolicy::Always { .. } => true,
+            RestartPolicy::UpTo { max_restarts, .. } => restarts < *max_restarts,
+        }
+    }
+
+    pub(crate) fn get_backoff(&self) -> Duration {
+        match self {
+            RestartPolicy::Never => Duration::ZERO,
+            RestartPolicy::Always { backoff_in_millis }
+            | RestartPolicy::UpTo {
+                backoff_in_millis, ..
+            } => Duration::from_millis(*backoff_in_millis),
         }
     }
 }
@@ -212,3 +294,47 @@ impl UserController {
         BreakPoint.await;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn restart_policy_limits_restarts() {
+        assert!(!RestartPolicy::Never.should_restart(0));
+
+        let always = RestartPolicy::Always {
+            backoff_in_millis: 10,
+        };
+        assert!(always.should_restart(0));
+        assert!(always.should_restart(u64::MAX));
+
+        let up_to = RestartPolicy::UpTo {
+            max_restarts: 2,
+            backoff_in_millis: 10,
+        };
+        assert!(up_to.should_restart(0));
+        assert!(up_to.should_restart(1));
+        assert!(!up_to.should_restart(2));
+    }
+
+    #[test]
+    fn restart_policy_backoff() {
+        assert_eq!(RestartPolicy::Never.get_backoff(), Duration::ZERO);
+        assert_eq!(
+            RestartPolicy::Always {
+                backoff_in_millis: 10
+            }
+            .get_backoff(),
+            Duration::from_millis(10)
+        );
+        assert_eq!(
+            RestartPolicy::UpTo {
+                max_restarts: 1,
+                backoff_in_millis: 20
+            }
+            .get_backoff(),
+            Duration::from_millis(20)
+        );
+    }
+}
@@ -1,5 +1,92 @@
-use crate::{events::EventsHandler, test::controller::TestController, test::user::UserController};
-use std::sync::Arc;
+use crate::{
+    custom_metrics::MetricUpdate,
+    events::EventsHandler,
+    futures::TimedExt,
+    results::{ResponseInfo, TRANSACTION_ENDPOINT_TYPE},
+    tasks::EventsTaskInfo,
+    test::controller::TestController,
+    test::user::UserController,
+};
+use std::{
+    fmt::Display,
+    future::Future,
+    sync::{Arc, Mutex},
+};
+
+// how Context::measure should record the output of a future
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    Success,
+    Failure,
+    Error(String),
+}
+
+tokio::task_local! {
+    // set while the future of a transaction is running, so results know which transaction they belong to
+    static CURRENT_TRANSACTION: u64;
+}
+
+#[derive(Debug)]
+struct OpenTransaction {
+    id: u64,
+    // the enclosing transaction
+    parent: Option<u64>,
+    failed: bool,
+}
+
+#[derive(Debug, Default)]
+struct Transactions {
+    next_id: u64,
+    // the currently open transactions
+    open: Vec<OpenTransaction>,
+}
+
+impl Transactions {
+    // fails the transaction and all its enclosing transactions, but not its siblings
+    fn fail(&mut self, id: u64) {
+        let mut current = Some(id);
+        while let Some(id) = current {
+            current = None;
+            if let Some(transaction) = self
+                .open
+                .iter_mut()
+                .find(|transaction| transaction.id == id)
+            {
+                transaction.failed = true;
+                current = transaction.parent;
+            }
+        }
+    }
+}
+
+// removes the transaction even if its future is dropped before completion
+struct TransactionGuard<'a> {
+    transactions: &'a Mutex<Transactions>,
+    id: u64,
+}
+
+impl TransactionGuard<'_> {
+    // returns true if the transaction failed
+    fn finish(self) -> bool {
+        match self.transactions.lock() {
+            Ok(transactions) => transactions
+                .open
+                .iter()
+                .any(|transaction| transaction.id == self.id && transaction.failed),
+            Err(_) => false,
+        }
+    }
+}
+
+impl Drop for TransactionGuard<'_> {
+    fn drop(&mut self) {
+        if let Ok(mut transactions) = self.transactions.lock() {
+            transactions
+                .open
+                .retain(|transaction| transaction.id != self.id);
+        }
+    }
+}
 
 pub struct Context {
     // each user will recieve a Data obj containing
@@ -12,6 +99,15 @@ pub struct Context {
 
     // not shared between users
     user_controller: UserController,
+
+    // the task the user is currently running, None in on_start and on_stop
+    current_task: Mutex<Option<EventsTaskInfo>>,
+
+    // not shared between users
+    transactions: Mutex<Transactions>,
+
+    // from the test config, relative urls of the http client are joined with it
+    base_url: Option<String>,
     // why is AllResults not included here?
     // well, because it is behind an RwLock, wich is only accessed in 3 main tasks (test server, test main loop, test background loop)
     // we don't want the ~1000 users to accuire a lock on it with on every single task
@@ -28,9 +124,16 @@ impl Context {
             test_controller,
             events_handler,
             user_controller,
+            current_task: Mutex::new(None),
+            transactions: Mutex::new(Transactions::default()),
+            base_url: None,
         }
     }
 
+    pub(crate) fn base_url(self, base_url: Option<String>) -> Self {
+        Self { base_url, ..self }
+    }
+
     pub async fn stop(&self) {
         self.user_controller.stop().await;
     }
@@ -40,17 +143,249 @@ impl Context {
     }
 
     pub async fn add_success(&self, r#type: String, name: String, response_time: f64) {
+        self.add_success_with_info(r#type, name, response_time, ResponseInfo::default())
+            .await;
+    }
+
+    // with the status code and the size of the response, e.g. for http requests
+    pub async fn add_success_with_info(
+        &self,
+        r#type: String,
+        name: String,
+        response_time: f64,
+        response_info: ResponseInfo,
+    ) {
         self.events_handler
-            .add_success(r#type, name, response_time)
+            .report_success(
+                self.get_current_task(),
+                r#type,
+                name,
+                response_time,
+                response_info,
+            )
             .await;
     }
 
     pub async fn add_failure(&self, r#type: String, name: String) {
-        self.events_handler.add_failure(r#type, name).await;
+        self.add_failure_with_info(r#type, name, ResponseInfo::default())
+            .await;
+    }
+
+    pub async fn add_failure_with_info(
+        &self,
+        r#type: String,
+        name: String,
+        response_info: ResponseInfo,
+    ) {
+        self.add_failure_with_details(r#type, name, None, None, response_info)
+            .await;
+    }
+
+    /// Failures with a reason are aggregated by endpoint and reason, like errors.
+    /// The response time of failures is recorded separately from the response time of successes.
+    pub async fn add_failure_with_reason(
+        &self,
+        r#type: String,
+        name: String,
+        reason: String,
+        response_time: Option<f64>,
+    ) {
+        self.add_failure_with_details(
+            r#type,
+            name,
+            Some(reason),
+            response_time,
+            ResponseInfo::default(),
+        )
+        .await;
+    }
+
+    pub async fn add_failure_with_details(
+        &self,
+        r#type: String,
+        name: String,
+        reason: Option<String>,
+        response_time: Option<f64>,
+        response_info: ResponseInfo,
+    ) {
+        self.fail_current_transaction();
+        self.events_handler
+            .report_failure(
+                self.get_current_task(),
+                r#type,
+                name,
+                reason,
+                response_time,
+                response_info,
+            )
+            .await;
     }
 
     pub async fn add_error(&self, r#type: String, name: String, error: String) {
-        self.events_handler.add_error(r#type, name, error).await;
+        self.fail_current_transaction();
+        self.events_handler
+            .report_error(self.get_current_task(), r#type, name, error)
+            .await;
+    }
+
+    /// Records a k6 style check with the given name. Checks do not affect the result of a request,
+    /// they are aggregated by name with their pass rate and can be used as thresholds. Returns passed.
+    pub async fn check(&self, name: &str, passed: bool) -> bool {
+        self.events_handler
+            .add_checks(self.get_current_task(), vec![(String::from(name), passed)])
+            .await;
+        passed
+    }
+
+    /// Like Context::check for multiple checks at once. Returns true if all checks passed.
+    pub async fn check_all(&self, checks: &[(&str, bool)]) -> bool {
+        let all_passed = checks.iter().all(|(_, passed)| *passed);
+        self.events_handler
+            .add_checks(
+                self.get_current_task(),
+                checks
+                    .iter()
+                    .map(|(name, passed)| (String::from(*name), *passed))
+                    .collect(),
+            )
+            .await;
+        all_passed
+    }
+
+    pub(crate) async fn update_metric(&self, name: &str, update: MetricUpdate) {
+        self.events_handler
+            .add_metric_update(String::from(name), update)
+            .await;
+    }
+
+    /// Times the given future and records the outcome given by classify with the given type and name.
+    /// Returns the output of the future, so the task can continue with it.
+    pub async fn measure<F, C>(&self, r#type: &str, name: &str, future: F, classify: C) -> F::Output
+    where
+        F: Future,
+        C: FnOnce(&F::Output) -> Outcome,
+    {
+        let (output, duration) = future.timed().await;
+        match classify(&output) {
+            Outcome::Success => {
+                self.add_success(
+                    String::from(r#type),
+                    String::from(name),
+                    duration.as_secs_f64(),
+                )
+                .await;
+            }
+            Outcome::Failure => {
+                self.add_failure_with_details(
+                    String::from(r#type),
+                    String::from(name),
+                    None,
+                    Some(duration.as_secs_f64()),
+                    ResponseInfo::default(),
+                )
+                .await;
+            }
+            Outcome::Error(error) => {
+                self.add_error(String::from(r#type), String::from(name), error)
+                    .await;
+            }
+        }
+        output
+    }
+
+    /// Like Context::measure, Ok is recorded as a success and Err as an error.
+    pub async fn measure_result<F, T, E>(&self, r#type: &str, name: &str, future: F) -> Result<T, E>
+    where
+        F: Future<Output = Result<T, E>>,
+        E: Display,
+    {
+        self.measure(r#type, name, future, |output| match output {
+            Ok(_) => Outcome::Success,
+            Err(error) => Outcome::Error(error.to_string()),
+        })
+        .await
+    }
+
+    /// Times the given future and records it with the type TRANSACTION and the given name.
+    /// The transaction is recorded as a failure if a failure or an error was added by its future,
+    /// this includes nested transactions. Concurrently running sibling transactions do not affect each other.
+    /// If the future is dropped, nothing is recorded.
+    pub async fn transaction<F>(&self, name: &str, future: F) -> F::Output
+    where
+        F: Future,
+    {
+        let guard = self.open_transaction();
+        let (output, duration) = CURRENT_TRANSACTION.scope(guard.id, future.timed()).await;
+        let failed = guard.finish();
+
+        if failed {
+            self.add_failure_with_details(
+                String::from(TRANSACTION_ENDPOINT_TYPE),
+                String::from(name),
+                None,
+                Some(duration.as_secs_f64()),
+                ResponseInfo::default(),
+            )
+            .await;
+        } else {
+            self.add_success(
+                String::from(TRANSACTION_ENDPOINT_TYPE),
+                String::from(name),
+                duration.as_secs_f64(),
+            )
+            .await;
+        }
+        output
+    }
+
+    fn open_transaction(&self) -> TransactionGuard<'_> {
+        let parent = CURRENT_TRANSACTION.try_with(|id| *id).ok();
+        let mut id = 0;
+        if let Ok(mut transactions) = self.transactions.lock() {
+            id = transactions.next_id;
+            transactions.next_id += 1;
+            transactions.open.push(OpenTransaction {
+                id,
+                parent,
+                failed: false,
+            });
+        }
+        TransactionGuard {
+            transactions: &self.transactions,
+            id,
+        }
+    }
+
+    // a failure inside a nested transaction fails all the enclosing transactions
+    fn fail_current_transaction(&self) {
+        if let Ok(id) = CURRENT_TRANSACTION.try_with(|id| *id) {
+            if let Ok(mut transactions) = self.transactions.lock() {
+                transactions.fail(id);
+            }
+        }
+    }
+
+    pub(crate) fn set_current_task(&self, task_info: Option<EventsTaskInfo>) {
+        if let Ok(mut current_task) = self.current_task.lock() {
+            *current_task = task_info;
+        }
+    }
+
+    pub(crate) fn get_current_task(&self) -> Option<EventsTaskInfo> {
+        match self.current_task.lock() {
+            Ok(current_task) => *current_task,
+            Err(_) => None,
+        }
+    }
+
+    pub fn get_current_task_name(&self) -> Option<&'static str> {
+        self.get_current_task().map(|task_info| task_info.name)
+    }
+
+    pub fn get_current_task_tags(&self) -> &'static [&'static str] {
+        self.get_current_task()
+            .map(|task_info| task_info.tags)
+            .unwrap_or(&[])
     }
 
     pub(crate) fn _get_events_handler(&self) -> &EventsHandler {
@@ -60,4 +395,270 @@ impl Context {
     pub fn get_id(&self) -> u64 {
         self.events_handler.get_user_id()
     }
+
+    pub fn get_base_url(&self) -> Option<&str> {
+        self.base_url.as_deref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        messages::{MainMessage, ResultMessage},
+        test::user::EventsUserInfo,
+    };
+    use tokio::sync::mpsc::{self, Receiver};
+    use tokio_util::sync::CancellationToken;
+
+    fn context() -> (Context, Receiver<MainMessage>) {
+        let (results_tx, results_rx) = mpsc::channel(100);
+        let context = Context::new(
+            Arc::new(TestController::new(CancellationToken::new())),
+            EventsHandler::new(EventsUserInfo::new(0, "TestUser"), results_tx),
+            UserController::new(CancellationToken::new()),
+        );
+        (context, results_rx)
+    }
+
+    // the recorded transactions in order of completion, with true for a success
+    fn transactions(results_rx: &mut Receiver<MainMessage>) -> Vec<(String, bool)> {
+        let mut transactions = Vec::new();
+        while let Ok(message) = results_rx.try_recv() {
+            let (endpoint_type_name, passed) = match message {
+                MainMessage::ResultMessage(ResultMessage::Success(msg)) => {
+                    (msg.endpoint_type_name, true)
+                }
+                MainMessage::ResultMessage(ResultMessage::Failure(msg)) => {
+                    (msg.endpoint_type_name, false)
+                }
+                _ => continue,
+            };
+            if endpoint_type_name.r#type == TRANSACTION_ENDPOINT_TYPE {
+                transactions.push((endpoint_type_name.name, passed));
+            }
+        }
+        transactions
+    }
+
+    #[tokio::test]
+    async fn nested_transactions_are_recorded() {
+        let (context, mut results_rx) = context();
+
+        context
+            .transaction("outer", async {
+                context.transaction("inner", async {}).await;
+            })
+            .await;
+
+        assert_eq!(
+            transactions(&mut results_rx),
+            vec![(String::from("inner"), true), (String::from("outer"), true)]
+        );
+    }
+
+    #[tokio::test]
+    async fn failure_is_propagated_to_the_parents() {
+        let (context, mut results_rx) = context();
+
+        context
+            .transaction("outer", async {
+                context
+                    .transaction("middle", async {
+                        context
+                            .transaction("inner", async {
+                                context
+                                    .add_error(
+                                        String::from("GET"),
+                                        String::from("/pay"),
+                                        String::from("refused"),
+                                    )
+                                    .await;
+                            })
+                            .await;
+                    })
+                    .await;
+            })
+            .await;
+
+        assert_eq!(
+            transactions(&mut results_rx),
+            vec![
+                (String::from("inner"), false),
+                (String::from("middle"), false),
+                (String::from("outer"), false)
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn failure_is_not_propagated_to_siblings() {
+        let (context, mut results_rx) = context();
+
+        context
+            .transaction("outer", async {
+                // both transactions are open while the failure is added
+                tokio::join!(
+                    context.transaction("failing", async {
+                        tokio::task::yield_now().await;
+                        context
+                            .add_failure(String::from("GET"), String::from("/cart"))
+                            .await;
+                    }),
+                    context.transaction("passing", async {
+                        tokio::task::yield_now().await;
+                        tokio::task::yield_now().await;
+                    }),
+                );
+            })
+            .await;
+        // results outside of a transaction fail nothing
+        context
+            .add_failure(String::from("GET"), String::from("/cart"))
+            .await;
+        context.transaction("after", async {}).await;
+
+        assert_eq!(
+            transactions(&mut results_rx),
+            vec![
+                (String::from("failing"), false),
+                (String::from("passing"), true),
+                (String::from("outer"), false),
+                (String::from("after"), true)
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn measure_records_the_outcome() {
+        let (context, mut results_rx) = context();
+
+        let status = context
+            .measure("GET", "/items", async { 200 }, |_| Outcome::Success)
+            .await;
+        context
+            .measure("GET", "/cart", async { 500 }, |_| Outcome::Failure)
+            .await;
+        context
+            .measure("GET", "/pay", async {}, |_| {
+                Outcome::Error(String::from("refused"))
+            })
+            .await;
+
+        assert_eq!(status, 200);
+        match results_rx.try_recv() {
+            Ok(MainMessage::ResultMessage(ResultMessage::Success(msg))) => {
+                assert_eq!(msg.endpoint_type_name.r#type, "GET");
+                assert_eq!(msg.endpoint_type_name.name, "/items");
+            }
+            _ => panic!("expected a success"),
+        }
+        match results_rx.try_recv() {
+            Ok(MainMessage::ResultMessage(ResultMessage::Failure(msg))) => {
+                assert_eq!(msg.endpoint_type_name.name, "/cart");
+                // the response time of failures is recorded too
+                assert!(msg.response_time.is_some());
+            }
+            _ => panic!("expected a failure"),
+        }
+        match results_rx.try_recv() {
+            Ok(MainMessage::ResultMessage(ResultMessage::Error(msg))) => {
+                assert_eq!(msg.endpoint_type_name.name, "/pay");
+                assert_eq!(msg.error, "refused");
+            }
+            _ => panic!("expected an error"),
+        }
+    }
+
+    #[tokio::test]
+    async fn measure_result_records_err_as_error() {
+        let (context, mut results_rx) = context();
+
+        let ok: Result<u8, String> = context
+            .measure_result("GET", "/items", async { Ok(1) })
+            .await;
+        let err: Result<u8, String> = context
+            .measure_result("GET", "/items", async { Err(String::from("timed out")) })
+            .await;
+
+        assert_eq!(ok, Ok(1));
+        assert_eq!(err, Err(String::from("timed out")));
+        assert!(matches!(
+            results_rx.try_recv(),
+            Ok(MainMessage::ResultMessage(ResultMessage::Success(_)))
+        ));
+        match results_rx.try_recv() {
+            Ok(MainMessage::ResultMessage(ResultMessage::Error(msg))) => {
+                assert_eq!(msg.error, "timed out");
+            }
+            _ => panic!("expected an error"),
+        }
+    }
+
+    #[tokio::test]
+    async fn failure_is_reported_with_reason_and_response_time() {
+        let (context, mut results_rx) = context();
+
+        context
+            .add_failure_with_reason(
+                String::from("GET"),
+                String::from("/cart"),
+                String::from("empty cart"),
+                Some(0.5),
+            )
+            .await;
+
+        match results_rx.try_recv() {
+            Ok(MainMessage::ResultMessage(ResultMessage::Failure(msg))) => {
+                assert_eq!(msg.reason.as_deref(), Some("empty cart"));
+                assert_eq!(msg.response_time, Some(0.5));
+            }
+            _ => panic!("expected a failure"),
+        }
+    }
+
+    #[tokio::test]
+    async fn results_are_stamped_when_reported() {
+        let (context, mut results_rx) = context();
+
+        let before = crate::utils::get_timestamp_as_millis().unwrap() as u64;
+        context
+            .add_success(String::from("GET"), String::from("/cart"), 0.1)
+            .await;
+        let after = crate::utils::get_timestamp_as_millis().unwrap() as u64;
+        // the main loop handles the result later
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+
+        match results_rx.try_recv() {
+            Ok(MainMessage::ResultMessage(ResultMessage::Success(msg))) => {
+                assert!(before <= msg.timestamp && msg.timestamp <= after);
+            }
+            _ => panic!("expected a success"),
+        }
+    }
+
+    #[tokio::test]
+    async fn checks_are_reported_and_returned() {
+        let (context, mut results_rx) = context();
+
+        assert!(context.check("status is 200", true).await);
+        assert!(
+            !context
+                .check_all(&[("status is 200", true), ("body is json", false)])
+                .await
+        );
+
+        let mut checks = Vec::new();
+        while let Ok(MainMessage::Check(msg)) = results_rx.try_recv() {
+            checks.extend(msg.checks);
+        }
+        assert_eq!(
+            checks,
+            vec![
+                (String::from("status is 200"), true),
+                (String::from("status is 200"), true),
+                (String::from("body is json"), false)
+            ]
+        );
+    }
 }
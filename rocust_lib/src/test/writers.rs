@@ -1,17 +1,38 @@
 use crate::{
+    errors::AllErrors,
     fs::{timestamped_writer::TimeStapmedWriter, writer::Writer},
     prometheus_exporter::PrometheusExporter,
+    prometheus_pusher::PrometheusPusher,
     results::AllResults,
+    test::{
+        line_writers::{GraphiteWriter, InfluxWriter},
+        results_history_writer::ResultsHistoryWriter,
+    },
     utils, TestConfig,
 };
+use std::time::Duration;
+
+// built while the results are locked, sent after they are released
+#[derive(Default)]
+pub(crate) struct LineProtocols {
+    influx: Option<String>,
+    graphite: Option<String>,
+}
 
 #[derive(Clone)]
 pub(crate) struct Writers {
     current_results_writer: Option<Writer>,
-    results_history_writer: Option<Writer>,
+    results_history_writer: Option<ResultsHistoryWriter>,
     summary_writer: Option<Writer>,
+    html_report_writer: Option<Writer>,
+    junit_report_writer: Option<Writer>,
+    errors_writer: Option<Writer>,
+    failures_writer: Option<Writer>,
     prometheus_current_metrics_writer: Option<Writer>,
     prometheus_metrics_history_writer: Option<TimeStapmedWriter>,
+    prometheus_pusher: Option<PrometheusPusher>,
+    influx_writer: Option<InfluxWriter>,
+    graphite_writer: Option<GraphiteWriter>,
 }
 
 impl Writers {
@@ -30,30 +51,8 @@ impl Writers {
             };
         let results_history_writer =
             if let Some(results_history_file) = &test_config.results_history_file {
-                match Writer::from_str(results_history_file).await {
-                    Ok(writer) => {
-                        // write header
-                        let header = AllResults::history_header_csv_string();
-                        match header {
-                            Ok(header) => match writer.write_all(header.as_bytes()).await {
-                                Ok(_) => Some(writer),
-                                Err(error) => {
-                                    tracing::error!(
-                                        %error,
-                                        "Failed to write header to results history file"
-                                    );
-                                    None
-                                }
-                            },
-                            Err(error) => {
-                                tracing::error!(
-                                    %error,
-                                    "Failed to create header for results history file",
-                                );
-                                None
-                            }
-                        }
-                    }
+                match ResultsHistoryWriter::new(results_history_file).await {
+                    Ok(writer) => Some(writer),
                     Err(error) => {
                         tracing::error!(%error, "Failed to create writer for results history file");
                         None
@@ -73,6 +72,50 @@ impl Writers {
         } else {
             None
         };
+        let html_report_writer = if let Some(html_report_file) = &test_config.html_report_file {
+            match Writer::from_str(html_report_file).await {
+                Ok(writer) => Some(writer),
+                Err(error) => {
+                    tracing::error!(%error, "Failed to create writer for html report file");
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        let junit_report_writer = if let Some(junit_report_file) = &test_config.junit_report_file {
+            match Writer::from_str(junit_report_file).await {
+                Ok(writer) => Some(writer),
+                Err(error) => {
+                    tracing::error!(%error, "Failed to create writer for junit report file");
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        let errors_writer = if let Some(errors_file) = &test_config.errors_file {
+            match Writer::from_str(errors_file).await {
+                Ok(writer) => Some(writer),
+                Err(error) => {
+                    tracing::error!(%error, "Failed to create writer for errors file");
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        let failures_writer = if let Some(failures_file) = &test_config.failures_file {
+            match Writer::from_str(failures_file).await {
+                Ok(writer) => Some(writer),
+                Err(error) => {
+                    tracing::error!(%error, "Failed to create writer for failures file");
+                    None
+                }
+            }
+        } else {
+            None
+        };
         let prometheus_current_metrics_writer = if let Some(prometheus_current_metrics_file) =
             &test_config.prometheus_current_metrics_file
         {
@@ -110,12 +153,46 @@ impl Writers {
         } else {
             None
         };
+        let prometheus_pusher =
+            if let Some(prometheus_pushgateway) = &test_config.prometheus_pushgateway {
+                match PrometheusPusher::new(prometheus_pushgateway) {
+                    Ok(prometheus_pusher) => Some(prometheus_pusher),
+                    Err(error) => {
+                        tracing::error!(%error, "Failed to create prometheus pusher");
+                        None
+                    }
+                }
+            } else {
+                None
+            };
+        let influx_writer = if let Some(influx_output) = &test_config.influx_output {
+            match InfluxWriter::new(influx_output).await {
+                Ok(writer) => Some(writer),
+                Err(error) => {
+                    tracing::error!(%error, "Failed to create influx writer");
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        let graphite_writer = test_config
+            .graphite_output
+            .as_ref()
+            .map(GraphiteWriter::new);
         Self {
             current_results_writer,
             results_history_writer,
             summary_writer,
+            html_report_writer,
+            junit_report_writer,
+            errors_writer,
+            failures_writer,
             prometheus_current_metrics_writer,
             prometheus_metrics_history_writer,
+            prometheus_pusher,
+            influx_writer,
+            graphite_writer,
         }
     }
 
@@ -135,21 +212,12 @@ impl Writers {
         }
     }
 
-    async fn write_results_history(&self, all_results: &AllResults) {
+    async fn write_results_history(&self, all_results: &AllResults, elapsed_time: &Duration) {
         if let Some(writer) = &self.results_history_writer {
-            match utils::get_timestamp_as_millis_as_string() {
+            match utils::get_timestamp_as_millis() {
                 Ok(timestamp) => {
-                    let csv_string = all_results
-                        .current_aggrigated_results_with_timestamp_csv_string(&timestamp);
-                    match csv_string {
-                        Ok(csv_string) => {
-                            if let Err(error) = writer.append_all(csv_string.as_bytes()).await {
-                                tracing::error!(%error, "Error writing to csv");
-                            }
-                        }
-                        Err(error) => {
-                            tracing::error!(%error, "Error getting csv string");
-                        }
+                    if let Err(error) = writer.write(all_results, elapsed_time, timestamp).await {
+                        tracing::error!(%error, "Error writing results history");
                     }
                 }
                 Err(error) => {
@@ -159,6 +227,23 @@ impl Writers {
         }
     }
 
+    // errors and failures
+    async fn write_errors(writer: &Option<Writer>, all_errors: &AllErrors) {
+        if let Some(writer) = writer {
+            let csv_string = all_errors.csv_string();
+            match csv_string {
+                Ok(csv_string) => {
+                    if let Err(error) = writer.write_all(csv_string.as_bytes()).await {
+                        tracing::error!(%error, kind = ?all_errors.get_kind(), "Error writing errors to csv");
+                    }
+                }
+                Err(error) => {
+                    tracing::error!(%error, kind = ?all_errors.get_kind(), "Error getting errors csv string");
+                }
+            }
+        }
+    }
+
     async fn write_prometheus_current_metrics(&self, prometheus_exporter: &PrometheusExporter) {
         if let Some(writer) = &self.prometheus_current_metrics_writer {
             let prometheus_metrics_string = prometheus_exporter.get_metrics();
@@ -193,20 +278,139 @@ impl Writers {
         }
     }
 
+    async fn push_prometheus_metrics(&self, prometheus_exporter: &PrometheusExporter) {
+        if let Some(prometheus_pusher) = &self.prometheus_pusher {
+            if let Err(error) = prometheus_pusher.push(prometheus_exporter).await {
+                tracing::error!(%error, "Error pushing prometheus metrics");
+            }
+        }
+    }
+
+    async fn send_influx(&self, lines: Option<String>) {
+        if let (Some(influx_writer), Some(lines)) = (&self.influx_writer, lines) {
+            if let Err(error) = influx_writer.write_file(&lines).await {
+                tracing::error!(%error, "Error writing influx line protocol");
+            }
+            if let Err(error) = influx_writer.send(lines).await {
+                tracing::error!(%error, "Error sending influx line protocol");
+            }
+        }
+    }
+
+    async fn send_graphite(&self, lines: Option<String>) {
+        if let (Some(graphite_writer), Some(lines)) = (&self.graphite_writer, lines) {
+            if let Err(error) = graphite_writer.send(&lines).await {
+                tracing::error!(%error, "Error sending graphite plaintext");
+            }
+        }
+    }
+
+    // influx and graphite
+    pub(crate) fn line_protocols(&self, all_results: &AllResults) -> LineProtocols {
+        if self.influx_writer.is_none() && self.graphite_writer.is_none() {
+            return LineProtocols::default();
+        }
+        match utils::get_timestamp_as_millis() {
+            Ok(timestamp) => LineProtocols {
+                influx: self
+                    .influx_writer
+                    .as_ref()
+                    .map(|influx_writer| influx_writer.lines(all_results, timestamp)),
+                graphite: self
+                    .graphite_writer
+                    .as_ref()
+                    .map(|graphite_writer| graphite_writer.lines(all_results, timestamp)),
+            },
+            Err(error) => {
+                tracing::error!(%error, "Error getting timestamp");
+                LineProtocols::default()
+            }
+        }
+    }
+
     pub(crate) async fn write_on_update_interval(
         &self,
         all_results: &AllResults,
+        all_errors: &AllErrors,
+        all_failures: &AllErrors,
         prometheus_exporter: &PrometheusExporter,
+        elapsed_time: &Duration,
     ) {
         self.write_current_results(all_results).await;
-        self.write_results_history(all_results).await;
+        self.write_results_history(all_results, elapsed_time).await;
+        Writers::write_errors(&self.errors_writer, all_errors).await;
+        Writers::write_errors(&self.failures_writer, all_failures).await;
         self.write_prometheus_current_metrics(prometheus_exporter)
             .await;
         self.write_prometheus_metrics_history(prometheus_exporter)
             .await;
     }
 
+    // called without holding the results, a slow server only delays the next update
+    pub(crate) async fn send_on_update_interval(
+        &self,
+        prometheus_exporter: &PrometheusExporter,
+        line_protocols: LineProtocols,
+    ) {
+        self.push_prometheus_metrics(prometheus_exporter).await;
+        self.send_influx(line_protocols.influx).await;
+        self.send_graphite(line_protocols.graphite).await;
+    }
+
     pub(crate) fn get_summary_writer(&self) -> &Option<Writer> {
         &self.summary_writer
     }
+
+    pub(crate) fn get_html_report_writer(&self) -> &Option<Writer> {
+        &self.html_report_writer
+    }
+
+    pub(crate) fn get_junit_report_writer(&self) -> &Option<Writer> {
+        &self.junit_report_writer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        results::{EndpointTypeName, ResponseInfo},
+        test::config::GraphiteOutput,
+    };
+    use tokio::{io::AsyncReadExt, net::TcpListener};
+
+    #[tokio::test]
+    async fn line_protocols_are_sent_as_built() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let received = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut received = String::new();
+            stream.read_to_string(&mut received).await.unwrap();
+            received
+        });
+        let test_config = TestConfig::default().graphite_output(GraphiteOutput::new(address));
+        let writers = Writers::new(&test_config).await;
+        let endpoint_type_name = EndpointTypeName {
+            r#type: String::from("GET"),
+            name: String::from("/items"),
+        };
+        let mut all_results = AllResults::default();
+        all_results.add_success(&endpoint_type_name, None, 0.5, &ResponseInfo::new());
+
+        let line_protocols = writers.line_protocols(&all_results);
+        // results added after the lines are built are sent with the next update
+        all_results.add_success(&endpoint_type_name, None, 0.5, &ResponseInfo::new());
+        writers
+            .send_on_update_interval(&PrometheusExporter::new(&test_config), line_protocols)
+            .await;
+
+        let received = received.await.unwrap();
+        assert!(received.contains("rocust.total_requests;type=GET;name=/items 1 "));
+        // nothing is built without influx and graphite writers
+        let line_protocols = Writers::new(&TestConfig::default())
+            .await
+            .line_protocols(&all_results);
+        assert!(line_protocols.influx.is_none() && line_protocols.graphite.is_none());
+    }
 }
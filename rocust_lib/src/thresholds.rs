@@ -0,0 +1,368 @@
+use crate::results::{AllResults, EndpointTypeName, Results};
+use std::{fmt::Display, num::ParseFloatError, str::FromStr};
+use thiserror::Error as ThisError;
+
+const ENDPOINT_THRESHOLD_SEPARATOR: &str = "<=";
+const STOP_CONDITION_SEPARATOR: &str = ">";
+
+/// A value of the results of an endpoint that can be limited, response times are in seconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResultsMetric {
+    AverageResponseTime,
+    MedianResponseTime,
+    Percentile90ResponseTime,
+    Percentile95ResponseTime,
+    Percentile99ResponseTime,
+    MaxResponseTime,
+    // failed requests divided by requests, between 0 and 1
+    FailureRate,
+}
+
+impl ResultsMetric {
+    fn as_str(&self) -> &'static str {
+        match self {
+            ResultsMetric::AverageResponseTime => "avg_response_time",
+            ResultsMetric::MedianResponseTime => "median_response_time",
+            ResultsMetric::Percentile90ResponseTime => "p90_response_time",
+            ResultsMetric::Percentile95ResponseTime => "p95_response_time",
+            ResultsMetric::Percentile99ResponseTime => "p99_response_time",
+            ResultsMetric::MaxResponseTime => "max_response_time",
+            ResultsMetric::FailureRate => "failure_rate",
+        }
+    }
+
+    fn value(&self, results: &Results) -> f64 {
+        match self {
+            ResultsMetric::AverageResponseTime => results.get_average_response_time(),
+            ResultsMetric::MedianResponseTime => results.get_median_response_time(),
+            ResultsMetric::Percentile90ResponseTime => results.get_percentile_90_response_time(),
+            ResultsMetric::Percentile95ResponseTime => results.get_percentile_95_response_time(),
+            ResultsMetric::Percentile99ResponseTime => results.get_percentile_99_response_time(),
+            ResultsMetric::MaxResponseTime => results.get_max_response_time(),
+            ResultsMetric::FailureRate => {
+                if results.get_total_requests() == 0 {
+                    return 0.0;
+                }
+                results.get_total_failed_requests() as f64 / results.get_total_requests() as f64
+            }
+        }
+    }
+}
+
+impl Display for ResultsMetric {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+#[derive(Debug, ThisError)]
+pub enum ThresholdParseError {
+    #[error("Expected [{{type}} {{name}} ]{{metric}}{0}{{value}}")]
+    MissingSeparator(&'static str),
+    #[error("Expected {{type}} {{name}} before the metric")]
+    MissingEndpointName,
+    #[error("Unknown metric: {0}")]
+    UnknownMetric(String),
+    #[error("Invalid value: {0}")]
+    InvalidValue(#[from] ParseFloatError),
+}
+
+impl FromStr for ResultsMetric {
+    type Err = ThresholdParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        [
+            ResultsMetric::AverageResponseTime,
+            ResultsMetric::MedianResponseTime,
+            ResultsMetric::Percentile90ResponseTime,
+            ResultsMetric::Percentile95ResponseTime,
+            ResultsMetric::Percentile99ResponseTime,
+            ResultsMetric::MaxResponseTime,
+            ResultsMetric::FailureRate,
+        ]
+        .into_iter()
+        .find(|metric| metric.as_str() == s)
+        .ok_or_else(|| ThresholdParseError::UnknownMetric(String::from(s)))
+    }
+}
+
+// [{type} {name} ]{metric}{separator}{value}, the name may contain spaces
+fn parse(
+    s: &str,
+    separator: &'static str,
+) -> Result<(Option<EndpointTypeName>, ResultsMetric, f64), ThresholdParseError> {
+    let (target, value) = s
+        .rsplit_once(separator)
+        .ok_or(ThresholdParseError::MissingSeparator(separator))?;
+    let value = value.trim().parse()?;
+    let target = target.trim();
+    let (endpoint_type_name, metric) = match target.rsplit_once(' ') {
+        Some((endpoint, metric)) => {
+            let (r#type, name) = endpoint
+                .trim()
+                .split_once(' ')
+                .ok_or(ThresholdParseError::MissingEndpointName)?;
+            let endpoint_type_name = EndpointTypeName {
+                r#type: String::from(r#type),
+                name: String::from(name.trim()),
+            };
+            (Some(endpoint_type_name), metric)
+        }
+        None => (None, target),
+    };
+    Ok((endpoint_type_name, metric.parse()?, value))
+}
+
+// None if the endpoint has no results
+fn observe(
+    all_results: &AllResults,
+    endpoint_type_name: &Option<EndpointTypeName>,
+    metric: ResultsMetric,
+) -> Option<f64> {
+    let results = match endpoint_type_name {
+        Some(endpoint_type_name) => all_results
+            .get_by_type_and_name(&endpoint_type_name.r#type, &endpoint_type_name.name)?,
+        None => all_results.get_aggrigated_results(),
+    };
+    Some(metric.value(results))
+}
+
+fn fmt_target(
+    f: &mut std::fmt::Formatter<'_>,
+    endpoint_type_name: &Option<EndpointTypeName>,
+    metric: ResultsMetric,
+) -> std::fmt::Result {
+    if let Some(endpoint_type_name) = endpoint_type_name {
+        write!(
+            f,
+            "{} {} ",
+            endpoint_type_name.r#type, endpoint_type_name.name
+        )?;
+    }
+    write!(f, "{}", metric)
+}
+
+/// The test is considered failed if the metric of the endpoint is above `max` at the end of the test, or the endpoint has no results.
+/// Without an endpoint the threshold applies to the aggregated results.
+/// Given as `[{type} {name} ]{metric}<={max}` on the command line, e.g. `GET /items p95_response_time<=0.5` or `failure_rate<=0.01`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EndpointThreshold {
+    pub endpoint_type_name: Option<EndpointTypeName>,
+    pub metric: ResultsMetric,
+    pub max: f64,
+}
+
+impl FromStr for EndpointThreshold {
+    type Err = ThresholdParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (endpoint_type_name, metric, max) = parse(s, ENDPOINT_THRESHOLD_SEPARATOR)?;
+        Ok(Self {
+            endpoint_type_name,
+            metric,
+            max,
+        })
+    }
+}
+
+impl Display for EndpointThreshold {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt_target(f, &self.endpoint_type_name, self.metric)?;
+        write!(f, "{}{}", ENDPOINT_THRESHOLD_SEPARATOR, self.max)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct EndpointThresholdResult {
+    pub endpoint_threshold: EndpointThreshold,
+    // None if the endpoint has no results
+    pub observed: Option<f64>,
+    pub passed: bool,
+}
+
+pub fn evaluate_endpoint_thresholds(
+    all_results: &AllResults,
+    endpoint_thresholds: &[EndpointThreshold],
+) -> Vec<EndpointThresholdResult> {
+    endpoint_thresholds
+        .iter()
+        .map(|endpoint_threshold| {
+            let observed = observe(
+                all_results,
+                &endpoint_threshold.endpoint_type_name,
+                endpoint_threshold.metric,
+            );
+            EndpointThresholdResult {
+                endpoint_threshold: endpoint_threshold.clone(),
+                observed,
+                passed: observed
+                    .map(|observed| observed <= endpoint_threshold.max)
+                    .unwrap_or(false),
+            }
+        })
+        .collect()
+}
+
+/// The test is stopped once the metric of the endpoint is above `max`, checked every update interval.
+/// Without an endpoint the condition applies to the aggregated results.
+/// Given as `[{type} {name} ]{metric}>{max}` on the command line, e.g. `failure_rate>0.1`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StopCondition {
+    pub endpoint_type_name: Option<EndpointTypeName>,
+    pub metric: ResultsMetric,
+    pub max: f64,
+}
+
+impl StopCondition {
+    // the observed value if the condition is met
+    pub(crate) fn evaluate(&self, all_results: &AllResults) -> Option<f64> {
+        observe(all_results, &self.endpoint_type_name, self.metric)
+            .filter(|observed| *observed > self.max)
+    }
+}
+
+impl FromStr for StopCondition {
+    type Err = ThresholdParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (endpoint_type_name, metric, max) = parse(s, STOP_CONDITION_SEPARATOR)?;
+        Ok(Self {
+            endpoint_type_name,
+            metric,
+            max,
+        })
+    }
+}
+
+impl Display for StopCondition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt_target(f, &self.endpoint_type_name, self.metric)?;
+        write!(f, "{}{}", STOP_CONDITION_SEPARATOR, self.max)
+    }
+}
+
+/// The stop condition that stopped the test and the value it was met with.
+#[derive(Debug, Clone)]
+pub struct StopConditionMet {
+    pub stop_condition: StopCondition,
+    pub observed: f64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::results::ResponseInfo;
+    use std::time::Duration;
+
+    fn endpoint_type_name(name: &str) -> EndpointTypeName {
+        EndpointTypeName {
+            r#type: String::from("GET"),
+            name: String::from(name),
+        }
+    }
+
+    fn all_results() -> AllResults {
+        let mut all_results = AllResults::with_percentiles();
+        for response_time in [0.1, 0.2, 0.3] {
+            all_results.add_success(
+                &endpoint_type_name("/items"),
+                None,
+                response_time,
+                &ResponseInfo::default(),
+            );
+        }
+        all_results.add_failure(
+            &endpoint_type_name("/cart"),
+            None,
+            None,
+            &ResponseInfo::default(),
+        );
+        all_results.calculate_on_update_interval(&Duration::from_secs(1));
+        all_results
+    }
+
+    #[test]
+    fn thresholds_are_parsed_with_and_without_endpoint() {
+        let endpoint_threshold: EndpointThreshold =
+            "GET /items, all p95_response_time <= 0.5".parse().unwrap();
+
+        assert_eq!(
+            endpoint_threshold,
+            EndpointThreshold {
+                endpoint_type_name: Some(endpoint_type_name("/items, all")),
+                metric: ResultsMetric::Percentile95ResponseTime,
+                max: 0.5,
+            }
+        );
+        assert_eq!(
+            endpoint_threshold.to_string(),
+            "GET /items, all p95_response_time<=0.5"
+        );
+
+        let stop_condition: StopCondition = "failure_rate>0.1".parse().unwrap();
+        assert_eq!(stop_condition.endpoint_type_name, None);
+        assert_eq!(stop_condition.metric, ResultsMetric::FailureRate);
+        assert_eq!(stop_condition.to_string(), "failure_rate>0.1");
+    }
+
+    #[test]
+    fn invalid_thresholds_are_rejected() {
+        assert!(matches!(
+            "failure_rate>0.1".parse::<EndpointThreshold>(),
+            Err(ThresholdParseError::MissingSeparator("<="))
+        ));
+        assert!(matches!(
+            "/items failure_rate<=0.1".parse::<EndpointThreshold>(),
+            Err(ThresholdParseError::MissingEndpointName)
+        ));
+        assert!(matches!(
+            "GET /items p42<=0.1".parse::<EndpointThreshold>(),
+            Err(ThresholdParseError::UnknownMetric(_))
+        ));
+        assert!(matches!(
+            "failure_rate<=high".parse::<EndpointThreshold>(),
+            Err(ThresholdParseError::InvalidValue(_))
+        ));
+    }
+
+    #[test]
+    fn thresholds_are_evaluated_against_the_results() {
+        let endpoint_thresholds: Vec<EndpointThreshold> = [
+            "GET /items avg_response_time<=0.25",
+            "GET /items max_response_time<=0.25",
+            "GET /cart failure_rate<=0.5",
+            "GET /never failure_rate<=1",
+            "failure_rate<=0.25",
+        ]
+        .iter()
+        .map(|s| s.parse().unwrap())
+        .collect();
+
+        let results: Vec<(Option<f64>, bool)> =
+            evaluate_endpoint_thresholds(&all_results(), &endpoint_thresholds)
+                .into_iter()
+                .map(|result| (result.observed, result.passed))
+                .collect();
+
+        assert!((results[0].0.unwrap() - 0.2).abs() < 1e-9);
+        assert!(results[0].1);
+        assert_eq!(results[1], (Some(0.3), false));
+        assert_eq!(results[2], (Some(1.0), false));
+        // an endpoint without results fails its threshold
+        assert_eq!(results[3], (None, false));
+        assert_eq!(results[4], (Some(0.25), true));
+    }
+
+    #[test]
+    fn stop_condition_is_met_above_the_max() {
+        let all_results = all_results();
+
+        let met: StopCondition = "GET /cart failure_rate>0.5".parse().unwrap();
+        let not_met: StopCondition = "failure_rate>0.25".parse().unwrap();
+        let no_results: StopCondition = "GET /never failure_rate>0".parse().unwrap();
+
+        assert_eq!(met.evaluate(&all_results), Some(1.0));
+        assert_eq!(not_met.evaluate(&all_results), None);
+        assert_eq!(no_results.evaluate(&all_results), None);
+    }
+}
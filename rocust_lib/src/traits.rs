@@ -1,6 +1,14 @@
-use crate::{tasks::AsyncTask, test::config::TestConfig, test::user::context::Context};
+use crate::{
+    tasks::AsyncTask,
+    test::config::TestConfig,
+    test::user::{context::Context, RestartPolicy},
+};
 use async_trait::async_trait;
 use rand::{distributions::WeightedIndex, prelude::Distribution};
+use std::error::Error as StdError;
+
+// returned by User::new and User::on_start. Users can use any error type with the ? operator
+pub type StartError = Box<dyn StdError + Send + Sync>;
 
 pub trait HasTask: Sized + 'static {
     fn get_async_tasks() -> Vec<AsyncTask<Self>> {
@@ -16,15 +24,30 @@ pub trait HasTask: Sized + 'static {
     fn get_weight() -> u64 {
         1
     }
+
+    // overrides the task timeout of the test config for all tasks of this user type
+    fn get_task_timeout_in_millis() -> Option<u64> {
+        None
+    }
 }
 
 #[async_trait]
 pub trait User: Send + Sized + 'static {
     type Shared: Shared;
 
-    async fn new(_test_config: &TestConfig, _context: &Context, _shared: Self::Shared) -> Self;
-    async fn on_start(&mut self, _context: &Context) {}
+    async fn new(
+        _test_config: &TestConfig,
+        _context: &Context,
+        _shared: Self::Shared,
+    ) -> Result<Self, StartError>;
+    async fn on_start(&mut self, _context: &Context) -> Result<(), StartError> {
+        Ok(())
+    }
     async fn on_stop(&mut self, _context: &Context) {}
+
+    fn get_restart_policy() -> RestartPolicy {
+        RestartPolicy::Never
+    }
 }
 
 #[async_trait]
@@ -17,3 +17,19 @@ pub(crate) fn get_timestamp_as_millis_as_string() -> Result<String, SystemTimeEr
 pub(crate) fn get_extension_from_filename(filename: &Path) -> Option<&str> {
     filename.extension().and_then(OsStr::to_str)
 }
+
+// for html and xml text and attribute values
+pub(crate) fn escape_xml(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
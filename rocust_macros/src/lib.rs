@@ -11,6 +11,7 @@ pub fn has_task(attrs: TokenStream, item: TokenStream) -> TokenStream {
     let mut min_sleep = 0;
     let mut max_sleep = 0;
     let mut weight = 1;
+    let mut task_timeout_in_millis = None;
 
     for attr in attrs {
         if let syn::NestedMeta::Meta(syn::Meta::NameValue(name_value)) = attr {
@@ -41,8 +42,19 @@ pub fn has_task(attrs: TokenStream, item: TokenStream) -> TokenStream {
                 } else {
                     panic!("weight has to be an integer");
                 }
+            } else if *name_value.path.get_ident().unwrap() == "task_timeout_in_millis" {
+                if let syn::Lit::Int(lit_str) = name_value.lit {
+                    task_timeout_in_millis = match lit_str.base10_digits().parse::<u64>() {
+                        Ok(val) => Some(val),
+                        Err(_) => panic!("task_timeout_in_millis has to be u64"),
+                    };
+                } else {
+                    panic!("task_timeout_in_millis has to be an integer");
+                }
             } else {
-                panic!("Only min_sleep, max_sleep and weight are supported");
+                panic!(
+                    "Only min_sleep, max_sleep, weight and task_timeout_in_millis are supported"
+                );
             }
         } else {
             panic!("Only Meta is supported");
@@ -56,6 +68,16 @@ pub fn has_task(attrs: TokenStream, item: TokenStream) -> TokenStream {
     let min_sleep = syn::LitInt::new(&min_sleep.to_string(), proc_macro2::Span::call_site());
     let max_sleep = syn::LitInt::new(&max_sleep.to_string(), proc_macro2::Span::call_site());
     let weight = syn::LitInt::new(&weight.to_string(), proc_macro2::Span::call_site());
+    let task_timeout_in_millis = match task_timeout_in_millis {
+        Some(task_timeout_in_millis) => {
+            let task_timeout_in_millis = syn::LitInt::new(
+                &task_timeout_in_millis.to_string(),
+                proc_macro2::Span::call_site(),
+            );
+            quote! { Some(#task_timeout_in_millis) }
+        }
+        None => quote! { None },
+    };
 
     let struct_name = if let syn::Type::Path(type_path) = &impl_block.self_ty.as_ref() {
         if let Some(ident) = type_path.path.get_ident() {
@@ -82,39 +104,13 @@ pub fn has_task(attrs: TokenStream, item: TokenStream) -> TokenStream {
             for attr in task_attrs {
                 let mut token_stream = attr.tokens.clone().into_iter();
                 if let TokenTree::Group(group) = token_stream.next().expect("No group found") {
-                    let tokens = group.stream();
-                    let mut iter = tokens.into_iter();
-
-                    if let TokenTree::Ident(ident) = iter.next().expect("No ident found") {
-                        if ident != "priority" {
-                            panic!("Only priority is supported");
-                        }
-                    } else {
-                        panic!("Only Ident is supported");
-                    };
+                    if method.sig.asyncness.is_none() {
+                        panic!("Only async methods are supported");
+                    }
 
-                    if let TokenTree::Punct(punct) = iter.next().expect("No punct found") {
-                        if punct.as_char() != '=' {
-                            panic!("Only '=' is supported");
-                        }
-                    } else {
-                        panic!("Only Punct is supported");
-                    };
+                    let (priority, tags, timeout_in_millis) = parse_task_args(group.stream());
 
-                    if let TokenTree::Literal(lit) = iter.next().expect("No literal found") {
-                        if let Ok(priority) = lit.to_string().parse::<u64>() {
-                            if method.sig.asyncness.is_none() {
-                                panic!("Only async methods are supported");
-                            }
-                            let priority = syn::LitInt::new(&priority.to_string(), lit.span());
-                            methods.push((method.sig.ident.clone(), priority));
-                        } else {
-                            panic!("Only u64 is supported");
-                        }
-                    } else {
-                        //panic!("Only Literal is supported");
-                        panic!("Only u64 is supported");
-                    };
+                    methods.push((method.sig.ident.clone(), priority, tags, timeout_in_millis));
                 }
             }
 
@@ -123,7 +119,13 @@ pub fn has_task(attrs: TokenStream, item: TokenStream) -> TokenStream {
         }
     }
 
-    let methods = methods.iter().map(|(method_name, priority)| {
+    let methods = methods.iter().map(|(method_name, priority, tags, timeout_in_millis)| {
+        let timeout = match timeout_in_millis {
+            Some(timeout_in_millis) => quote! {
+                .with_timeout(::std::time::Duration::from_millis(#timeout_in_millis))
+            },
+            None => quote! {},
+        };
         let method_name_str = syn::LitStr::new(&method_name.to_string(), proc_macro2::Span::call_site());
         quote! {
             fn #method_name<'a>(u: &'a mut #struct_name, context: &'a rocust::rocust_lib::test::user::context::Context) -> ::core::pin::Pin<Box<dyn ::core::future::Future<Output = ()> + ::core::marker::Send + 'a>> {
@@ -131,7 +133,7 @@ pub fn has_task(attrs: TokenStream, item: TokenStream) -> TokenStream {
                     u.#method_name(context).await;
                 })
             }
-            async_tasks.push(rocust::rocust_lib::tasks::AsyncTask::new(#priority, #method_name_str, #method_name));
+            async_tasks.push(rocust::rocust_lib::tasks::AsyncTask::new(#priority, #method_name_str, #method_name).with_tags(&[#(#tags),*])#timeout);
         }
     });
 
@@ -157,8 +159,130 @@ pub fn has_task(attrs: TokenStream, item: TokenStream) -> TokenStream {
             fn get_weight() -> u64 {
                 #weight
             }
+
+            fn get_task_timeout_in_millis() -> Option<u64> {
+                #task_timeout_in_millis
+            }
         }
     };
 
     expanded.into()
 }
+
+// #[task(priority = 1, tags = ["checkout", "slow"], timeout_in_millis = 5000)]
+fn parse_task_args(
+    tokens: proc_macro2::TokenStream,
+) -> (syn::LitInt, Vec<syn::LitStr>, Option<syn::LitInt>) {
+    let mut iter = tokens.into_iter();
+    let mut priority = None;
+    let mut tags = Vec::new();
+    let mut timeout_in_millis = None;
+
+    while let Some(token) = iter.next() {
+        let ident = if let TokenTree::Ident(ident) = token {
+            ident
+        } else {
+            panic!("Only Ident is supported");
+        };
+
+        if let TokenTree::Punct(punct) = iter.next().expect("No punct found") {
+            if punct.as_char() != '=' {
+                panic!("Only '=' is supported");
+            }
+        } else {
+            panic!("Only Punct is supported");
+        };
+
+        let value = iter.next().expect("No value found");
+        if ident == "priority" {
+            priority = Some(parse_u64_literal(value, "priority"));
+        } else if ident == "timeout_in_millis" {
+            timeout_in_millis = Some(parse_u64_literal(value, "timeout_in_millis"));
+        } else if ident == "tags" {
+            if let TokenTree::Group(group) = value {
+                for token in group.stream() {
+                    match token {
+                        TokenTree::Literal(lit) => {
+                            if let syn::Lit::Str(lit_str) = syn::Lit::new(lit) {
+                                tags.push(lit_str);
+                            } else {
+                                panic!("Tags have to be strings");
+                            }
+                        }
+                        TokenTree::Punct(punct) if punct.as_char() == ',' => {}
+                        _ => panic!("Tags have to be strings"),
+                    }
+                }
+            } else {
+                panic!("Tags have to be an array of strings");
+            };
+        } else {
+            panic!("Only priority, tags and timeout_in_millis are supported");
+        }
+
+        match iter.next() {
+            Some(TokenTree::Punct(punct)) if punct.as_char() == ',' => {}
+            None => break,
+            _ => panic!("Only ',' is supported between arguments"),
+        }
+    }
+
+    let priority = priority.expect("priority is required");
+    (priority, tags, timeout_in_millis)
+}
+
+fn parse_u64_literal(token: TokenTree, name: &str) -> syn::LitInt {
+    if let TokenTree::Literal(lit) = token {
+        if let Ok(value) = lit.to_string().parse::<u64>() {
+            syn::LitInt::new(&value.to_string(), lit.span())
+        } else {
+            panic!("{} has to be u64", name);
+        }
+    } else {
+        panic!("{} has to be u64", name);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn task_args_are_parsed() {
+        let (priority, tags, timeout_in_millis) = parse_task_args(quote! {
+            priority = 3, tags = ["checkout", "slow"], timeout_in_millis = 5000
+        });
+
+        assert_eq!(priority.base10_digits(), "3");
+        let tags: Vec<String> = tags.iter().map(syn::LitStr::value).collect();
+        assert_eq!(tags, vec!["checkout", "slow"]);
+        assert_eq!(timeout_in_millis.unwrap().base10_digits(), "5000");
+    }
+
+    #[test]
+    fn tags_and_timeout_are_optional() {
+        let (priority, tags, timeout_in_millis) = parse_task_args(quote! { priority = 1 });
+
+        assert_eq!(priority.base10_digits(), "1");
+        assert!(tags.is_empty());
+        assert!(timeout_in_millis.is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "priority is required")]
+    fn priority_is_required() {
+        parse_task_args(quote! { tags = ["checkout"] });
+    }
+
+    #[test]
+    #[should_panic(expected = "Tags have to be strings")]
+    fn tags_have_to_be_strings() {
+        parse_task_args(quote! { priority = 1, tags = [1] });
+    }
+
+    #[test]
+    #[should_panic(expected = "timeout_in_millis has to be u64")]
+    fn timeout_has_to_be_u64() {
+        parse_task_args(quote! { priority = 1, timeout_in_millis = -1 });
+    }
+}
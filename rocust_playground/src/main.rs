@@ -1,5 +1,5 @@
 use async_trait::async_trait;
-use rocust::rocust_lib::{traits::HasTask, Context, TestConfig, User};
+use rocust::rocust_lib::{traits::HasTask, Context, StartError, TestConfig, User};
 
 #[allow(dead_code)]
 
@@ -56,8 +56,12 @@ impl HasTask for MyUser {
 #[async_trait]
 impl User for MyUser {
     type Shared = ();
-    async fn new(_test_config: &TestConfig, _context: &Context, _shared: Self::Shared) -> Self {
-        MyUser { id: 0 }
+    async fn new(
+        _test_config: &TestConfig,
+        _context: &Context,
+        _shared: Self::Shared,
+    ) -> Result<Self, StartError> {
+        Ok(MyUser { id: 0 })
     }
 }
 